@@ -0,0 +1,8 @@
+#![no_main]
+
+use bundler::utils::decoder::decode_bundle_bytes;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_bundle_bytes(data);
+});
@@ -0,0 +1,61 @@
+#ifndef BUNDLER_H
+#define BUNDLER_H
+
+/* Generated with cbindgen, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct BundlerEnvelope {
+  const uint8_t *data;
+  size_t data_len;
+  const char *target;
+} BundlerEnvelope;
+
+typedef void (*BundlerEnvelopeCallback)(size_t index,
+                                        const uint8_t *data,
+                                        size_t data_len,
+                                        const char *target,
+                                        void *user_data);
+
+/**
+ * Returns the message of the last error raised on the calling thread, or NULL.
+ * The pointer stays valid until the next bundler call on the same thread.
+ */
+const char *bundler_last_error_message(void);
+
+/**
+ * Builds and propagates a bundle, returning its txid (to be released with
+ * `bundler_string_free`) or NULL on error.
+ *
+ * # Safety
+ * `private_key` must be a NUL terminated string and `envelopes` must point to
+ * `envelopes_len` valid `BundlerEnvelope`s.
+ */
+char *bundler_create_bundle(const char *private_key,
+                            const struct BundlerEnvelope *envelopes,
+                            size_t envelopes_len);
+
+/**
+ * Retrieves a bundle and calls `callback` once per envelope, in bundle order.
+ * Returns 0 on success and -1 on error. Buffers passed to the callback are only valid during the call.
+ *
+ * # Safety
+ * `bundle_txid` must be a NUL terminated string.
+ */
+int32_t bundler_retrieve_bundle(const char *bundle_txid,
+                                BundlerEnvelopeCallback callback,
+                                void *user_data);
+
+/**
+ * Releases a string returned by the bundler.
+ *
+ * # Safety
+ * `s` must have been returned by a bundler function and not freed already.
+ */
+void bundler_string_free(char *s);
+
+#endif  /* BUNDLER_H */
@@ -0,0 +1,1231 @@
+use crate::utils::cancellation::CancellationToken;
+use crate::utils::core::bundle_config::BundleConfig;
+use crate::utils::core::bundle_receipt::BundleReceipt;
+use crate::utils::core::envelope::Envelope;
+use crate::utils::errors::Error;
+use crate::utils::evm::{create_http_client, submit_bundles};
+use crate::utils::inclusion::{inclusion_from, is_confirmed_from, ConfirmationLevel, Inclusion};
+use crate::utils::rate_limit::{RateLimit, TokenBucket};
+use crate::utils::snapshot::{StateSnapshot, TenantSnapshot, TrackedSnapshot};
+use crate::utils::submission_queue::{
+    Priority, QueuedEnvelope, SubmissionQueue, DEFAULT_LOW_PRIORITY_DELAY, DEFAULT_PACKING_TARGET,
+};
+use crate::utils::webhook::{
+    constant_time_eq, LifecycleEvent, LifecycleEventKind, Webhook, WebhookNotifier,
+};
+use {
+    alloy::{
+        primitives::{Address, B256},
+        providers::Provider,
+        signers::local::PrivateKeySigner,
+    },
+    axum::{
+        extract::{self, State},
+        http::{HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        routing::{get, post},
+        Json, Router,
+    },
+    hyper_util::{rt::TokioIo, service::TowerToHyperService},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, HashMap, VecDeque},
+        net::SocketAddr,
+        path::{Path, PathBuf},
+        str::FromStr,
+        sync::atomic::{AtomicBool, AtomicU64, Ordering},
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+    tokio::sync::{Mutex, Notify, RwLock},
+};
+
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+// submissions kept for `GET /status`
+pub const RECENT_SUBMISSIONS: usize = 100;
+// confirmed bundles are checked for reorgs this long before they stop being tracked
+pub const DEFAULT_REORG_WATCH: Duration = Duration::from_secs(600);
+// confirmations of the drained bundles are awaited this long by `shutdown`
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(60);
+// selects the tenant of a control API request, the default signer without it
+pub const TENANT_HEADER: &str = "x-bundler-tenant";
+pub const API_KEY_HEADER: &str = "x-bundler-api-key";
+// the admin key of the daemon wide routes (`/status`, `/flush`, `/drain`, `/shutdown`)
+pub const ADMIN_KEY_HEADER: &str = "x-bundler-admin-key";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TenantQuota {
+    // envelopes waiting in the tenant queue
+    pub max_queued: Option<usize>,
+    // envelopes enqueued, a whole request is rejected when it doesn't fit
+    pub rate: Option<RateLimit>,
+}
+
+impl TenantQuota {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_queued(mut self, envelopes: usize) -> Self {
+        self.max_queued = Some(envelopes);
+        self
+    }
+
+    pub fn rate(mut self, rate: RateLimit) -> Self {
+        self.rate = Some(rate);
+        self
+    }
+}
+
+// an app sharing the daemon with a signer of its own. Its bundles are submitted apart
+// from the other signers ones, with the target, policy, spend guard and receipt store of
+// its bundle config.
+#[derive(Debug, Clone)]
+pub struct TenantConfig {
+    pub id: String,
+    pub private_key: String,
+    pub bundle: BundleConfig,
+    // required in the `x-bundler-api-key` header of the tenant requests when set
+    pub api_key: Option<String>,
+    pub quota: TenantQuota,
+}
+
+impl TenantConfig {
+    pub fn new(id: &str, private_key: String) -> Self {
+        TenantConfig {
+            id: id.to_string(),
+            private_key,
+            bundle: BundleConfig::new(),
+            api_key: None,
+            quota: TenantQuota::new(),
+        }
+    }
+
+    pub fn bundle_config(mut self, config: BundleConfig) -> Self {
+        self.bundle = config;
+        self
+    }
+
+    pub fn api_key(mut self, api_key: String) -> Self {
+        self.api_key = Some(api_key);
+        self
+    }
+
+    pub fn quota(mut self, quota: TenantQuota) -> Self {
+        self.quota = quota;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DaemonConfig {
+    // signs and pays for the bundles of requests without a tenant
+    pub private_key: String,
+    // fees, retries and target of the bundles of the default signer
+    pub bundle: BundleConfig,
+    pub low_priority_delay: Duration,
+    pub packing_target: usize,
+    // how often due bundles are submitted, and submitted ones checked
+    pub flush_interval: Duration,
+    // notified of the lifecycle of every bundle, submitted bundles are only tracked
+    // (confirmations and reorgs) when there is one or a `state_path`
+    pub webhooks: Vec<Webhook>,
+    pub reorg_watch: Duration,
+    // state snapshot written by `shutdown` when work is left, and imported on start
+    pub state_path: Option<PathBuf>,
+    pub drain_timeout: Duration,
+    // tenant ids are unique, the first tenant of an id serves it
+    pub tenants: Vec<TenantConfig>,
+    // required by the daemon wide routes when set. Without one they are only open on a
+    // daemon serving no tenant.
+    pub admin_key: Option<String>,
+}
+
+impl DaemonConfig {
+    pub fn new(private_key: String) -> Self {
+        DaemonConfig {
+            private_key,
+            bundle: BundleConfig::new(),
+            low_priority_delay: DEFAULT_LOW_PRIORITY_DELAY,
+            packing_target: DEFAULT_PACKING_TARGET,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            webhooks: vec![],
+            reorg_watch: DEFAULT_REORG_WATCH,
+            state_path: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            tenants: vec![],
+            admin_key: None,
+        }
+    }
+
+    pub fn bundle_config(mut self, config: BundleConfig) -> Self {
+        self.bundle = config;
+        self
+    }
+
+    pub fn low_priority_delay(mut self, delay: Duration) -> Self {
+        self.low_priority_delay = delay;
+        self
+    }
+
+    pub fn packing_target(mut self, bytes: usize) -> Self {
+        self.packing_target = bytes;
+        self
+    }
+
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    pub fn webhook(mut self, webhook: Webhook) -> Self {
+        self.webhooks.push(webhook);
+        self
+    }
+
+    pub fn reorg_watch(mut self, watch: Duration) -> Self {
+        self.reorg_watch = watch;
+        self
+    }
+
+    pub fn state_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_path = Some(path.into());
+        self
+    }
+
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    pub fn tenant(mut self, tenant: TenantConfig) -> Self {
+        self.tenants.push(tenant);
+        self
+    }
+
+    pub fn admin_key(mut self, admin_key: String) -> Self {
+        self.admin_key = Some(admin_key);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmissionRecord {
+    pub envelope_count: usize,
+    pub txid: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShutdownReport {
+    // bundles submitted by the drain
+    pub submissions: Vec<SubmissionRecord>,
+    // the drain flush failure, its envelopes are left queued
+    pub flush_error: Option<String>,
+    // left in the queues (with the envelopes of the drained bundles that failed), and
+    // submitted but not confirmed once the drain timed out
+    pub queued: usize,
+    pub unconfirmed: usize,
+    // the state snapshot of the unfinished work
+    pub persisted: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TenantStatus {
+    pub queued: usize,
+    // envelopes of the bundles being submitted
+    pub in_flight: usize,
+    pub submitted_bundles: u64,
+    pub failed_bundles: u64,
+    pub enqueued_envelopes: u64,
+    // envelopes rejected by the tenant quota
+    pub throttled_envelopes: u64,
+    pub tracked_bundles: usize,
+    // latest first
+    pub recent: Vec<SubmissionRecord>,
+}
+
+// the counters of the default signer, and of each tenant in `tenants`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub queued: usize,
+    // envelopes of the bundles being submitted
+    pub in_flight: usize,
+    pub submitted_bundles: u64,
+    pub failed_bundles: u64,
+    pub draining: bool,
+    // latest first
+    pub recent: Vec<SubmissionRecord>,
+    // submitted bundles not confirmed yet, or still watched for reorgs
+    #[serde(default)]
+    pub tracked_bundles: usize,
+    // events waiting for their webhook delivery
+    #[serde(default)]
+    pub pending_events: usize,
+    // deliveries given up after their retries
+    #[serde(default)]
+    pub failed_deliveries: u64,
+    // by tenant id
+    #[serde(default)]
+    pub tenants: BTreeMap<String, TenantStatus>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    in_flight: usize,
+    submitted_bundles: u64,
+    failed_bundles: u64,
+    enqueued_envelopes: u64,
+    throttled_envelopes: u64,
+    recent: VecDeque<SubmissionRecord>,
+}
+
+#[derive(Debug, Clone)]
+struct TrackedBundle {
+    envelope_count: usize,
+    inclusion: Option<Inclusion>,
+    confirmed_at: Option<Instant>,
+}
+
+// queue, nonces and counters of a signer. Submissions of a signer are serialized so that
+// its bundles keep consecutive nonces, signers submit independently.
+#[derive(Debug)]
+struct Signer {
+    // `None` for the default signer
+    tenant: Option<String>,
+    private_key: String,
+    bundle: BundleConfig,
+    api_key: Option<String>,
+    max_queued: Option<usize>,
+    rate: Option<TokenBucket>,
+    queue: Mutex<SubmissionQueue>,
+    submitting: Mutex<()>,
+    counters: RwLock<Counters>,
+    tracked: Mutex<HashMap<String, TrackedBundle>>,
+}
+
+impl Signer {
+    fn new(config: &DaemonConfig, tenant: TenantConfig, id: Option<String>) -> Self {
+        let queue = SubmissionQueue::new(tenant.bundle.clone())
+            .low_priority_delay(config.low_priority_delay)
+            .packing_target(config.packing_target);
+        Signer {
+            tenant: id,
+            private_key: tenant.private_key,
+            bundle: tenant.bundle,
+            api_key: tenant.api_key,
+            max_queued: tenant.quota.max_queued,
+            rate: tenant.quota.rate.map(TokenBucket::new),
+            queue: Mutex::new(queue),
+            submitting: Mutex::new(()),
+            counters: RwLock::new(Counters::default()),
+            tracked: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn name(&self) -> String {
+        self.tenant.clone().unwrap_or_else(|| "default".to_string())
+    }
+
+    fn event(
+        &self,
+        kind: LifecycleEventKind,
+        txid: Option<String>,
+        count: usize,
+    ) -> LifecycleEvent {
+        LifecycleEvent::new(kind, txid, count).tenant(self.tenant.clone())
+    }
+
+    async fn status(&self) -> TenantStatus {
+        let queued = self.queue.lock().await.len();
+        let tracked_bundles = self.tracked.lock().await.len();
+        let counters = self.counters.read().await;
+        TenantStatus {
+            queued,
+            in_flight: counters.in_flight,
+            submitted_bundles: counters.submitted_bundles,
+            failed_bundles: counters.failed_bundles,
+            enqueued_envelopes: counters.enqueued_envelopes,
+            throttled_envelopes: counters.throttled_envelopes,
+            tracked_bundles,
+            recent: counters.recent.iter().cloned().collect(),
+        }
+    }
+
+    fn address(&self) -> Result<Address, Error> {
+        let signer: PrivateKeySigner = self
+            .private_key
+            .parse()
+            .map_err(|_| Error::PrivateKeyParsingError)?;
+        Ok(signer.address())
+    }
+
+    async fn pending_nonce(&self) -> Result<u64, Error> {
+        let provider = create_http_client(&self.bundle.target.rpc_url)?;
+        Ok(provider
+            .get_transaction_count(self.address()?)
+            .pending()
+            .await?)
+    }
+
+    async fn unconfirmed(&self) -> usize {
+        let tracked = self.tracked.lock().await;
+        tracked
+            .values()
+            .filter(|b| b.confirmed_at.is_none())
+            .count()
+    }
+
+    async fn tracked_snapshot(&self) -> Vec<TrackedSnapshot> {
+        let tracked = self.tracked.lock().await;
+        let snapshot = |(txid, bundle): (&String, &TrackedBundle)| TrackedSnapshot {
+            txid: txid.clone(),
+            envelope_count: bundle.envelope_count,
+            inclusion: bundle.inclusion.clone(),
+            confirmed: bundle.confirmed_at.is_some(),
+        };
+        tracked.iter().map(snapshot).collect()
+    }
+
+    async fn restore(
+        &self,
+        queue: &[QueuedEnvelope],
+        receipts: &[BundleReceipt],
+        tracked: &[TrackedSnapshot],
+    ) -> Result<(), Error> {
+        self.queue.lock().await.restore(queue.to_vec())?;
+        if let Some(store) = &self.bundle.receipt_store {
+            for receipt in receipts {
+                store.save(receipt)?;
+            }
+        }
+        let mut watched = self.tracked.lock().await;
+        for bundle in tracked {
+            let restored = TrackedBundle {
+                envelope_count: bundle.envelope_count,
+                inclusion: bundle.inclusion.clone(),
+                confirmed_at: bundle.confirmed.then(Instant::now),
+            };
+            watched.insert(bundle.txid.clone(), restored);
+        }
+        Ok(())
+    }
+}
+
+// long-running submitter owning the signer keys and their envelope queues
+#[derive(Debug)]
+pub struct Daemon {
+    config: DaemonConfig,
+    // the default signer, then the tenants in config order
+    signers: Vec<Signer>,
+    draining: AtomicBool,
+    // no submission starts once set, the queues being persisted
+    stopped: AtomicBool,
+    shutdown: CancellationToken,
+    notifier: WebhookNotifier,
+    // delivered in order by `deliver_events`
+    events: Mutex<VecDeque<LifecycleEvent>>,
+    events_ready: Notify,
+    failed_deliveries: AtomicU64,
+}
+
+impl Daemon {
+    // refuses tenants without an API key, anyone could spend their signer otherwise
+    pub fn new(config: DaemonConfig) -> Result<Self, Error> {
+        if let Some(tenant) = config.tenants.iter().find(|t| t.api_key.is_none()) {
+            return Err(Error::TenantKeyNeeded(tenant.id.clone()));
+        }
+        let default =
+            TenantConfig::new("", config.private_key.clone()).bundle_config(config.bundle.clone());
+        let mut signers = vec![Signer::new(&config, default, None)];
+        for tenant in &config.tenants {
+            let id = Some(tenant.id.clone());
+            signers.push(Signer::new(&config, tenant.clone(), id));
+        }
+        Ok(Daemon {
+            notifier: WebhookNotifier::new(config.webhooks.clone()),
+            config,
+            signers,
+            draining: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+            shutdown: CancellationToken::new(),
+            events: Mutex::new(VecDeque::new()),
+            events_ready: Notify::new(),
+            failed_deliveries: AtomicU64::new(0),
+        })
+    }
+
+    // cancelled once the daemon is drained
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    fn tracks_bundles(&self) -> bool {
+        !self.notifier.is_empty() || self.config.state_path.is_some()
+    }
+
+    fn signer(&self, tenant: Option<&str>) -> Result<&Signer, Error> {
+        let Some(id) = tenant else {
+            return Ok(&self.signers[0]);
+        };
+        self.signers
+            .iter()
+            .find(|signer| signer.tenant.as_deref() == Some(id))
+            .ok_or_else(|| Error::UnknownTenant(id.to_string()))
+    }
+
+    // checks the API key of the tenant. The default signer has none, on a daemon with
+    // tenants it takes the admin key instead.
+    pub fn authorize(&self, tenant: Option<&str>, api_key: Option<&str>) -> Result<(), Error> {
+        let signer = self.signer(tenant)?;
+        let Some(expected) = &signer.api_key else {
+            if self.config.tenants.is_empty() {
+                return Ok(());
+            }
+            return self.authorize_admin(api_key);
+        };
+        let provided = api_key.unwrap_or_default().as_bytes();
+        if !constant_time_eq(provided, expected.as_bytes()) {
+            return Err(Error::TenantUnauthorized(signer.name()));
+        }
+        Ok(())
+    }
+
+    // checks the admin key of the daemon wide routes, which act on every tenant
+    pub fn authorize_admin(&self, admin_key: Option<&str>) -> Result<(), Error> {
+        let authorized = match &self.config.admin_key {
+            Some(expected) => constant_time_eq(
+                admin_key.unwrap_or_default().as_bytes(),
+                expected.as_bytes(),
+            ),
+            None => self.config.tenants.is_empty(),
+        };
+        if !authorized {
+            return Err(Error::AdminUnauthorized);
+        }
+        Ok(())
+    }
+
+    // queued for the default signer, see `enqueue_for`
+    pub async fn enqueue(
+        &self,
+        envelopes: Vec<Envelope>,
+        priority: Priority,
+    ) -> Result<usize, Error> {
+        self.enqueue_for(None, envelopes, priority).await
+    }
+
+    // all envelopes are validated and checked against the tenant quota before any is
+    // queued, returns the tenant queue length
+    pub async fn enqueue_for(
+        &self,
+        tenant: Option<&str>,
+        envelopes: Vec<Envelope>,
+        priority: Priority,
+    ) -> Result<usize, Error> {
+        if self.draining.load(Ordering::SeqCst) {
+            return Err(Error::DaemonDraining);
+        }
+        let signer = self.signer(tenant)?;
+        signer.bundle.validate_envelopes(&envelopes)?;
+        let count = envelopes.len();
+        let mut queue = signer.queue.lock().await;
+        let exceeded = match (signer.max_queued, &signer.rate) {
+            (Some(max), _) if queue.len() + count > max => {
+                Some(format!("{max} queued envelopes at most"))
+            }
+            (_, Some(rate)) if !rate.try_acquire(count as u32) => Some(format!(
+                "{} envelopes per second",
+                rate.limit().requests_per_second
+            )),
+            _ => None,
+        };
+        if let Some(reason) = exceeded {
+            signer.counters.write().await.throttled_envelopes += count as u64;
+            return Err(Error::TenantQuotaExceeded(signer.name(), reason));
+        }
+        for envelope in envelopes {
+            queue.enqueue(envelope, priority)?;
+        }
+        signer.counters.write().await.enqueued_envelopes += count as u64;
+        Ok(queue.len())
+    }
+
+    pub async fn status(&self) -> DaemonStatus {
+        let default = self.signers[0].status().await;
+        let mut tenants = BTreeMap::new();
+        for signer in &self.signers[1..] {
+            tenants.insert(signer.name(), signer.status().await);
+        }
+        DaemonStatus {
+            queued: default.queued,
+            in_flight: default.in_flight,
+            submitted_bundles: default.submitted_bundles,
+            failed_bundles: default.failed_bundles,
+            draining: self.draining.load(Ordering::SeqCst),
+            recent: default.recent,
+            tracked_bundles: default.tracked_bundles,
+            pending_events: self.events.lock().await.len(),
+            failed_deliveries: self.failed_deliveries.load(Ordering::SeqCst),
+            tenants,
+        }
+    }
+
+    pub async fn tenant_status(&self, tenant: &str) -> Result<TenantStatus, Error> {
+        Ok(self.signer(Some(tenant))?.status().await)
+    }
+
+    // queued for the webhooks, dropped when there is none
+    pub async fn emit(&self, event: LifecycleEvent) {
+        if self.notifier.is_empty() {
+            return;
+        }
+        self.events.lock().await.push_back(event);
+        self.events_ready.notify_one();
+    }
+
+    // delivers the queued events one at a time so each webhook receives them in order,
+    // returns the number of events delivered
+    pub async fn deliver_events(&self) -> usize {
+        let mut delivered = 0;
+        loop {
+            let Some(event) = self.events.lock().await.pop_front() else {
+                return delivered;
+            };
+            let failures = self
+                .notifier
+                .notify(&event)
+                .await
+                .iter()
+                .filter(|result| result.is_err())
+                .count();
+            self.failed_deliveries
+                .fetch_add(failures as u64, Ordering::SeqCst);
+            delivered += 1;
+        }
+    }
+
+    // polls the tracked bundles of every signer once, see `check_tracked`. A signer whose
+    // RPC node fails doesn't hold the other ones back, the first error is returned after
+    // all of them were checked.
+    pub async fn check_bundles(&self) -> Result<Vec<LifecycleEvent>, Error> {
+        let mut events = vec![];
+        let mut failure = None;
+        for signer in &self.signers {
+            match self.check_tracked(signer).await {
+                Ok(checked) => events.extend(checked),
+                Err(e) => {
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(events),
+        }
+    }
+
+    // included bundles reaching the bundle confirmation level are confirmed, confirmed
+    // ones whose block is no longer canonical are reorged (and tracked again until
+    // confirmed anew), and txs the node forgot about failed. A bundle whose check fails
+    // doesn't hold the other ones back, the first error is returned once the events of
+    // the checked ones were emitted.
+    async fn check_tracked(&self, signer: &Signer) -> Result<Vec<LifecycleEvent>, Error> {
+        let rpc_url = &signer.bundle.target.rpc_url;
+        let level = signer.bundle.confirmation_level;
+        let mut tracked = signer.tracked.lock().await;
+        let mut events = vec![];
+        let mut done = vec![];
+        let mut failure = None;
+
+        for (txid, bundle) in tracked.iter_mut() {
+            let count = bundle.envelope_count;
+            let event = |kind| signer.event(kind, Some(txid.clone()), count);
+            let checked = async {
+                match (&bundle.inclusion, bundle.confirmed_at) {
+                    (Some(inclusion), Some(confirmed_at)) => {
+                        if confirmed_at.elapsed() > self.config.reorg_watch {
+                            done.push(txid.clone());
+                        } else if !is_confirmed_from(rpc_url, inclusion, ConfirmationLevel::Latest)
+                            .await?
+                        {
+                            events.push(
+                                event(LifecycleEventKind::Reorged)
+                                    .block_number(inclusion.block_number),
+                            );
+                            bundle.inclusion = None;
+                            bundle.confirmed_at = None;
+                        }
+                    }
+                    _ => match inclusion_from(rpc_url, txid).await? {
+                        Some(inclusion) => {
+                            if level == ConfirmationLevel::Latest
+                                || is_confirmed_from(rpc_url, &inclusion, level).await?
+                            {
+                                events.push(
+                                    event(LifecycleEventKind::Confirmed)
+                                        .block_number(inclusion.block_number),
+                                );
+                                bundle.confirmed_at = Some(Instant::now());
+                            }
+                            bundle.inclusion = Some(inclusion);
+                        }
+                        None => {
+                            let provider = create_http_client(rpc_url)?;
+                            let known = provider
+                                .get_transaction_by_hash(B256::from_str(txid)?)
+                                .await?;
+                            if known.is_none() {
+                                events.push(
+                                    event(LifecycleEventKind::Failed)
+                                        .error("bundle tx was dropped".to_string()),
+                                );
+                                done.push(txid.clone());
+                            }
+                        }
+                    },
+                }
+                Ok::<(), Error>(())
+            };
+            if let Err(e) = checked.await {
+                failure.get_or_insert(e);
+            }
+        }
+        for txid in done {
+            tracked.remove(&txid);
+        }
+        drop(tracked);
+        for event in &events {
+            self.emit(event.clone()).await;
+        }
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(events),
+        }
+    }
+
+    // submits the bundles due now
+    pub async fn tick(&self) -> Result<Vec<SubmissionRecord>, Error> {
+        self.submit_all(|queue| queue.ready_batches(Instant::now()))
+            .await
+    }
+
+    // submits every queued envelope, due or not
+    pub async fn flush(&self) -> Result<Vec<SubmissionRecord>, Error> {
+        self.submit_all(SubmissionQueue::drain_batches).await
+    }
+
+    // stops accepting envelopes, flushes the queues and shuts the daemon down. The
+    // daemon keeps running, and retrying the queues, when a flush fails.
+    pub async fn drain(&self) -> Result<Vec<SubmissionRecord>, Error> {
+        self.draining.store(true, Ordering::SeqCst);
+        let submissions = self.flush().await?;
+        self.shutdown.cancel();
+        Ok(submissions)
+    }
+
+    // stops accepting envelopes and shuts the daemon down. A drain first flushes the
+    // queues and waits up to `drain_timeout` for the confirmation of the tracked bundles.
+    // Work left (queued envelopes, unconfirmed bundles, undelivered events) is then
+    // written to `state_path` for the next daemon to `resume`, and nothing is submitted
+    // afterwards so the two daemons never send the same envelopes.
+    pub async fn shutdown(&self, drain: bool) -> Result<ShutdownReport, Error> {
+        self.draining.store(true, Ordering::SeqCst);
+        let mut report = ShutdownReport::default();
+        if drain {
+            match self.flush().await {
+                Ok(submissions) => report.submissions = submissions,
+                Err(e) => report.flush_error = Some(e.to_string()),
+            }
+            self.await_confirmations().await;
+            self.deliver_events().await;
+        }
+        self.stopped.store(true, Ordering::SeqCst);
+        // waits for a submission in progress
+        for signer in &self.signers {
+            drop(signer.submitting.lock().await);
+        }
+        for signer in &self.signers {
+            report.queued += signer.queue.lock().await.len();
+            report.unconfirmed += signer.unconfirmed().await;
+        }
+        let pending_events = self.events.lock().await.len();
+        let persisted = match &self.config.state_path {
+            Some(path) if report.queued + report.unconfirmed + pending_events > 0 => {
+                self.export_state(path).await.map(|_| Some(path.clone()))
+            }
+            _ => Ok(None),
+        };
+        self.shutdown.cancel();
+        report.persisted = persisted?;
+        Ok(report)
+    }
+
+    // imports and removes the `state_path` snapshot of a previous daemon, if any
+    pub async fn resume(&self) -> Result<Option<StateSnapshot>, Error> {
+        let Some(path) = &self.config.state_path else {
+            return Ok(None);
+        };
+        if !path.exists() {
+            return Ok(None);
+        }
+        let snapshot = self.import_state(path).await?;
+        std::fs::remove_file(path)?;
+        Ok(Some(snapshot))
+    }
+
+    async fn await_confirmations(&self) {
+        if !self.tracks_bundles() {
+            return;
+        }
+        let deadline = Instant::now() + self.config.drain_timeout;
+        loop {
+            let _ = self.check_bundles().await;
+            let mut unconfirmed = 0;
+            for signer in &self.signers {
+                unconfirmed += signer.unconfirmed().await;
+            }
+            let now = Instant::now();
+            if unconfirmed == 0 || now >= deadline {
+                return;
+            }
+            let poll = self.config.flush_interval.min(deadline - now);
+            tokio::time::sleep(poll).await;
+        }
+    }
+
+    // every signer submits its batches, the first error is returned once all of them
+    // were tried
+    async fn submit_all(
+        &self,
+        take: impl Fn(&mut SubmissionQueue) -> Vec<Vec<Envelope>>,
+    ) -> Result<Vec<SubmissionRecord>, Error> {
+        let mut records = vec![];
+        let mut failure = None;
+        for signer in &self.signers {
+            match self.submit(signer, &take).await {
+                Ok(submitted) => records.extend(submitted),
+                Err(e) => {
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(records),
+        }
+    }
+
+    // batches are taken under the submission lock so they are sent in queue order.
+    // Envelopes of a submission failing before any broadcast, and of its failed bundles,
+    // are queued again ahead of the others.
+    async fn submit(
+        &self,
+        signer: &Signer,
+        take: impl FnOnce(&mut SubmissionQueue) -> Vec<Vec<Envelope>>,
+    ) -> Result<Vec<SubmissionRecord>, Error> {
+        let _submitting = signer.submitting.lock().await;
+        if self.stopped.load(Ordering::SeqCst) {
+            return Ok(vec![]);
+        }
+        let batches = take(&mut *signer.queue.lock().await);
+        if batches.is_empty() {
+            return Ok(vec![]);
+        }
+        let envelope_count = batches.iter().map(Vec::len).sum();
+        signer.counters.write().await.in_flight = envelope_count;
+
+        let results =
+            submit_bundles(batches.clone(), signer.private_key.clone(), &signer.bundle).await;
+        let mut counters = signer.counters.write().await;
+        counters.in_flight = 0;
+        let results = match results {
+            Ok(results) => results,
+            Err(e) => {
+                drop(counters);
+                {
+                    let mut queue = signer.queue.lock().await;
+                    for envelope in batches.into_iter().flatten() {
+                        queue.enqueue(envelope, Priority::High)?;
+                    }
+                }
+                let failed = signer.event(LifecycleEventKind::Failed, None, envelope_count);
+                self.emit(failed.error(e.to_string())).await;
+                return Err(e);
+            }
+        };
+
+        let mut records = vec![];
+        let mut failed = vec![];
+        for (batch, result) in batches.into_iter().zip(results) {
+            let envelope_count = batch.len();
+            let record = match result {
+                Ok(txid) => {
+                    counters.submitted_bundles += 1;
+                    SubmissionRecord {
+                        envelope_count,
+                        txid: Some(txid),
+                        error: None,
+                    }
+                }
+                Err(e) => {
+                    counters.failed_bundles += 1;
+                    failed.extend(batch);
+                    SubmissionRecord {
+                        envelope_count,
+                        txid: None,
+                        error: Some(e.to_string()),
+                    }
+                }
+            };
+            counters.recent.push_front(record.clone());
+            counters.recent.truncate(RECENT_SUBMISSIONS);
+            records.push(record);
+        }
+        drop(counters);
+        if !failed.is_empty() {
+            let mut queue = signer.queue.lock().await;
+            for envelope in failed {
+                queue.enqueue(envelope, Priority::High)?;
+            }
+        }
+        for record in &records {
+            self.record_event(signer, record).await;
+        }
+        Ok(records)
+    }
+
+    // queues, tracked bundles, receipts of the bundle config receipt stores and next
+    // nonces of every signer (a nonce is left out when its RPC node is unreachable), and
+    // the undelivered events. No submission runs while the snapshot is taken.
+    pub async fn export_state(&self, path: impl AsRef<Path>) -> Result<StateSnapshot, Error> {
+        // in signer order, so that concurrent exports and imports can't deadlock
+        let mut submitting = vec![];
+        for signer in &self.signers {
+            submitting.push(signer.submitting.lock().await);
+        }
+        let mut snapshot = StateSnapshot::new();
+        for signer in &self.signers {
+            let queue = signer.queue.lock().await.snapshot();
+            let receipts = match &signer.bundle.receipt_store {
+                Some(store) => store.export()?,
+                None => vec![],
+            };
+            let tracked = signer.tracked_snapshot().await;
+            match &signer.tenant {
+                None => {
+                    snapshot.queue = queue;
+                    snapshot.receipts = receipts;
+                    snapshot.tracked = tracked;
+                }
+                Some(id) => {
+                    let tenant = TenantSnapshot {
+                        queue,
+                        receipts,
+                        tracked,
+                    };
+                    snapshot.tenants.insert(id.clone(), tenant);
+                }
+            }
+            if let Ok(nonce) = signer.pending_nonce().await {
+                snapshot = snapshot.nonce(&signer.address()?.to_string(), nonce);
+            }
+        }
+        snapshot.events = self.events.lock().await.iter().cloned().collect();
+        snapshot.write(path)?;
+        Ok(snapshot)
+    }
+
+    // restores a snapshot of `export_state`, its envelopes being queued after the queued
+    // ones. Nothing is restored when a tenant of the snapshot is unknown, one of its
+    // envelopes is invalid, or an RPC node is behind the snapshot nonce of its signer
+    // (new bundles would replace the in-flight ones).
+    pub async fn import_state(&self, path: impl AsRef<Path>) -> Result<StateSnapshot, Error> {
+        if self.draining.load(Ordering::SeqCst) {
+            return Err(Error::DaemonDraining);
+        }
+        let snapshot = StateSnapshot::read(path)?;
+        let default = &self.signers[0];
+        let mut restored = vec![(
+            default,
+            &snapshot.queue,
+            &snapshot.receipts,
+            &snapshot.tracked,
+        )];
+        for (id, tenant) in &snapshot.tenants {
+            let signer = self.signer(Some(id))?;
+            restored.push((signer, &tenant.queue, &tenant.receipts, &tenant.tracked));
+        }
+        for (signer, queue, ..) in &restored {
+            for (i, queued) in queue.iter().enumerate() {
+                queued
+                    .envelope
+                    .validate_with(&signer.bundle)
+                    .map_err(|e| Error::InvalidEnvelope(i, e))?;
+            }
+            if let Some(expected) = snapshot.next_nonce(&signer.address()?.to_string()) {
+                let nonce = signer.pending_nonce().await?;
+                if nonce < expected {
+                    return Err(Error::SnapshotNonceAhead(expected, nonce));
+                }
+            }
+        }
+
+        // in signer order, so that concurrent exports and imports can't deadlock
+        let mut submitting = vec![];
+        for signer in &self.signers {
+            submitting.push(signer.submitting.lock().await);
+        }
+        for (signer, queue, receipts, tracked) in restored {
+            signer.restore(queue, receipts, tracked).await?;
+        }
+        if !snapshot.events.is_empty() {
+            self.events.lock().await.extend(snapshot.events.clone());
+            self.events_ready.notify_one();
+        }
+        Ok(snapshot)
+    }
+
+    async fn record_event(&self, signer: &Signer, record: &SubmissionRecord) {
+        if !self.tracks_bundles() {
+            return;
+        }
+        let kind = match record.txid {
+            Some(_) => LifecycleEventKind::Submitted,
+            None => LifecycleEventKind::Failed,
+        };
+        let mut event = signer.event(kind, record.txid.clone(), record.envelope_count);
+        event.error = record.error.clone();
+        if let Some(txid) = &record.txid {
+            let tracked = TrackedBundle {
+                envelope_count: record.envelope_count,
+                inclusion: None,
+                confirmed_at: None,
+            };
+            signer.tracked.lock().await.insert(txid.clone(), tracked);
+        }
+        self.emit(event).await;
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnqueueEnvelope {
+    // hex encoded, with or without 0x prefix
+    pub data: String,
+    pub target: Option<String>,
+    // pure data, without target
+    #[serde(default)]
+    pub data_only: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnqueueRequest {
+    pub envelopes: Vec<EnqueueEnvelope>,
+    #[serde(default)]
+    pub priority: Priority,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EnqueueResponse {
+    pub queued: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShutdownRequest {
+    #[serde(default = "default_drain")]
+    pub drain: bool,
+}
+
+fn default_drain() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize)]
+pub struct FlushResponse {
+    pub submissions: Vec<SubmissionRecord>,
+}
+
+pub struct DaemonError(Error);
+
+impl From<Error> for DaemonError {
+    fn from(e: Error) -> Self {
+        DaemonError(e)
+    }
+}
+
+impl IntoResponse for DaemonError {
+    fn into_response(self) -> Response {
+        let status =
+            StatusCode::from_u16(self.0.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let body = serde_json::json!({ "error": self.0.to_string(), "code": self.0.code() });
+        (status, Json(body)).into_response()
+    }
+}
+
+pub fn router(daemon: Arc<Daemon>) -> Router {
+    Router::new()
+        .route("/enqueue", post(post_enqueue))
+        .route("/status", get(get_status))
+        .route("/flush", post(post_flush))
+        .route("/drain", post(post_drain))
+        .route("/shutdown", post(post_shutdown))
+        .route("/tenants/:tenant/status", get(get_tenant_status))
+        .with_state(daemon)
+}
+
+// control API on a TCP address, until the daemon is drained
+pub async fn serve(addr: SocketAddr, config: DaemonConfig) -> Result<(), Error> {
+    let daemon = Arc::new(Daemon::new(config)?);
+    daemon.resume().await?;
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let shutdown = daemon.shutdown_token();
+    let flusher = tokio::spawn(run_flusher(daemon.clone()));
+    let notifier = tokio::spawn(run_notifier(daemon.clone()));
+    let signals = tokio::spawn(shutdown_on_signal(daemon.clone()));
+    let served = axum::serve(listener, router(daemon.clone()))
+        .with_graceful_shutdown(async move { shutdown.cancelled().await })
+        .await;
+    flusher.abort();
+    notifier.abort();
+    signals.abort();
+    daemon.deliver_events().await;
+    Ok(served?)
+}
+
+// control API on a unix socket, removed once the daemon is drained
+pub async fn serve_unix(path: impl AsRef<Path>, config: DaemonConfig) -> Result<(), Error> {
+    let path = path.as_ref();
+    let daemon = Arc::new(Daemon::new(config)?);
+    daemon.resume().await?;
+    let listener = tokio::net::UnixListener::bind(path)?;
+    let shutdown = daemon.shutdown_token();
+    let flusher = tokio::spawn(run_flusher(daemon.clone()));
+    let notifier = tokio::spawn(run_notifier(daemon.clone()));
+    let signals = tokio::spawn(shutdown_on_signal(daemon.clone()));
+    let app = router(daemon.clone());
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => accepted?.0,
+            _ = shutdown.cancelled() => break,
+        };
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            let _ = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+    flusher.abort();
+    notifier.abort();
+    signals.abort();
+    daemon.deliver_events().await;
+    Ok(std::fs::remove_file(path)?)
+}
+
+// failed submissions are kept in the status, the daemon keeps running
+async fn run_flusher(daemon: Arc<Daemon>) {
+    let shutdown = daemon.shutdown_token();
+    while !shutdown.is_cancelled() {
+        tokio::time::sleep(daemon.config.flush_interval).await;
+        let _ = daemon.tick().await;
+        if daemon.tracks_bundles() {
+            let _ = daemon.check_bundles().await;
+        }
+    }
+}
+
+// SIGTERM (Kubernetes, systemd) and ctrl-c drain the daemon, see `Daemon::shutdown`
+async fn shutdown_on_signal(daemon: Arc<Daemon>) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let Ok(mut terminate) = signal(SignalKind::terminate()) else {
+        return;
+    };
+    tokio::select! {
+        _ = terminate.recv() => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    let _ = daemon.shutdown(true).await;
+}
+
+// the events of a drain are delivered once the control API is shut down
+async fn run_notifier(daemon: Arc<Daemon>) {
+    loop {
+        daemon.events_ready.notified().await;
+        daemon.deliver_events().await;
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+async fn post_enqueue(
+    State(daemon): State<Arc<Daemon>>,
+    headers: HeaderMap,
+    Json(request): Json<EnqueueRequest>,
+) -> Result<Json<EnqueueResponse>, DaemonError> {
+    let tenant = header(&headers, TENANT_HEADER);
+    daemon.authorize(tenant, header(&headers, API_KEY_HEADER))?;
+    if request.envelopes.is_empty() {
+        return Err(Error::EnvelopesNeeded.into());
+    }
+    let mut envelopes: Vec<Envelope> = vec![];
+    for envelope in request.envelopes {
+        let data = alloy::hex::decode(&envelope.data).map_err(Error::from)?;
+        let mut input = Envelope::new().data(Some(data)).target(envelope.target);
+        if envelope.data_only {
+            input = input.data_only();
+        }
+        envelopes.push(input);
+    }
+    let queued = daemon
+        .enqueue_for(tenant, envelopes, request.priority)
+        .await?;
+    Ok(Json(EnqueueResponse { queued }))
+}
+
+async fn get_status(
+    State(daemon): State<Arc<Daemon>>,
+    headers: HeaderMap,
+) -> Result<Json<DaemonStatus>, DaemonError> {
+    daemon.authorize_admin(header(&headers, ADMIN_KEY_HEADER))?;
+    Ok(Json(daemon.status().await))
+}
+
+async fn get_tenant_status(
+    State(daemon): State<Arc<Daemon>>,
+    extract::Path(tenant): extract::Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<TenantStatus>, DaemonError> {
+    daemon.authorize(Some(&tenant), header(&headers, API_KEY_HEADER))?;
+    Ok(Json(daemon.tenant_status(&tenant).await?))
+}
+
+async fn post_flush(
+    State(daemon): State<Arc<Daemon>>,
+    headers: HeaderMap,
+) -> Result<Json<FlushResponse>, DaemonError> {
+    daemon.authorize_admin(header(&headers, ADMIN_KEY_HEADER))?;
+    let submissions = daemon.flush().await?;
+    Ok(Json(FlushResponse { submissions }))
+}
+
+async fn post_drain(
+    State(daemon): State<Arc<Daemon>>,
+    headers: HeaderMap,
+) -> Result<Json<FlushResponse>, DaemonError> {
+    daemon.authorize_admin(header(&headers, ADMIN_KEY_HEADER))?;
+    let submissions = daemon.drain().await?;
+    Ok(Json(FlushResponse { submissions }))
+}
+
+// drains by default, `{"drain": false}` persists the queues right away
+async fn post_shutdown(
+    State(daemon): State<Arc<Daemon>>,
+    headers: HeaderMap,
+    request: Option<Json<ShutdownRequest>>,
+) -> Result<Json<ShutdownReport>, DaemonError> {
+    daemon.authorize_admin(header(&headers, ADMIN_KEY_HEADER))?;
+    let drain = request.is_none_or(|Json(request)| request.drain);
+    Ok(Json(daemon.shutdown(drain).await?))
+}
@@ -0,0 +1,176 @@
+use crate::utils::core::bundle::Bundle;
+use crate::utils::core::envelope::Envelope;
+use crate::utils::errors::{EnvelopeError, Error};
+use {
+    std::{
+        cell::RefCell,
+        ffi::{c_char, c_void, CStr, CString},
+        panic::{catch_unwind, AssertUnwindSafe},
+        ptr, slice,
+        sync::OnceLock,
+    },
+    tokio::runtime::Runtime,
+};
+
+#[repr(C)]
+pub struct BundlerEnvelope {
+    pub data: *const u8,
+    pub data_len: usize,
+    // nullable, NUL terminated hex address
+    pub target: *const c_char,
+}
+
+pub type BundlerEnvelopeCallback = extern "C" fn(
+    index: usize,
+    data: *const u8,
+    data_len: usize,
+    target: *const c_char,
+    user_data: *mut c_void,
+);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| Runtime::new().expect("failed to start the bundler runtime"))
+}
+
+fn set_last_error(e: Error) {
+    let message = CString::new(e.to_string()).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+// no panic unwinds across the C boundary, it is reported as the last error instead
+fn catch_panic<T>(f: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let message = match panic.downcast::<String>() {
+            Ok(message) => *message,
+            Err(panic) => panic
+                .downcast::<&str>()
+                .map(|message| message.to_string())
+                .unwrap_or_default(),
+        };
+        Err(Error::Panicked(message))
+    })
+}
+
+unsafe fn read_str(s: *const c_char) -> Result<Option<String>, Error> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map(|s| Some(s.to_string()))
+        .map_err(Error::from)
+}
+
+/// Returns the message of the last error raised on the calling thread, or NULL.
+/// The pointer stays valid until the next bundler call on the same thread.
+#[no_mangle]
+pub extern "C" fn bundler_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map(|message| message.as_ptr())
+            .unwrap_or(ptr::null())
+    })
+}
+
+/// Builds and propagates a bundle, returning its txid (to be released with
+/// `bundler_string_free`) or NULL on error.
+///
+/// # Safety
+/// `private_key` must be a NUL terminated string and `envelopes` must point to
+/// `envelopes_len` valid `BundlerEnvelope`s.
+#[no_mangle]
+pub unsafe extern "C" fn bundler_create_bundle(
+    private_key: *const c_char,
+    envelopes: *const BundlerEnvelope,
+    envelopes_len: usize,
+) -> *mut c_char {
+    let result = catch_panic(|| {
+        let private_key = read_str(private_key)?.ok_or(Error::PrivateKeyNeeded)?;
+        if envelopes.is_null() {
+            return Err(Error::EnvelopesNeeded);
+        }
+
+        let mut inputs: Vec<Envelope> = vec![];
+        for (i, envelope) in slice::from_raw_parts(envelopes, envelopes_len)
+            .iter()
+            .enumerate()
+        {
+            if envelope.data.is_null() {
+                return Err(Error::InvalidEnvelope(i, EnvelopeError::MissingData));
+            }
+            let data = slice::from_raw_parts(envelope.data, envelope.data_len).to_vec();
+            inputs.push(
+                Envelope::new()
+                    .data(Some(data))
+                    .target(read_str(envelope.target)?),
+            );
+        }
+
+        let bundle = Bundle::new()
+            .private_key(private_key)
+            .envelopes(inputs)
+            .build()?;
+        runtime().block_on(bundle.propagate())
+    });
+
+    match result.map(CString::new) {
+        Ok(Ok(txid)) => txid.into_raw(),
+        Ok(Err(e)) => {
+            set_last_error(Error::from(e));
+            ptr::null_mut()
+        }
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Retrieves a bundle and calls `callback` once per envelope, in bundle order.
+/// Returns 0 on success and -1 on error. Buffers passed to the callback are only valid during the call.
+///
+/// # Safety
+/// `bundle_txid` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn bundler_retrieve_bundle(
+    bundle_txid: *const c_char,
+    callback: BundlerEnvelopeCallback,
+    user_data: *mut c_void,
+) -> i32 {
+    let result = catch_panic(|| {
+        let txid = read_str(bundle_txid)?.ok_or(Error::BundleRetrievalProblem)?;
+        let bundle = runtime().block_on(Bundle::retrieve_envelopes(txid))?;
+
+        for (i, envelope) in bundle.envelopes.iter().enumerate() {
+            let data = alloy::hex::decode(&envelope.input)?;
+            let target = CString::new(envelope.to.clone()).unwrap_or_default();
+            callback(i, data.as_ptr(), data.len(), target.as_ptr(), user_data);
+        }
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+/// Releases a string returned by the bundler.
+///
+/// # Safety
+/// `s` must have been returned by a bundler function and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn bundler_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
@@ -0,0 +1,438 @@
+use crate::utils::core::bundle::Bundle;
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::bundle_receipt::BundleReceipt;
+use crate::utils::core::decode_config::DecodeConfig;
+use crate::utils::core::envelope::Envelope;
+use crate::utils::core::retention::unix_now;
+use crate::utils::core::tag::{find_tag, CONTENT_TYPE_TAG};
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use crate::utils::inclusion_proof::{prove_inclusion, InclusionProof};
+use crate::utils::partial_retrieval::{
+    resume_bundle_within, retrieve_bundle_within, ContinuationToken, PartialBundle,
+};
+use crate::utils::receipt_store::{MemoryReceiptStore, ReceiptStore};
+use crate::utils::webhook::constant_time_eq;
+use {
+    alloy::primitives::keccak256,
+    axum::{
+        extract::{Path, Query, State},
+        http::{header, HeaderMap, HeaderValue, StatusCode},
+        response::{IntoResponse, Response},
+        routing::{get, post},
+        Json, Router,
+    },
+    serde::{Deserialize, Serialize},
+    std::{net::SocketAddr, sync::Arc, time::Duration},
+};
+
+// payloads are compressed as they are served, favoring speed over ratio
+pub const PAYLOAD_BROTLI_QUALITY: u32 = 5;
+pub const DEFAULT_PARTIAL_TIMEOUT: Duration = Duration::from_secs(2);
+// the API key of `POST /bundle`
+pub const API_KEY_HEADER: &str = "x-bundler-api-key";
+
+#[derive(Debug, Clone, Default)]
+pub struct GatewayConfig {
+    // signs and pays for bundles posted through `POST /bundle`
+    pub private_key: Option<String>,
+    // required by `POST /bundle`, which is refused to everyone without one
+    pub api_key: Option<String>,
+    // when set, the envelope and bundle routes only serve links signed with it, see
+    // `sign_envelope_link` and `sign_bundle_link`
+    pub link_secret: Option<Vec<u8>>,
+    // where `GET /envelope/:id` looks envelopes up, e.g. the store the bundler saves its
+    // receipts to. Bundles posted or served in full by the gateway are saved to it as
+    // well (without the idempotency key of the bundles read from the chain), to an
+    // in-memory store when there is none.
+    pub receipt_store: Option<Arc<dyn ReceiptStore>>,
+}
+
+#[derive(Debug)]
+struct GatewayState {
+    config: GatewayConfig,
+    receipts: Arc<dyn ReceiptStore>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PartialParams {
+    pub timeout_ms: Option<u64>,
+    pub continuation: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LinkParams {
+    pub expires: Option<u64>,
+    pub signature: Option<String>,
+}
+
+// keccak of the borsh encoded secret, envelope id and expiry (keccak has no length
+// extension, and borsh length-prefixes each field)
+fn link_signature(secret: &[u8], id: &str, expires_at: u64) -> [u8; 32] {
+    let preimage = borsh::to_vec(&(secret, id, expires_at)).unwrap();
+    keccak256(preimage).0
+}
+
+// shareable `GET /envelope/:id` path, valid until `expires_at` (unix seconds)
+pub fn sign_envelope_link(secret: &[u8], id: &str, expires_at: u64) -> String {
+    let signature = alloy::hex::encode(link_signature(secret, id, expires_at));
+    format!("/envelope/{id}?expires={expires_at}&signature={signature}")
+}
+
+pub fn verify_envelope_link(
+    secret: &[u8],
+    id: &str,
+    expires_at: u64,
+    signature: &str,
+) -> Result<(), Error> {
+    check_link(
+        link_signature(secret, id, expires_at),
+        expires_at,
+        signature,
+    )
+}
+
+// the envelope link preimage with a "bundle" field, so that neither link signature is
+// valid for the other
+fn bundle_link_signature(secret: &[u8], txid: &str, expires_at: u64) -> [u8; 32] {
+    let preimage = borsh::to_vec(&(secret, "bundle", txid, expires_at)).unwrap();
+    keccak256(preimage).0
+}
+
+// shareable `GET /bundle/:txid` path, valid until `expires_at` (unix seconds). The same
+// query also opens the `/partial` and `/proof` routes of the bundle.
+pub fn sign_bundle_link(secret: &[u8], txid: &str, expires_at: u64) -> String {
+    let signature = alloy::hex::encode(bundle_link_signature(secret, txid, expires_at));
+    format!("/bundle/{txid}?expires={expires_at}&signature={signature}")
+}
+
+pub fn verify_bundle_link(
+    secret: &[u8],
+    txid: &str,
+    expires_at: u64,
+    signature: &str,
+) -> Result<(), Error> {
+    check_link(
+        bundle_link_signature(secret, txid, expires_at),
+        expires_at,
+        signature,
+    )
+}
+
+fn check_link(expected: [u8; 32], expires_at: u64, signature: &str) -> Result<(), Error> {
+    let signature = alloy::hex::decode(signature).map_err(|_| Error::InvalidLinkSignature)?;
+    if !constant_time_eq(&signature, &expected) {
+        return Err(Error::InvalidLinkSignature);
+    }
+    if unix_now() > expires_at {
+        return Err(Error::LinkExpired(expires_at));
+    }
+    Ok(())
+}
+
+// with a link secret, the expiry and signature of the request query, checked by `verify`
+fn authorize_link(
+    state: &GatewayState,
+    link: LinkParams,
+    verify: impl FnOnce(&[u8], u64, &str) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let Some(secret) = &state.config.link_secret else {
+        return Ok(());
+    };
+    let (Some(expires), Some(signature)) = (link.expires, link.signature) else {
+        return Err(Error::InvalidLinkSignature);
+    };
+    verify(secret, expires, &signature)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    // no range, or one served in full (several ranges, malformed or not in bytes)
+    Full,
+    // inclusive bounds
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+// a single `bytes=start-end`, `bytes=start-` or `bytes=-suffix` range header over a
+// payload of `len` bytes
+pub fn parse_range(range: &str, len: u64) -> ByteRange {
+    let Some(spec) = range.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    let Some((start, end)) = spec.split_once('-').filter(|_| !spec.contains(',')) else {
+        return ByteRange::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+    let last = len.saturating_sub(1);
+    let (start, end) = match (start.parse::<u64>(), end.parse::<u64>()) {
+        (Ok(start), Ok(end)) if start <= end => (start, end.min(last)),
+        (Ok(start), Err(_)) if end.is_empty() => (start, last),
+        (Err(_), Ok(0)) if start.is_empty() => return ByteRange::Unsatisfiable,
+        (Err(_), Ok(suffix)) if start.is_empty() => (len.saturating_sub(suffix), last),
+        _ => return ByteRange::Full,
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start, end)
+}
+
+// `br` (or `*`) listed without a zero quality
+pub fn accepts_brotli(accept_encoding: &str) -> bool {
+    accept_encoding.split(',').any(|coding| {
+        let mut params = coding.split(';');
+        let name = params.next().unwrap_or_default().trim();
+        let quality = params
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok());
+        (name.eq_ignore_ascii_case("br") || name == "*") && quality.is_some_and(|q| q > 0.0)
+    })
+}
+
+// the raw payload, a single byte range of it (never compressed, ranges apply to the
+// identity encoding) or, for clients accepting it, the whole payload brotli compressed
+// when that makes it smaller
+pub fn payload_response(payload: Vec<u8>, content_type: &str, headers: &HeaderMap) -> Response {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let len = payload.len() as u64;
+    let common = [
+        (header::CONTENT_TYPE, content_type),
+        (header::ACCEPT_RANGES, "bytes"),
+        (header::VARY, "accept-encoding"),
+    ];
+    match header(header::RANGE).map_or(ByteRange::Full, |range| parse_range(range, len)) {
+        ByteRange::Partial(start, end) => {
+            let content_range = [(header::CONTENT_RANGE, format!("bytes {start}-{end}/{len}"))];
+            let body = payload[start as usize..=end as usize].to_vec();
+            return (StatusCode::PARTIAL_CONTENT, common, content_range, body).into_response();
+        }
+        ByteRange::Unsatisfiable => {
+            let content_range = [(header::CONTENT_RANGE, format!("bytes */{len}"))];
+            return (StatusCode::RANGE_NOT_SATISFIABLE, common, content_range).into_response();
+        }
+        ByteRange::Full => {}
+    }
+    if header(header::ACCEPT_ENCODING).is_some_and(accepts_brotli) {
+        let compressed =
+            TxEnvelopeWrapper::brotli_compress_with(&payload, PAYLOAD_BROTLI_QUALITY, 22);
+        if compressed.len() < payload.len() {
+            return (common, [(header::CONTENT_ENCODING, "br")], compressed).into_response();
+        }
+    }
+    (common, payload).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostEnvelope {
+    // hex encoded, with or without 0x prefix
+    pub data: String,
+    pub target: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostBundle {
+    pub envelopes: Vec<PostEnvelope>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PostBundleResponse {
+    pub txid: String,
+}
+
+pub struct GatewayError(Error);
+
+impl From<Error> for GatewayError {
+    fn from(e: Error) -> Self {
+        GatewayError(e)
+    }
+}
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        let status =
+            StatusCode::from_u16(self.0.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let body = serde_json::json!({ "error": self.0.to_string(), "code": self.0.code() });
+        (status, Json(body)).into_response()
+    }
+}
+
+pub fn router(config: GatewayConfig) -> Router {
+    let receipts = config
+        .receipt_store
+        .clone()
+        .unwrap_or_else(|| Arc::new(MemoryReceiptStore::new()));
+    let state = Arc::new(GatewayState { config, receipts });
+
+    Router::new()
+        .route("/bundle/:txid", get(get_bundle))
+        .route("/bundle/:txid/proof", get(get_inclusion_proof))
+        .route("/bundle/:txid/partial", get(get_partial_bundle))
+        .route("/bundle", post(post_bundle))
+        .route("/envelope/:id", get(get_envelope))
+        .route("/envelope/:id/payload", get(get_envelope_payload))
+        .with_state(state)
+}
+
+pub async fn serve(addr: SocketAddr, config: GatewayConfig) -> Result<(), Error> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    Ok(axum::serve(listener, router(config)).await?)
+}
+
+// receipt of a bundle read from the chain, which anyone can post to. Its idempotency key
+// is left out, or a bundler sharing the store would take it for its own submission.
+pub(crate) fn indexed_receipt(txid: String, bundle: &BundleData) -> BundleReceipt {
+    BundleReceipt {
+        idempotency_key: None,
+        ..BundleReceipt::new(txid, bundle)
+    }
+}
+
+async fn fetch_and_index(state: &GatewayState, txid: String) -> Result<BundleData, Error> {
+    let bundle = Bundle::retrieve_envelopes(txid.clone()).await?;
+    state.receipts.save(&indexed_receipt(txid, &bundle))?;
+    Ok(bundle)
+}
+
+async fn get_bundle(
+    State(state): State<Arc<GatewayState>>,
+    Path(txid): Path<String>,
+    Query(link): Query<LinkParams>,
+) -> Result<Json<BundleData>, GatewayError> {
+    authorize_link(&state, link, |secret, expires, signature| {
+        verify_bundle_link(secret, &txid, expires, signature)
+    })?;
+    Ok(Json(fetch_and_index(&state, txid).await?))
+}
+
+// the envelopes decoded within `timeout_ms`, and a token for the next request when
+// the bundle was not fully returned
+async fn get_partial_bundle(
+    State(state): State<Arc<GatewayState>>,
+    Path(txid): Path<String>,
+    Query(params): Query<PartialParams>,
+    Query(link): Query<LinkParams>,
+) -> Result<Json<PartialBundle>, GatewayError> {
+    authorize_link(&state, link, |secret, expires, signature| {
+        verify_bundle_link(secret, &txid, expires, signature)
+    })?;
+    let timeout = params
+        .timeout_ms
+        .map_or(DEFAULT_PARTIAL_TIMEOUT, Duration::from_millis);
+    let config = DecodeConfig::default();
+    let partial = match params.continuation {
+        Some(token) => {
+            let token = ContinuationToken::decode(&token)?;
+            if token.txid != txid {
+                return Err(Error::InvalidContinuationToken.into());
+            }
+            resume_bundle_within(&token, timeout, &config).await?
+        }
+        None => retrieve_bundle_within(txid.clone(), timeout, &config).await?,
+    };
+    // receipts list every envelope of their bundle, only a whole bundle is saved
+    if partial.first_envelope == 0 && partial.is_complete() {
+        let bundle = BundleData {
+            envelopes: partial.envelopes.clone(),
+            header: partial.header.clone().unwrap_or_default(),
+        };
+        state.receipts.save(&indexed_receipt(txid, &bundle))?;
+    }
+    Ok(Json(partial))
+}
+
+async fn get_inclusion_proof(
+    State(state): State<Arc<GatewayState>>,
+    Path(txid): Path<String>,
+    Query(link): Query<LinkParams>,
+) -> Result<Json<InclusionProof>, GatewayError> {
+    authorize_link(&state, link, |secret, expires, signature| {
+        verify_bundle_link(secret, &txid, expires, signature)
+    })?;
+    Ok(Json(prove_inclusion(&txid).await?))
+}
+
+async fn find_envelope(
+    state: &GatewayState,
+    id: String,
+    link: LinkParams,
+) -> Result<TxEnvelopeWrapper, Error> {
+    authorize_link(state, link, |secret, expires, signature| {
+        verify_envelope_link(secret, &id, expires, signature)
+    })?;
+    let location = state
+        .receipts
+        .lookup_envelope(&id)?
+        .ok_or(Error::EnvelopeNotFound)?;
+    let bundle = fetch_and_index(state, location.bundle_txid).await?;
+    bundle
+        .envelopes
+        .into_iter()
+        .nth(location.index)
+        .filter(|e| e.hash.eq_ignore_ascii_case(&id))
+        .ok_or(Error::EnvelopeNotFound)
+}
+
+async fn get_envelope(
+    State(state): State<Arc<GatewayState>>,
+    Path(id): Path<String>,
+    Query(link): Query<LinkParams>,
+) -> Result<Json<TxEnvelopeWrapper>, GatewayError> {
+    Ok(Json(find_envelope(&state, id, link).await?))
+}
+
+// the payload bytes, typed by the envelope `Content-Type` tag, for browsers and media
+// players
+async fn get_envelope_payload(
+    State(state): State<Arc<GatewayState>>,
+    Path(id): Path<String>,
+    Query(link): Query<LinkParams>,
+    headers: HeaderMap,
+) -> Result<Response, GatewayError> {
+    let envelope = find_envelope(&state, id, link).await?;
+    let content_type = find_tag(&envelope.tags, CONTENT_TYPE_TAG)
+        .filter(|content_type| HeaderValue::from_str(content_type).is_ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    let payload = envelope.payload()?.to_vec();
+    Ok(payload_response(payload, &content_type, &headers))
+}
+
+async fn post_bundle(
+    State(state): State<Arc<GatewayState>>,
+    headers: HeaderMap,
+    Json(request): Json<PostBundle>,
+) -> Result<Json<PostBundleResponse>, GatewayError> {
+    let api_key = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let authorized = state
+        .config
+        .api_key
+        .as_ref()
+        .is_some_and(|expected| constant_time_eq(api_key.as_bytes(), expected.as_bytes()));
+    if !authorized {
+        return Err(Error::GatewayUnauthorized.into());
+    }
+    let private_key = state
+        .config
+        .private_key
+        .clone()
+        .ok_or(Error::PrivateKeyNeeded)?;
+
+    let mut envelopes: Vec<Envelope> = vec![];
+    for envelope in request.envelopes {
+        let data = alloy::hex::decode(&envelope.data).map_err(Error::from)?;
+        envelopes.push(Envelope::new().data(Some(data)).target(envelope.target));
+    }
+
+    let txid = Bundle::new()
+        .private_key(private_key)
+        .envelopes(envelopes)
+        .receipt_store(state.receipts.clone())
+        .build()?
+        .propagate()
+        .await?;
+    Ok(Json(PostBundleResponse { txid }))
+}
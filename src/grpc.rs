@@ -0,0 +1,172 @@
+use crate::utils::core::bundle::Bundle;
+use crate::utils::core::envelope::Envelope;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::{Error, ErrorKind};
+use crate::utils::scanner::{latest_block_number, watch_bundles, ScannedBundle};
+use {
+    futures::{Stream, TryStreamExt},
+    std::{net::SocketAddr, pin::Pin, time::Duration},
+    tonic::{transport::Server, Code, Request, Response, Status},
+};
+
+pub mod proto {
+    tonic::include_proto!("bundler");
+}
+
+use proto::bundler_server::{Bundler, BundlerServer};
+
+#[derive(Debug, Clone)]
+pub struct GrpcConfig {
+    // signs and pays for bundles submitted through `SubmitBundle`
+    pub private_key: Option<String>,
+    pub poll_interval: Duration,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        GrpcConfig {
+            private_key: None,
+            poll_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BundlerService {
+    config: GrpcConfig,
+}
+
+impl BundlerService {
+    pub fn new(config: GrpcConfig) -> Self {
+        BundlerService { config }
+    }
+}
+
+impl From<Error> for Status {
+    fn from(e: Error) -> Self {
+        let code = match e {
+            Error::GatewayUnauthorized
+            | Error::AdminUnauthorized
+            | Error::TenantUnauthorized(_) => Code::Unauthenticated,
+            Error::PrivateKeyNeeded | Error::InvalidLinkSignature | Error::LinkExpired(_) => {
+                Code::PermissionDenied
+            }
+            Error::TenantQuotaExceeded(..) => Code::ResourceExhausted,
+            _ => match e.kind() {
+                ErrorKind::Validation | ErrorKind::Serialization | ErrorKind::Compression => {
+                    Code::InvalidArgument
+                }
+                ErrorKind::NotFound => Code::NotFound,
+                ErrorKind::Rpc | ErrorKind::Broadcast | ErrorKind::Unavailable => Code::Unavailable,
+                ErrorKind::Cancelled => Code::Cancelled,
+                ErrorKind::Signing | ErrorKind::Storage | ErrorKind::Other => Code::Internal,
+            },
+        };
+        let mut status = Status::new(code, e.to_string());
+        // see `Error::code`
+        status
+            .metadata_mut()
+            .insert("bundler-error-code", e.code().parse().unwrap());
+        status
+    }
+}
+
+impl From<TxEnvelopeWrapper> for proto::TxEnvelope {
+    fn from(envelope: TxEnvelopeWrapper) -> Self {
+        proto::TxEnvelope {
+            chain_id: envelope.chain_id,
+            nonce: envelope.nonce,
+            gas_price: envelope.gas_price.to_string(),
+            gas_limit: envelope.gas_limit,
+            to: envelope.to,
+            value: envelope.value,
+            input: alloy::hex::decode(&envelope.input).unwrap_or_default(),
+            hash: envelope.hash,
+            signature: Some(proto::EnvelopeSignature {
+                y_parity: envelope.signature.y_parity,
+                r: envelope.signature.r,
+                s: envelope.signature.s,
+            }),
+        }
+    }
+}
+
+impl From<ScannedBundle> for proto::BundleEvent {
+    fn from(scanned: ScannedBundle) -> Self {
+        proto::BundleEvent {
+            txid: scanned.txid,
+            block_number: scanned.block_number,
+            sender: scanned.sender,
+            envelopes: scanned
+                .bundle
+                .envelopes
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        }
+    }
+}
+
+type BundleEventStream = Pin<Box<dyn Stream<Item = Result<proto::BundleEvent, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Bundler for BundlerService {
+    async fn submit_bundle(
+        &self,
+        request: Request<proto::SubmitBundleRequest>,
+    ) -> Result<Response<proto::SubmitBundleResponse>, Status> {
+        let private_key = self
+            .config
+            .private_key
+            .clone()
+            .ok_or(Error::PrivateKeyNeeded)?;
+        let envelopes: Vec<Envelope> = request
+            .into_inner()
+            .envelopes
+            .into_iter()
+            .map(|e| Envelope::new().data(Some(e.data)).target(e.target))
+            .collect();
+
+        let txid = Bundle::new()
+            .private_key(private_key)
+            .envelopes(envelopes)
+            .build()?
+            .propagate()
+            .await?;
+        Ok(Response::new(proto::SubmitBundleResponse { txid }))
+    }
+
+    async fn get_bundle(
+        &self,
+        request: Request<proto::GetBundleRequest>,
+    ) -> Result<Response<proto::GetBundleResponse>, Status> {
+        let bundle = Bundle::retrieve_envelopes(request.into_inner().txid).await?;
+        Ok(Response::new(proto::GetBundleResponse {
+            envelopes: bundle.envelopes.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    type StreamBundlesStream = BundleEventStream;
+
+    async fn stream_bundles(
+        &self,
+        request: Request<proto::StreamBundlesRequest>,
+    ) -> Result<Response<Self::StreamBundlesStream>, Status> {
+        let from_block = match request.into_inner().from_block {
+            Some(block) => block,
+            None => latest_block_number().await?,
+        };
+        let stream = watch_bundles(from_block, self.config.poll_interval)
+            .map_ok(proto::BundleEvent::from)
+            .map_err(Status::from);
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+pub async fn serve(addr: SocketAddr, config: GrpcConfig) -> Result<(), Error> {
+    Server::builder()
+        .add_service(BundlerServer::new(BundlerService::new(config)))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
@@ -1,6 +1,92 @@
 pub mod utils;
 
+#[cfg(feature = "daemon")]
 #[tokio::main]
-async fn main() {
+async fn main() -> Result<(), bundler::utils::errors::Error> {
+    use bundler::daemon::{serve, serve_unix, DaemonConfig};
+    use bundler::utils::errors::Error;
+    use bundler::utils::replay::{set_rpc_replay, RpcReplay};
+    use utils::env_var::get_env_key;
+
+    if std::env::args().nth(1).as_deref() != Some("daemon") {
+        println!("hello world from WeaveVM Bundler");
+        return Ok(());
+    }
+    let private_key = match get_env_key("BUNDLER_PRIVATE_KEY".to_string()) {
+        Ok(private_key) => private_key,
+        // or derived from a seed phrase, see `bundler::utils::signer`
+        #[cfg(feature = "mnemonic")]
+        Err(_) => {
+            use bundler::utils::signer::{from_mnemonic, DEFAULT_DERIVATION_PATH};
+            let phrase =
+                get_env_key("BUNDLER_MNEMONIC".to_string()).map_err(|_| Error::PrivateKeyNeeded)?;
+            let path = get_env_key("BUNDLER_DERIVATION_PATH".to_string())
+                .unwrap_or_else(|_| DEFAULT_DERIVATION_PATH.to_string());
+            from_mnemonic(&phrase, &path)?
+        }
+        #[cfg(not(feature = "mnemonic"))]
+        Err(_) => return Err(Error::PrivateKeyNeeded),
+    };
+    let mut config = DaemonConfig::new(private_key);
+    if let Ok(url) = get_env_key("BUNDLER_WEBHOOK_URL".to_string()) {
+        let mut webhook = bundler::utils::webhook::Webhook::new(&url);
+        if let Ok(secret) = get_env_key("BUNDLER_WEBHOOK_SECRET".to_string()) {
+            webhook = webhook.secret(secret.into_bytes());
+        }
+        config = config.webhook(webhook);
+    }
+    // unfinished work of a shutdown, resumed by the next daemon
+    if let Ok(path) = get_env_key("BUNDLER_STATE_PATH".to_string()) {
+        config = config.state_path(path);
+    }
+    if let Some(secs) = get_env_key("BUNDLER_DRAIN_TIMEOUT_SECS".to_string())
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+    {
+        config = config.drain_timeout(std::time::Duration::from_secs(secs));
+    }
+    // required by `/status`, `/flush`, `/drain` and `/shutdown` of a daemon with tenants
+    if let Ok(admin_key) = get_env_key("BUNDLER_ADMIN_KEY".to_string()) {
+        config = config.admin_key(admin_key);
+    }
+    // apps with a signer of their own, e.g. `BUNDLER_TENANTS=billing,search` and
+    // `BUNDLER_TENANT_BILLING_PRIVATE_KEY`
+    if let Ok(tenants) = get_env_key("BUNDLER_TENANTS".to_string()) {
+        use bundler::daemon::{TenantConfig, TenantQuota};
+        for id in tenants
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+        {
+            let var = |name: &str| {
+                let prefix = id.to_uppercase().replace('-', "_");
+                get_env_key(format!("BUNDLER_TENANT_{prefix}_{name}")).ok()
+            };
+            let private_key = var("PRIVATE_KEY").ok_or(Error::PrivateKeyNeeded)?;
+            let api_key = var("API_KEY").ok_or_else(|| Error::TenantKeyNeeded(id.to_string()))?;
+            let mut tenant = TenantConfig::new(id, private_key).api_key(api_key);
+            if let Some(max_queued) = var("MAX_QUEUED").and_then(|max| max.parse().ok()) {
+                tenant = tenant.quota(TenantQuota::new().max_queued(max_queued));
+            }
+            config = config.tenant(tenant);
+        }
+    }
+    // recordings to reproduce reported failures offline, see `bundler::utils::replay`
+    if let Ok(path) = get_env_key("BUNDLER_RPC_RECORD".to_string()) {
+        set_rpc_replay(Some(RpcReplay::record(path)?));
+    } else if let Ok(path) = get_env_key("BUNDLER_RPC_REPLAY".to_string()) {
+        set_rpc_replay(Some(RpcReplay::replay(path)?));
+    }
+    // a socket address, or the path of a unix socket
+    let listen =
+        get_env_key("BUNDLER_LISTEN".to_string()).unwrap_or_else(|_| "127.0.0.1:8090".to_string());
+    match listen.parse() {
+        Ok(addr) => serve(addr, config).await,
+        Err(_) => serve_unix(listen, config).await,
+    }
+}
+
+#[cfg(not(feature = "daemon"))]
+fn main() {
     println!("hello world from WeaveVM Bundler")
 }
@@ -0,0 +1,150 @@
+use crate::utils::batcher::DEFAULT_BATCH_MAX_BYTES;
+use crate::utils::core::retention::unix_now;
+use crate::utils::errors::Error;
+use crate::utils::evm::create_http_client;
+use alloy::eips::BlockNumberOrTag;
+use alloy::providers::Provider;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const DEFAULT_TUNING_REFRESH: Duration = Duration::from_secs(12);
+
+// base fee and fullness of the recent blocks
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChainConditions {
+    // next block base fee, in wei
+    pub base_fee: u128,
+    // mean gas used ratio of the blocks, between 0 and 1
+    pub fullness: f64,
+}
+
+// fee history of the last `block_count` blocks
+pub async fn chain_conditions(rpc_url: &str, block_count: u64) -> Result<ChainConditions, Error> {
+    let provider = create_http_client(rpc_url)?;
+    let history = provider
+        .get_fee_history(block_count.max(1), BlockNumberOrTag::Latest, &[])
+        .await?;
+    let base_fee = history.next_block_base_fee().ok_or(Error::MissingBaseFee)?;
+    let ratios = &history.gas_used_ratio;
+    let fullness = match ratios.len() {
+        0 => 0.0,
+        n => ratios.iter().sum::<f64>() / n as f64,
+    };
+    Ok(ChainConditions { base_fee, fullness })
+}
+
+// a batcher setting picked by `AdaptivePolicy::decide`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchTuning {
+    pub conditions: ChainConditions,
+    // 0 on a cheap and idle chain, 1 with fees at `high_base_fee` or full blocks
+    pub pressure: f64,
+    pub max_bytes: usize,
+    pub max_age: Duration,
+    // unix seconds
+    pub decided_at: u64,
+}
+
+// bundle size and flush age of a `Batcher` following the chain conditions: expensive
+// or congested blocks get bigger bundles flushed less often, so fewer bundle txs pay the
+// base fee, a cheap chain gets smaller bundles flushed sooner
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AdaptivePolicy {
+    // base fees at which the pressure is 0 and 1, in wei
+    pub low_base_fee: u128,
+    pub high_base_fee: u128,
+    // block fullness above which a congestion pressure adds up, the 1559 target
+    pub target_fullness: f64,
+    pub min_bytes: usize,
+    pub max_bytes: usize,
+    pub min_age: Duration,
+    pub max_age: Duration,
+    // how often the chain conditions are fetched, over `block_count` blocks
+    pub refresh: Duration,
+    pub block_count: u64,
+}
+
+impl Default for AdaptivePolicy {
+    fn default() -> Self {
+        AdaptivePolicy {
+            low_base_fee: 1_000_000_000,
+            high_base_fee: 50_000_000_000,
+            target_fullness: 0.5,
+            min_bytes: 1_000_000,
+            max_bytes: DEFAULT_BATCH_MAX_BYTES,
+            min_age: Duration::from_secs(5),
+            max_age: Duration::from_secs(120),
+            refresh: DEFAULT_TUNING_REFRESH,
+            block_count: 10,
+        }
+    }
+}
+
+impl AdaptivePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn base_fees(mut self, low: u128, high: u128) -> Self {
+        self.low_base_fee = low;
+        self.high_base_fee = high;
+        self
+    }
+
+    pub fn target_fullness(mut self, fullness: f64) -> Self {
+        self.target_fullness = fullness;
+        self
+    }
+
+    pub fn bytes(mut self, min: usize, max: usize) -> Self {
+        self.min_bytes = min;
+        self.max_bytes = max;
+        self
+    }
+
+    pub fn ages(mut self, min: Duration, max: Duration) -> Self {
+        self.min_age = min;
+        self.max_age = max;
+        self
+    }
+
+    pub fn refresh(mut self, refresh: Duration) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    pub fn block_count(mut self, block_count: u64) -> Self {
+        self.block_count = block_count;
+        self
+    }
+
+    // the highest of the fee and congestion pressures, interpolated between the min and
+    // max bytes and ages
+    pub fn decide(&self, conditions: ChainConditions) -> BatchTuning {
+        let fee = ratio(
+            conditions.base_fee as f64,
+            self.low_base_fee as f64,
+            self.high_base_fee as f64,
+        );
+        let congestion = ratio(conditions.fullness, self.target_fullness, 1.0);
+        let pressure = fee.max(congestion);
+        let lerp = |min: f64, max: f64| min + (max - min) * pressure;
+        let max_bytes = lerp(self.min_bytes as f64, self.max_bytes as f64).round() as usize;
+        let max_age = lerp(self.min_age.as_secs_f64(), self.max_age.as_secs_f64());
+        BatchTuning {
+            conditions,
+            pressure,
+            max_bytes,
+            max_age: Duration::from_secs_f64(max_age.max(0.0)),
+            decided_at: unix_now(),
+        }
+    }
+}
+
+// position of `value` between `low` and `high`, clamped to 0..=1
+fn ratio(value: f64, low: f64, high: f64) -> f64 {
+    if high <= low {
+        return if value >= high { 1.0 } else { 0.0 };
+    }
+    ((value - low) / (high - low)).clamp(0.0, 1.0)
+}
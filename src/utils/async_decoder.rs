@@ -0,0 +1,311 @@
+use crate::utils::constants::{
+    BUNDLE_FORMAT_V1, BUNDLE_MAGIC, ENVELOPE_FORMAT_V1, ENVELOPE_FORMAT_VERSION,
+};
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::bundle_header::{check_version, BundleHeader};
+use crate::utils::core::bundle_item::{BundleItem, CHECKSUM_SIZE};
+use crate::utils::core::compression::Compression;
+use crate::utils::core::decode_config::DecodeConfig;
+use crate::utils::core::dictionary::Dictionary;
+use crate::utils::core::extension::Extension;
+use crate::utils::core::policy::BundlePolicy;
+use crate::utils::core::tag::Tag;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use base64::Engine;
+use borsh::BorshDeserialize;
+use futures::io::{AsyncRead, AsyncReadExt};
+use futures::stream::{self, Stream};
+
+pub use crate::utils::decoder::CalldataEncoding;
+
+struct Source<R> {
+    reader: R,
+    encoding: CalldataEncoding,
+    // bytes read ahead while sniffing the 0x prefix and the bundle magic
+    pending: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> Source<R> {
+    async fn fill(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let n = self.reader.read(&mut buf[filled..]).await?;
+            if n == 0 {
+                break;
+            }
+            filled += n;
+        }
+        Ok(filled)
+    }
+
+    // reads up to `len` decoded bytes, fewer only at the end of the input
+    async fn read_up_to(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::with_capacity(len.min(65_536));
+        let from_pending = len.min(self.pending.len());
+        out.extend(self.pending.drain(..from_pending));
+
+        let mut chunk = vec![0u8; 65_536];
+        while out.len() < len {
+            let wanted = (len - out.len()).min(32_768);
+            match self.encoding {
+                CalldataEncoding::Raw => {
+                    let n = self.fill(&mut chunk[..wanted]).await?;
+                    out.extend_from_slice(&chunk[..n]);
+                    if n < wanted {
+                        break;
+                    }
+                }
+                CalldataEncoding::Hex => {
+                    let n = self.fill(&mut chunk[..wanted * 2]).await?;
+                    if n % 2 != 0 {
+                        return Err(Error::InvalidBundleHeader);
+                    }
+                    out.extend(alloy::hex::decode(&chunk[..n])?);
+                    if n < wanted * 2 {
+                        break;
+                    }
+                }
+                CalldataEncoding::Base64 => {
+                    let encoded_len = wanted.div_ceil(3) * 4;
+                    let n = self.fill(&mut chunk[..encoded_len]).await?;
+                    let mut decoded =
+                        base64::engine::general_purpose::STANDARD.decode(&chunk[..n])?;
+                    // a 4 chars group may decode past `len`, the rest is kept for later
+                    if decoded.len() > len - out.len() {
+                        self.pending = decoded.split_off(len - out.len());
+                    }
+                    out.extend(decoded);
+                    if n < encoded_len {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    async fn read_exact(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        let bytes = self.read_up_to(len).await?;
+        if bytes.len() != len {
+            return Err(Error::DeserializationError(
+                std::io::ErrorKind::UnexpectedEof.into(),
+            ));
+        }
+        Ok(bytes)
+    }
+
+    async fn read_u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.read_exact(4).await?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // length prefixed field, bounded by `max_len` before anything is buffered
+    async fn read_vec(&mut self, max_len: usize) -> Result<Vec<u8>, Error> {
+        let len = self.read_u32().await? as usize;
+        if len > max_len {
+            return Err(Error::DecompressedSizeExceeded(max_len));
+        }
+        self.read_exact(len).await
+    }
+
+    async fn read_string(&mut self, max_len: usize) -> Result<String, Error> {
+        String::from_utf8(self.read_vec(max_len).await?).map_err(|e| {
+            Error::DeserializationError(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })
+    }
+
+    async fn read_to_end(&mut self, max_len: usize) -> Result<Vec<u8>, Error> {
+        let bytes = self.read_up_to(max_len.saturating_add(1)).await?;
+        if bytes.len() > max_len {
+            return Err(Error::DecompressedSizeExceeded(max_len));
+        }
+        Ok(bytes)
+    }
+}
+
+enum Stage<R> {
+    Start(Source<R>, DecodeConfig),
+    Decoding(DecodeState<R>),
+    Done,
+}
+
+struct DecodeState<R> {
+    source: Source<R>,
+    config: DecodeConfig,
+    policy: BundlePolicy,
+    dictionary: Option<Dictionary>,
+    // bundle format version, the item layout depends on it
+    version: u8,
+    remaining_items: u32,
+    index: usize,
+    budget: usize,
+    // headerless bundles can only be decoded as a whole
+    legacy: Option<std::vec::IntoIter<TxEnvelopeWrapper>>,
+}
+
+impl<R: AsyncRead + Unpin> DecodeState<R> {
+    async fn start(mut source: Source<R>, config: DecodeConfig) -> Result<Self, Error> {
+        let budget = config.max_decompressed_size;
+        if source.encoding == CalldataEncoding::Hex {
+            let mut prefix = [0u8; 2];
+            let n = source.fill(&mut prefix).await?;
+            if !prefix[..n].eq_ignore_ascii_case(b"0x") {
+                if n % 2 != 0 {
+                    return Err(Error::InvalidBundleHeader);
+                }
+                source.pending = alloy::hex::decode(&prefix[..n])?;
+            }
+        }
+
+        let magic = source.read_up_to(BUNDLE_MAGIC.len()).await?;
+        if magic != BUNDLE_MAGIC {
+            let mut calldata = magic;
+            calldata.extend(source.read_to_end(budget).await?);
+            let bundle = BundleData::decode_calldata_with(&calldata, &config)?;
+            return Ok(DecodeState {
+                source,
+                policy: config.envelope_policy(),
+                config,
+                dictionary: None,
+                version: 0,
+                remaining_items: 0,
+                index: 0,
+                budget,
+                legacy: Some(bundle.envelopes.into_iter()),
+            });
+        }
+
+        let version = source.read_exact(1).await?[0];
+        check_version(version)?;
+        let header = source.read_vec(budget).await?;
+        let header: BundleHeader =
+            borsh::from_slice(&header).map_err(|_| Error::InvalidBundleHeader)?;
+        let dictionary = match &header.dictionary_id {
+            Some(id) => Some(
+                config
+                    .find_dictionary(id)
+                    .cloned()
+                    .ok_or_else(|| Error::UnknownDictionary(id.clone()))?,
+            ),
+            None => None,
+        };
+        let remaining_items = source.read_u32().await?;
+
+        Ok(DecodeState {
+            source,
+            policy: config.envelope_policy(),
+            config,
+            dictionary,
+            version,
+            remaining_items,
+            index: 0,
+            budget,
+            legacy: None,
+        })
+    }
+
+    async fn next_envelope(&mut self) -> Result<Option<TxEnvelopeWrapper>, Error> {
+        let envelope = match &mut self.legacy {
+            Some(envelopes) => match envelopes.next() {
+                Some(envelope) => envelope,
+                None => return Ok(None),
+            },
+            None => {
+                if self.remaining_items == 0 {
+                    return Ok(None);
+                }
+                self.remaining_items -= 1;
+                let item = self.read_item().await?;
+                let (envelope, size) = item
+                    .into_envelope(self.dictionary.as_ref(), self.budget)
+                    .map_err(|e| match e {
+                        Error::DecompressedSizeExceeded(_) => {
+                            Error::DecompressedSizeExceeded(self.config.max_decompressed_size)
+                        }
+                        _ => Error::CorruptedEnvelope(self.index),
+                    })?;
+                self.budget -= size;
+                envelope
+            }
+        };
+        self.policy.check(self.index, &envelope)?;
+        self.index += 1;
+        Ok(Some(envelope))
+    }
+
+    // mirrors the borsh layout of `BundleItem`, or `BundleItemV1`
+    async fn read_item(&mut self) -> Result<BundleItem, Error> {
+        let version = match self.version {
+            BUNDLE_FORMAT_V1 => ENVELOPE_FORMAT_VERSION,
+            _ => self.source.read_exact(1).await?[0],
+        };
+        let compression = self.source.read_exact(1).await?;
+        let compression: Compression =
+            borsh::from_slice(&compression).map_err(|_| Error::CorruptedEnvelope(self.index))?;
+        let data = self.source.read_vec(self.budget).await?;
+        let tag_count = self.source.read_u32().await?;
+        let mut tags = vec![];
+        for _ in 0..tag_count {
+            let name = self.source.read_string(self.budget).await?;
+            let value = self.source.read_string(self.budget).await?;
+            tags.push(Tag::new(name, value));
+        }
+        let (extensions, checksum) = match self.version {
+            BUNDLE_FORMAT_V1 => (vec![], None),
+            _ => {
+                let section = self.source.read_vec(self.budget).await?;
+                let mut section = section.as_slice();
+                let extensions = Vec::<Extension>::deserialize(&mut section)
+                    .map_err(|_| Error::CorruptedEnvelope(self.index))?;
+                // required from version 2 on, as in `BundleItem`
+                let checksum = match section.get(..CHECKSUM_SIZE) {
+                    _ if version <= ENVELOPE_FORMAT_V1 => None,
+                    Some(checksum) => checksum.try_into().ok(),
+                    None => return Err(Error::CorruptedEnvelope(self.index)),
+                };
+                (extensions, checksum)
+            }
+        };
+        Ok(BundleItem {
+            version,
+            compression,
+            data,
+            tags,
+            extensions,
+            checksum,
+        })
+    }
+}
+
+// yields the envelopes of a bundle as its calldata is read, e.g. from an HTTP body
+pub fn decode_envelopes<R>(
+    reader: R,
+    encoding: CalldataEncoding,
+    config: DecodeConfig,
+) -> impl Stream<Item = Result<TxEnvelopeWrapper, Error>>
+where
+    R: AsyncRead + Unpin,
+{
+    let source = Source {
+        reader,
+        encoding,
+        pending: vec![],
+    };
+    stream::unfold(Stage::Start(source, config), |stage| async move {
+        let mut decoder = match stage {
+            Stage::Start(source, config) => match DecodeState::start(source, config).await {
+                Ok(decoder) => decoder,
+                Err(e) => return Some((Err(e), Stage::Done)),
+            },
+            Stage::Decoding(decoder) => decoder,
+            Stage::Done => return None,
+        };
+        match decoder.next_envelope().await {
+            Ok(Some(envelope)) => Some((Ok(envelope), Stage::Decoding(decoder))),
+            Ok(None) => None,
+            // the input can't be resynchronized after an error
+            Err(e) => Some((Err(e), Stage::Done)),
+        }
+    })
+}
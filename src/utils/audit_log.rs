@@ -0,0 +1,362 @@
+use crate::utils::core::retention::unix_now;
+use crate::utils::errors::Error;
+use crate::utils::gas_oracle::GasFees;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    // envelopes signed and encoded into bundle calldata
+    SignEnvelopes,
+    // bundle tx signed without being broadcasted, see `build_bundle_tx`
+    SignBundleTx,
+    // bundle tx signed and sent, records per attempt
+    Broadcast,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::SignEnvelopes => "sign_envelopes",
+            AuditAction::SignBundleTx => "sign_bundle_tx",
+            AuditAction::Broadcast => "broadcast",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    // logged before the bundle tx is sent, nothing is sent if it can't be logged
+    Attempted,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    // unix seconds
+    pub timestamp: u64,
+    pub action: AuditAction,
+    // address of the signing key
+    pub signer: String,
+    // keccak256 of the bundle calldata, empty when the action failed before it was built
+    pub payload_hash: String,
+    pub envelope_count: usize,
+    pub txid: Option<String>,
+    pub chain_id: Option<u64>,
+    pub nonce: Option<u64>,
+    pub gas_limit: Option<u64>,
+    pub fees: Option<GasFees>,
+    // RPC endpoint the tx was sent to
+    pub endpoint: Option<String>,
+    pub outcome: AuditOutcome,
+    // `Error::code` of a failed action
+    pub error_code: Option<String>,
+    pub error: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn new(action: AuditAction, signer: String, payload_hash: String) -> Self {
+        AuditRecord {
+            timestamp: unix_now(),
+            action,
+            signer,
+            payload_hash,
+            envelope_count: 0,
+            txid: None,
+            chain_id: None,
+            nonce: None,
+            gas_limit: None,
+            fees: None,
+            endpoint: None,
+            outcome: AuditOutcome::Succeeded,
+            error_code: None,
+            error: None,
+        }
+    }
+
+    pub fn envelope_count(mut self, count: usize) -> Self {
+        self.envelope_count = count;
+        self
+    }
+
+    pub fn txid(mut self, txid: String) -> Self {
+        self.txid = Some(txid);
+        self
+    }
+
+    pub fn tx(mut self, chain_id: u64, nonce: u64, gas_limit: u64, fees: GasFees) -> Self {
+        self.chain_id = Some(chain_id);
+        self.nonce = Some(nonce);
+        self.gas_limit = Some(gas_limit);
+        self.fees = Some(fees);
+        self
+    }
+
+    pub fn endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    pub fn outcome(mut self, outcome: AuditOutcome) -> Self {
+        self.outcome = outcome;
+        self
+    }
+
+    pub fn failed(mut self, error: &Error) -> Self {
+        self.outcome = AuditOutcome::Failed;
+        self.error_code = Some(error.code().to_string());
+        self.error = Some(error.to_string());
+        self
+    }
+}
+
+// every field set must match, the latest `limit` matching records are returned
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditQuery {
+    pub action: Option<AuditAction>,
+    pub signer: Option<String>,
+    pub txid: Option<String>,
+    pub payload_hash: Option<String>,
+    pub outcome: Option<AuditOutcome>,
+    // unix seconds, inclusive
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn action(mut self, action: AuditAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn signer(mut self, signer: &str) -> Self {
+        self.signer = Some(signer.to_string());
+        self
+    }
+
+    pub fn txid(mut self, txid: &str) -> Self {
+        self.txid = Some(txid.to_string());
+        self
+    }
+
+    pub fn payload_hash(mut self, hash: &str) -> Self {
+        self.payload_hash = Some(hash.to_string());
+        self
+    }
+
+    pub fn outcome(mut self, outcome: AuditOutcome) -> Self {
+        self.outcome = Some(outcome);
+        self
+    }
+
+    pub fn since(mut self, timestamp: u64) -> Self {
+        self.since = Some(timestamp);
+        self
+    }
+
+    pub fn until(mut self, timestamp: u64) -> Self {
+        self.until = Some(timestamp);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    // addresses and hashes are compared case-insensitively
+    pub fn matches(&self, record: &AuditRecord) -> bool {
+        let same = |expected: &Option<String>, actual: Option<&str>| match expected {
+            Some(expected) => actual.is_some_and(|a| a.eq_ignore_ascii_case(expected)),
+            None => true,
+        };
+        self.action.is_none_or(|action| action == record.action)
+            && same(&self.signer, Some(&record.signer))
+            && same(&self.txid, record.txid.as_deref())
+            && same(&self.payload_hash, Some(&record.payload_hash))
+            && self.outcome.is_none_or(|outcome| outcome == record.outcome)
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp <= until)
+    }
+
+    // the matching records in log order, keeping the latest `limit`
+    pub fn apply(&self, records: impl IntoIterator<Item = AuditRecord>) -> Vec<AuditRecord> {
+        let mut matching: Vec<_> = records.into_iter().filter(|r| self.matches(r)).collect();
+        if let Some(limit) = self.limit {
+            matching.drain(..matching.len().saturating_sub(limit));
+        }
+        matching
+    }
+}
+
+// append-only record of the signing and broadcast actions taken with the bundler keys
+pub trait AuditLog: Debug + Send + Sync {
+    fn append(&self, record: &AuditRecord) -> Result<(), Error>;
+    fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, Error>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryAuditLog {
+    records: RwLock<Vec<AuditRecord>>,
+}
+
+impl MemoryAuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AuditLog for MemoryAuditLog {
+    fn append(&self, record: &AuditRecord) -> Result<(), Error> {
+        self.records
+            .write()
+            .map_err(|_| Error::AuditLogError)?
+            .push(record.clone());
+        Ok(())
+    }
+
+    fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, Error> {
+        let records = self.records.read().map_err(|_| Error::AuditLogError)?;
+        Ok(query.apply(records.iter().cloned()))
+    }
+}
+
+// JSON lines file, one record per line, only ever appended to
+#[derive(Debug)]
+pub struct FileAuditLog {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileAuditLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileAuditLog {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+impl AuditLog for FileAuditLog {
+    fn append(&self, record: &AuditRecord) -> Result<(), Error> {
+        let _guard = self.lock.lock().map_err(|_| Error::AuditLogError)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+
+    fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, Error> {
+        let _guard = self.lock.lock().map_err(|_| Error::AuditLogError)?;
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut records = vec![];
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                records.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(query.apply(records))
+    }
+}
+
+// records are kept as JSON, next to the columns queries filter on
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteAuditLog {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteAuditLog {
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
+        Self::with_connection(rusqlite::Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<Self, Error> {
+        Self::with_connection(rusqlite::Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: rusqlite::Connection) -> Result<Self, Error> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS audit_log (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp INTEGER NOT NULL,
+                action TEXT NOT NULL,
+                signer TEXT NOT NULL,
+                txid TEXT,
+                record TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS audit_log_signer ON audit_log (signer, timestamp);
+            CREATE INDEX IF NOT EXISTS audit_log_txid ON audit_log (txid);
+            CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+                BEGIN SELECT RAISE(ABORT, 'audit log is append-only'); END;
+            CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+                BEGIN SELECT RAISE(ABORT, 'audit log is append-only'); END;",
+        )?;
+        Ok(SqliteAuditLog {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl AuditLog for SqliteAuditLog {
+    fn append(&self, record: &AuditRecord) -> Result<(), Error> {
+        let conn = self.conn.lock().map_err(|_| Error::AuditLogError)?;
+        conn.execute(
+            "INSERT INTO audit_log (timestamp, action, signer, txid, record) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                record.timestamp as i64,
+                record.action.as_str(),
+                record.signer.to_lowercase(),
+                record.txid.as_ref().map(|txid| txid.to_lowercase()),
+                serde_json::to_string(record)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    // the indexed columns narrow the scan, the rest of the query is applied on the records
+    fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, Error> {
+        let conn = self.conn.lock().map_err(|_| Error::AuditLogError)?;
+        let mut stmt = conn.prepare(
+            "SELECT record FROM audit_log
+            WHERE (?1 IS NULL OR signer = ?1) AND (?2 IS NULL OR txid = ?2)
+                AND (?3 IS NULL OR timestamp >= ?3) AND (?4 IS NULL OR timestamp <= ?4)
+            ORDER BY seq",
+        )?;
+        let rows = stmt.query_map(
+            rusqlite::params![
+                query.signer.as_ref().map(|s| s.to_lowercase()),
+                query.txid.as_ref().map(|t| t.to_lowercase()),
+                query.since.map(|t| t as i64),
+                query.until.map(|t| t as i64)
+            ],
+            |row| row.get::<_, String>(0),
+        )?;
+        let mut records = vec![];
+        for row in rows {
+            records.push(serde_json::from_str(&row?)?);
+        }
+        Ok(query.apply(records))
+    }
+}
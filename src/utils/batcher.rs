@@ -0,0 +1,282 @@
+use crate::utils::adaptive_batching::{chain_conditions, AdaptivePolicy, BatchTuning};
+use crate::utils::core::bundle_config::BundleConfig;
+use crate::utils::core::bundle_header::BundleHeader;
+use crate::utils::core::envelope::Envelope;
+use crate::utils::errors::Error;
+use crate::utils::evm::create_bundle;
+use futures::channel::mpsc;
+use futures::future::{self, Either};
+use futures::stream::{self, Stream, StreamExt};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_BATCH_MAX_BYTES: usize = 8_000_000;
+pub const DEFAULT_BATCH_MAX_AGE: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushReason {
+    // the next envelope would pass `max_bytes`, or `max_envelopes_per_bundle` is reached
+    Full,
+    // the oldest envelope waited `max_age`
+    Age,
+    // the envelopes stream ended
+    Closed,
+    Manual,
+}
+
+#[derive(Debug, Clone)]
+pub struct Flush {
+    pub reason: FlushReason,
+    pub envelopes: Vec<Envelope>,
+    // raw envelope data
+    pub bytes: usize,
+}
+
+#[derive(Debug)]
+pub struct FlushReceipt {
+    pub reason: FlushReason,
+    pub envelope_count: usize,
+    pub txid: Result<String, Error>,
+}
+
+// groups single envelopes into bundles by size and age
+#[derive(Debug)]
+pub struct Batcher {
+    config: BundleConfig,
+    max_bytes: usize,
+    max_age: Duration,
+    pending: Vec<Envelope>,
+    bytes: usize,
+    oldest: Option<Instant>,
+    adaptive: Option<AdaptivePolicy>,
+    adapted_at: Option<Instant>,
+    tuning: Option<BatchTuning>,
+    tunings: Option<mpsc::UnboundedSender<BatchTuning>>,
+}
+
+impl Batcher {
+    pub fn new(config: BundleConfig) -> Self {
+        Batcher {
+            config,
+            max_bytes: DEFAULT_BATCH_MAX_BYTES,
+            max_age: DEFAULT_BATCH_MAX_AGE,
+            pending: vec![],
+            bytes: 0,
+            oldest: None,
+            adaptive: None,
+            adapted_at: None,
+            tuning: None,
+            tunings: None,
+        }
+    }
+
+    pub fn max_bytes(mut self, bytes: usize) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = age;
+        self
+    }
+
+    // `max_bytes` and `max_age` follow the chain conditions of the config target, fetched
+    // every `policy.refresh` by `batches` and `submit` (see `adapt`)
+    pub fn adaptive(mut self, policy: AdaptivePolicy) -> Self {
+        self.adaptive = Some(policy);
+        self
+    }
+
+    // every tuning decision, for logs and metrics. Decisions are dropped while nobody
+    // subscribed, a new subscription replaces the previous one.
+    pub fn tunings(&mut self) -> mpsc::UnboundedReceiver<BatchTuning> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.tunings = Some(sender);
+        receiver
+    }
+
+    // the latest tuning decision
+    pub fn tuning(&self) -> Option<&BatchTuning> {
+        self.tuning.as_ref()
+    }
+
+    // applies to the pending envelopes too: a shorter `max_age` can make them due at once
+    pub fn tune(&mut self, tuning: BatchTuning) {
+        self.max_bytes = tuning.max_bytes;
+        self.max_age = tuning.max_age;
+        if let Some(tunings) = &self.tunings {
+            let _ = tunings.unbounded_send(tuning.clone());
+        }
+        self.tuning = Some(tuning);
+    }
+
+    // fetches the chain conditions and tunes the batcher with the adaptive policy. A
+    // failed lookup keeps the current tuning until the next refresh.
+    pub async fn adapt(&mut self) -> Result<Option<BatchTuning>, Error> {
+        let Some(policy) = self.adaptive else {
+            return Ok(None);
+        };
+        self.adapted_at = Some(Instant::now());
+        let conditions = chain_conditions(&self.config.target.rpc_url, policy.block_count).await?;
+        let tuning = policy.decide(conditions);
+        self.tune(tuning.clone());
+        Ok(Some(tuning))
+    }
+
+    fn next_adaptation(&self) -> Option<Instant> {
+        let policy = self.adaptive.as_ref()?;
+        match self.adapted_at {
+            Some(adapted_at) => Some(adapted_at + policy.refresh),
+            None => Some(Instant::now()),
+        }
+    }
+
+    pub fn config(&self) -> &BundleConfig {
+        &self.config
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    // when the pending envelopes are due for an age flush
+    pub fn deadline(&self) -> Option<Instant> {
+        self.oldest.map(|oldest| oldest + self.max_age)
+    }
+
+    // the returned flush never includes `envelope` unless it fills the bundle up to
+    // `max_envelopes_per_bundle`, so bundles stay under `max_bytes` (a single
+    // oversized envelope aside)
+    pub fn push(&mut self, envelope: Envelope) -> Result<Option<Flush>, Error> {
+        envelope
+            .validate_with(&self.config)
+            .map_err(|e| Error::InvalidEnvelope(self.pending.len(), e))?;
+        let len = envelope.data.as_ref().map_or(0, Vec::len);
+
+        let mut flushed = None;
+        if !self.pending.is_empty() && self.bytes + len > self.max_bytes {
+            flushed = self.take(FlushReason::Full);
+        }
+        self.pending.push(envelope);
+        self.bytes += len;
+        self.oldest.get_or_insert_with(Instant::now);
+        if flushed.is_none() && self.pending.len() >= self.config.max_envelopes_per_bundle.max(1) {
+            flushed = self.take(FlushReason::Full);
+        }
+        Ok(flushed)
+    }
+
+    pub fn flush_due(&mut self, now: Instant) -> Option<Flush> {
+        match self.deadline() {
+            Some(deadline) if deadline <= now => self.take(FlushReason::Age),
+            _ => None,
+        }
+    }
+
+    pub fn flush(&mut self) -> Option<Flush> {
+        self.take(FlushReason::Manual)
+    }
+
+    fn take(&mut self, reason: FlushReason) -> Option<Flush> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let flush = Flush {
+            reason,
+            envelopes: std::mem::take(&mut self.pending),
+            bytes: self.bytes,
+        };
+        self.bytes = 0;
+        self.oldest = None;
+        Some(flush)
+    }
+
+    // flush notifications of `envelopes` as they arrive, the rest being flushed as
+    // `Closed` once the stream ends. Invalid envelopes are reported and skipped.
+    pub fn batches<S>(self, envelopes: S) -> impl Stream<Item = Result<Flush, Error>>
+    where
+        S: Stream<Item = Envelope> + Send + 'static,
+    {
+        let state = (self, envelopes.boxed(), false);
+        stream::unfold(
+            state,
+            |(mut batcher, mut envelopes, mut closed)| async move {
+                loop {
+                    if closed {
+                        let flush = batcher.take(FlushReason::Closed)?;
+                        return Some((Ok(flush), (batcher, envelopes, closed)));
+                    }
+                    if batcher
+                        .next_adaptation()
+                        .is_some_and(|at| at <= Instant::now())
+                    {
+                        let _ = batcher.adapt().await;
+                    }
+                    // adaptations only matter to pending envelopes
+                    let adaptation = match batcher.is_empty() {
+                        true => None,
+                        false => batcher.next_adaptation(),
+                    };
+                    let wake = batcher.deadline().into_iter().chain(adaptation).min();
+                    let age = match wake {
+                        Some(wake) => {
+                            let wait = wake.saturating_duration_since(Instant::now());
+                            Either::Left(futures_timer::Delay::new(wait))
+                        }
+                        None => Either::Right(future::pending()),
+                    };
+                    match future::select(envelopes.next(), age).await {
+                        Either::Left((Some(envelope), _)) => match batcher.push(envelope) {
+                            Ok(Some(flush)) => {
+                                return Some((Ok(flush), (batcher, envelopes, closed)))
+                            }
+                            Ok(None) => {}
+                            Err(e) => return Some((Err(e), (batcher, envelopes, closed))),
+                        },
+                        Either::Left((None, _)) => closed = true,
+                        Either::Right(_) => {
+                            if let Some(flush) = batcher.flush_due(Instant::now()) {
+                                return Some((Ok(flush), (batcher, envelopes, closed)));
+                            }
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    // propagates every flushed bundle, one at a time, in flush order
+    pub fn submit<S>(
+        self,
+        envelopes: S,
+        private_key: String,
+    ) -> impl Stream<Item = Result<FlushReceipt, Error>>
+    where
+        S: Stream<Item = Envelope> + Send + 'static,
+    {
+        let config = self.config.clone();
+        self.batches(envelopes).then(move |flush| {
+            let (config, private_key) = (config.clone(), private_key.clone());
+            async move {
+                let flush = flush?;
+                let envelope_count = flush.envelopes.len();
+                let txid =
+                    create_bundle(flush.envelopes, private_key, BundleHeader::new(), &config)
+                        .await
+                        .map(|tx| tx.tx_hash().to_string());
+                Ok(FlushReceipt {
+                    reason: flush.reason,
+                    envelope_count,
+                    txid,
+                })
+            }
+        })
+    }
+}
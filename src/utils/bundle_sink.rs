@@ -0,0 +1,179 @@
+use crate::utils::adaptive_batching::BatchTuning;
+use crate::utils::batcher::{Batcher, Flush, FlushReason, FlushReceipt};
+use crate::utils::core::bundle_header::BundleHeader;
+use crate::utils::core::envelope::Envelope;
+use crate::utils::errors::Error;
+use crate::utils::evm::create_bundle;
+use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::stream::{FuturesOrdered, StreamExt};
+use futures::{FutureExt, Sink};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 1;
+
+pub type SubmitFn =
+    Arc<dyn Fn(Vec<Envelope>) -> BoxFuture<'static, Result<String, Error>> + Send + Sync>;
+
+// a `Sink` of envelopes batched by a `Batcher`. It stops accepting envelopes while
+// `max_in_flight` flushed bundles wait on their submission, so producers are slowed
+// down to the submission rate and at most `max_in_flight` bundles plus the pending batch
+// are held in memory. No task is spawned: submissions and age flushes progress while
+// the sink is polled (sending, flushing or closing it).
+pub struct BundleSink {
+    batcher: Batcher,
+    submit: SubmitFn,
+    max_in_flight: usize,
+    in_flight: FuturesOrdered<BoxFuture<'static, FlushReceipt>>,
+    receipts: Option<mpsc::UnboundedSender<FlushReceipt>>,
+}
+
+impl fmt::Debug for BundleSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BundleSink")
+            .field("batcher", &self.batcher)
+            .field("max_in_flight", &self.max_in_flight)
+            .field("in_flight", &self.in_flight.len())
+            .field("receipts", &self.receipts.is_some())
+            .finish()
+    }
+}
+
+impl BundleSink {
+    // propagates the flushed bundles with the batcher config
+    pub fn new(batcher: Batcher, private_key: String) -> Self {
+        let config = batcher.config().clone();
+        Self::with_submit(batcher, move |envelopes| {
+            let (config, private_key) = (config.clone(), private_key.clone());
+            async move {
+                let tx =
+                    create_bundle(envelopes, private_key, BundleHeader::new(), &config).await?;
+                Ok(tx.tx_hash().to_string())
+            }
+        })
+    }
+
+    // submits the flushed bundles with `submit`, which returns their txid
+    pub fn with_submit<F, Fut>(batcher: Batcher, submit: F) -> Self
+    where
+        F: Fn(Vec<Envelope>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, Error>> + Send + 'static,
+    {
+        BundleSink {
+            batcher,
+            submit: Arc::new(move |envelopes| submit(envelopes).boxed()),
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            in_flight: FuturesOrdered::new(),
+            receipts: None,
+        }
+    }
+
+    pub fn max_in_flight(mut self, bundles: usize) -> Self {
+        self.max_in_flight = bundles.max(1);
+        self
+    }
+
+    // receipts of the submitted bundles, in flush order. Receipts are dropped while
+    // nobody subscribed, a new subscription replaces the previous one.
+    pub fn receipts(&mut self) -> mpsc::UnboundedReceiver<FlushReceipt> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.receipts = Some(sender);
+        receiver
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    // see `Batcher::tune`, the sink doesn't fetch chain conditions by itself
+    pub fn tune(&mut self, tuning: BatchTuning) {
+        self.batcher.tune(tuning);
+    }
+
+    // envelopes waiting for their bundle to be flushed
+    pub fn pending(&self) -> usize {
+        self.batcher.len()
+    }
+
+    fn start(&mut self, flush: Flush) {
+        let (reason, envelope_count) = (flush.reason, flush.envelopes.len());
+        let submission = (self.submit)(flush.envelopes);
+        self.in_flight.push_back(
+            async move {
+                FlushReceipt {
+                    reason,
+                    envelope_count,
+                    txid: submission.await,
+                }
+            }
+            .boxed(),
+        );
+    }
+
+    // delivers the finished submissions, `Ready` once none is in flight
+    fn poll_submissions(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        while let Poll::Ready(receipt) = self.in_flight.poll_next_unpin(cx) {
+            let Some(receipt) = receipt else {
+                return Poll::Ready(());
+            };
+            if let Some(receipts) = &self.receipts {
+                let _ = receipts.unbounded_send(receipt);
+            }
+        }
+        Poll::Pending
+    }
+
+    fn poll_drain(&mut self, cx: &mut Context<'_>, reason: FlushReason) -> Poll<Result<(), Error>> {
+        if let Some(mut flush) = self.batcher.flush() {
+            flush.reason = reason;
+            self.start(flush);
+        }
+        self.poll_submissions(cx).map(Ok)
+    }
+}
+
+impl Sink<Envelope> for BundleSink {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let sink = self.get_mut();
+        loop {
+            let _ = sink.poll_submissions(cx);
+            if sink.in_flight.len() >= sink.max_in_flight {
+                return Poll::Pending;
+            }
+            match sink.batcher.flush_due(Instant::now()) {
+                Some(flush) => sink.start(flush),
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+
+    // an invalid envelope is rejected, the sink stays usable
+    fn start_send(self: Pin<&mut Self>, envelope: Envelope) -> Result<(), Error> {
+        let sink = self.get_mut();
+        if let Some(flush) = sink.batcher.push(envelope)? {
+            sink.start(flush);
+        }
+        Ok(())
+    }
+
+    // submits the pending batch and waits for every submission
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.get_mut().poll_drain(cx, FlushReason::Manual)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let sink = self.get_mut();
+        let drained = sink.poll_drain(cx, FlushReason::Closed);
+        if drained.is_ready() {
+            sink.receipts = None;
+        }
+        drained
+    }
+}
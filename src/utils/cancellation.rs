@@ -0,0 +1,87 @@
+use crate::utils::errors::Error;
+use futures::future::{self, Either};
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, Waker};
+
+// shutdown signal shared by all its clones. Pipelines stop at their next await point
+// once it is cancelled, but never in the middle of a broadcast.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        for waker in self.inner.wakers.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn check(&self) -> Result<(), Error> {
+        match self.is_cancelled() {
+            true => Err(Error::Cancelled),
+            false => Ok(()),
+        }
+    }
+
+    // resolves once the token is cancelled
+    pub async fn cancelled(&self) {
+        future::poll_fn(|cx| {
+            if self.is_cancelled() {
+                return Poll::Ready(());
+            }
+            let mut wakers = self.inner.wakers.lock().unwrap();
+            if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+            drop(wakers);
+            // `cancel` may have drained the wakers between the check and the push
+            match self.is_cancelled() {
+                true => Poll::Ready(()),
+                false => Poll::Pending,
+            }
+        })
+        .await
+    }
+
+    // drops `future` and fails with `Error::Cancelled` once the token is cancelled
+    pub async fn run<T>(&self, future: impl Future<Output = Result<T, Error>>) -> Result<T, Error> {
+        self.check()?;
+        match future::select(pin!(future), pin!(self.cancelled())).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(Error::Cancelled),
+        }
+    }
+}
+
+pub async fn with_cancellation<T>(
+    token: Option<&CancellationToken>,
+    future: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    match token {
+        Some(token) => token.run(future).await,
+        None => future.await,
+    }
+}
+
+pub fn check_cancellation(token: Option<&CancellationToken>) -> Result<(), Error> {
+    token.map_or(Ok(()), CancellationToken::check)
+}
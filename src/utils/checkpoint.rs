@@ -0,0 +1,138 @@
+use crate::utils::errors::Error;
+use std::fmt::Debug;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// last block a scan fully processed, to resume it after a crash, see `backfill_bundles`
+pub trait CheckpointStore: Debug + Send + Sync {
+    fn load(&self) -> Result<Option<u64>, Error>;
+    fn save(&self, block_number: u64) -> Result<(), Error>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryCheckpointStore {
+    block_number: Mutex<Option<u64>>,
+}
+
+impl MemoryCheckpointStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CheckpointStore for MemoryCheckpointStore {
+    fn load(&self) -> Result<Option<u64>, Error> {
+        let block_number = self
+            .block_number
+            .lock()
+            .map_err(|_| Error::CheckpointStoreError)?;
+        Ok(*block_number)
+    }
+
+    fn save(&self, block_number: u64) -> Result<(), Error> {
+        *self
+            .block_number
+            .lock()
+            .map_err(|_| Error::CheckpointStoreError)? = Some(block_number);
+        Ok(())
+    }
+}
+
+// the block number as text, replaced atomically through a temporary file next to it
+#[derive(Debug)]
+pub struct FileCheckpointStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileCheckpointStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileCheckpointStore {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn load(&self) -> Result<Option<u64>, Error> {
+        let _guard = self.lock.lock().map_err(|_| Error::CheckpointStoreError)?;
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let block_number = content
+            .trim()
+            .parse()
+            .map_err(|_| Error::CheckpointStoreError)?;
+        Ok(Some(block_number))
+    }
+
+    fn save(&self, block_number: u64) -> Result<(), Error> {
+        let _guard = self.lock.lock().map_err(|_| Error::CheckpointStoreError)?;
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, block_number.to_string())?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+// one row per scan name, several scans can share the database
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteCheckpointStore {
+    conn: Mutex<rusqlite::Connection>,
+    name: String,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteCheckpointStore {
+    pub fn open(path: impl AsRef<std::path::Path>, name: &str) -> Result<Self, Error> {
+        Self::with_connection(rusqlite::Connection::open(path)?, name)
+    }
+
+    pub fn in_memory(name: &str) -> Result<Self, Error> {
+        Self::with_connection(rusqlite::Connection::open_in_memory()?, name)
+    }
+
+    fn with_connection(conn: rusqlite::Connection, name: &str) -> Result<Self, Error> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS checkpoints (
+                name TEXT PRIMARY KEY,
+                block_number INTEGER NOT NULL
+            );",
+        )?;
+        Ok(SqliteCheckpointStore {
+            conn: Mutex::new(conn),
+            name: name.to_string(),
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl CheckpointStore for SqliteCheckpointStore {
+    fn load(&self) -> Result<Option<u64>, Error> {
+        use rusqlite::OptionalExtension;
+        let conn = self.conn.lock().map_err(|_| Error::CheckpointStoreError)?;
+        let block_number = conn
+            .query_row(
+                "SELECT block_number FROM checkpoints WHERE name = ?1",
+                [&self.name],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?;
+        Ok(block_number.map(|n| n as u64))
+    }
+
+    fn save(&self, block_number: u64) -> Result<(), Error> {
+        let conn = self.conn.lock().map_err(|_| Error::CheckpointStoreError)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO checkpoints (name, block_number) VALUES (?1, ?2)",
+            rusqlite::params![self.name, block_number as i64],
+        )?;
+        Ok(())
+    }
+}
@@ -1,3 +1,10 @@
 pub const CHAIN_ID: u64 = 9496;
 pub const WVM_RPC_URL: &str = "https://testnet-rpc.wvm.dev";
 pub const ADDRESS_BABE1: &str = "0xbabe1d25501157043c7b4ea7CBC877B9B4D8A057";
+pub const BUNDLE_MAGIC: [u8; 4] = *b"WVMB";
+// 2 adds the envelope version and extension section to every bundle item
+pub const BUNDLE_FORMAT_VERSION: u8 = 2;
+pub const BUNDLE_FORMAT_V1: u8 = 1;
+// 2 appends a checksum of the item to its extension section
+pub const ENVELOPE_FORMAT_VERSION: u8 = 2;
+pub const ENVELOPE_FORMAT_V1: u8 = 1;
@@ -0,0 +1,64 @@
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use alloy::primitives::{Address, PrimitiveSignature};
+use alloy::signers::{local::PrivateKeySigner, SignerSync};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// off-chain EIP-191 statement binding an envelope id to its author
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorshipProof {
+    pub envelope_id: String,
+    pub author: String,
+    pub signature: String,
+}
+
+impl AuthorshipProof {
+    pub fn statement(envelope_id: &str, author: &str) -> String {
+        format!(
+            "WeaveVM Bundler authorship proof\nenvelope: {}\nauthor: {}",
+            envelope_id.to_lowercase(),
+            author.to_lowercase()
+        )
+    }
+
+    pub fn sign(envelope_id: &str, private_key: &str) -> Result<Self, Error> {
+        let signer: PrivateKeySigner = private_key
+            .parse()
+            .map_err(|_| Error::PrivateKeyParsingError)?;
+        let author = signer.address().to_string();
+        let statement = Self::statement(envelope_id, &author);
+        let signature = signer
+            .sign_message_sync(statement.as_bytes())
+            .map_err(|_| Error::InvalidSignature)?;
+        Ok(AuthorshipProof {
+            envelope_id: envelope_id.to_string(),
+            author,
+            signature: alloy::hex::encode_prefixed(signature.as_bytes()),
+        })
+    }
+
+    pub fn verify(&self) -> Result<(), Error> {
+        let signature =
+            PrimitiveSignature::from_str(&self.signature).map_err(|_| Error::InvalidSignature)?;
+        let statement = Self::statement(&self.envelope_id, &self.author);
+        let recovered = signature
+            .recover_address_from_msg(statement.as_bytes())
+            .map_err(|_| Error::InvalidSignature)?;
+        if recovered != self.author.parse::<Address>()? {
+            return Err(Error::AuthorshipMismatch);
+        }
+        Ok(())
+    }
+
+    // also checks that the proven author is the signer of `envelope`
+    pub fn verify_envelope(&self, envelope: &TxEnvelopeWrapper) -> Result<(), Error> {
+        self.verify()?;
+        if !envelope.hash.eq_ignore_ascii_case(&self.envelope_id)
+            || envelope.recover_signer()? != self.author.parse::<Address>()?
+        {
+            return Err(Error::AuthorshipMismatch);
+        }
+        Ok(())
+    }
+}
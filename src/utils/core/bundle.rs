@@ -1,14 +1,44 @@
-use crate::utils::constants::ADDRESS_BABE1;
+use crate::utils::audit_log::AuditLog;
+use crate::utils::cancellation::CancellationToken;
+use crate::utils::core::bundle_config::BundleConfig;
 use crate::utils::core::bundle_data::BundleData;
-use crate::utils::core::bundle_tx_metadata::BundleTxMetadata;
+use crate::utils::core::bundle_header::BundleHeader;
+use crate::utils::core::chain_target::{ChainTarget, TargetSubmission};
+use crate::utils::core::decode_config::DecodeConfig;
+use crate::utils::core::dictionary::Dictionary;
 use crate::utils::core::envelope::Envelope;
+use crate::utils::core::envelope_ordering::EnvelopeOrdering;
+use crate::utils::core::envelope_stats::EnvelopeStats;
+use crate::utils::core::packing::PackingStrategy;
+use crate::utils::core::policy::BundlePolicy;
+use crate::utils::core::profile::BundleProfile;
+use crate::utils::core::stream::StreamPointer;
 use crate::utils::errors::Error;
-use crate::utils::evm::{create_bundle, retrieve_bundle_data, retrieve_bundle_tx};
+use crate::utils::evm::{
+    create_bundle_until, create_sponsored_bundle, estimate_bundle_size, estimate_envelope_stats,
+    pack_envelopes, retrieve_bundle_with, sponsored_bundle, submit_sponsored_bundles,
+    submit_to_targets,
+};
+use crate::utils::gas_oracle::GasOracle;
+use crate::utils::inclusion::{
+    wait_for_confirmation_from, BroadcastOutcome, ConfirmationLevel, Inclusion,
+};
+use crate::utils::receipt_store::ReceiptStore;
+use crate::utils::retry::RetryConfig;
+use crate::utils::sidecar::SidecarSink;
+use crate::utils::spend_guard::SpendGuard;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, Default)]
 pub struct Bundle {
     pub envelopes: Option<Vec<Envelope>>,
     pub private_key: Option<String>,
+    // signs and pays for the bundle tx instead of `private_key`, which then only signs
+    // the envelopes
+    pub payer_key: Option<String>,
+    pub header: BundleHeader,
+    pub config: BundleConfig,
 }
 
 impl Bundle {
@@ -16,6 +46,9 @@ impl Bundle {
         Bundle {
             envelopes: None,
             private_key: None,
+            payer_key: None,
+            header: BundleHeader::new(),
+            config: BundleConfig::new(),
         }
     }
 
@@ -24,6 +57,11 @@ impl Bundle {
         self
     }
 
+    pub fn payer_key(mut self, key: String) -> Self {
+        self.payer_key = Some(key);
+        self
+    }
+
     pub fn envelopes(mut self, envelopes: Vec<Envelope>) -> Self {
         self.envelopes = Some(envelopes);
         self
@@ -34,6 +72,130 @@ impl Bundle {
         self
     }
 
+    pub fn stream(mut self, pointer: StreamPointer) -> Self {
+        self.header.stream = Some(pointer);
+        self
+    }
+
+    pub fn idempotency_key(mut self, key: String) -> Self {
+        self.header.idempotency_key = Some(key);
+        self
+    }
+
+    pub fn config(mut self, config: BundleConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn profile(mut self, profile: BundleProfile) -> Self {
+        self.config = self.config.profile(profile);
+        self
+    }
+
+    pub fn dictionary(mut self, dictionary: Dictionary) -> Self {
+        self.config.dictionary = Some(dictionary);
+        self
+    }
+
+    pub fn compression_quality(mut self, quality: u32) -> Self {
+        self.config.compression.quality = quality;
+        self
+    }
+
+    pub fn compression_window(mut self, window: u32) -> Self {
+        self.config.compression.window = window;
+        self
+    }
+
+    pub fn ordering(mut self, ordering: EnvelopeOrdering) -> Self {
+        self.config = self.config.ordering(ordering);
+        self
+    }
+
+    pub fn packing(mut self, packing: PackingStrategy) -> Self {
+        self.config = self.config.packing(packing);
+        self
+    }
+
+    pub fn target(mut self, target: ChainTarget) -> Self {
+        self.config = self.config.target(target);
+        self
+    }
+
+    pub fn gas_oracle(mut self, gas_oracle: impl GasOracle + 'static) -> Self {
+        self.config = self.config.gas_oracle(gas_oracle);
+        self
+    }
+
+    // gas limit and fee overrides of this bundle tx only
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.config = self.config.gas_limit(gas_limit);
+        self
+    }
+
+    pub fn max_fee_per_gas(mut self, max_fee: u128) -> Self {
+        self.config = self.config.max_fee_per_gas(max_fee);
+        self
+    }
+
+    pub fn max_priority_fee_per_gas(mut self, max_priority_fee: u128) -> Self {
+        self.config = self.config.max_priority_fee_per_gas(max_priority_fee);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryConfig) -> Self {
+        self.config = self.config.retry(retry);
+        self
+    }
+
+    pub fn broadcast_timeout(mut self, timeout: Duration) -> Self {
+        self.config = self.config.broadcast_timeout(timeout);
+        self
+    }
+
+    // keep a clone of the store to look envelopes up later
+    pub fn receipt_store(mut self, store: Arc<dyn ReceiptStore>) -> Self {
+        self.config = self.config.receipt_store(store);
+        self
+    }
+
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.config = self.config.cancellation(token);
+        self
+    }
+
+    pub fn policy(mut self, policy: BundlePolicy) -> Self {
+        self.config = self.config.policy(policy);
+        self
+    }
+
+    pub fn spend_guard(mut self, guard: SpendGuard) -> Self {
+        self.config = self.config.spend_guard(guard);
+        self
+    }
+
+    pub fn audit_log(mut self, log: Arc<dyn AuditLog>) -> Self {
+        self.config = self.config.audit_log(log);
+        self
+    }
+
+    pub fn sidecar_sink(mut self, sink: Arc<dyn SidecarSink>) -> Self {
+        self.config = self.config.sidecar_sink(sink);
+        self
+    }
+
+    // does not require a private key, envelopes are signed with a throwaway key
+    pub async fn estimate_size(&self) -> Result<usize, Error> {
+        let envelopes = self.envelopes.as_deref().unwrap_or_default();
+        estimate_bundle_size(envelopes, &self.header, &self.config).await
+    }
+
+    // same throwaway key as `estimate_size`, stats are in envelope order
+    pub async fn envelope_stats(&self) -> Result<Vec<EnvelopeStats>, Error> {
+        let envelopes = self.envelopes.as_deref().unwrap_or_default();
+        estimate_envelope_stats(envelopes, &self.header, &self.config).await
+    }
+
     pub fn build(self) -> Result<Bundle, Error> {
         let envelopes = self
             .envelopes
@@ -43,33 +205,169 @@ impl Bundle {
             .private_key
             .filter(|p| !p.is_empty())
             .ok_or(Error::PrivateKeyNeeded)?;
+        self.config.compression.validate()?;
+        // stream bundles can't be split, a stream pointer links exactly one bundle
+        if !self.config.auto_batch || self.header.stream.is_some() {
+            self.config.check_envelope_count(envelopes.len())?;
+        }
+        self.config.validate_envelopes(&envelopes)?;
 
         Ok(Bundle {
             envelopes: Some(envelopes),
             private_key: Some(private_key),
+            payer_key: self.payer_key.filter(|p| !p.is_empty()),
+            header: self.header,
+            config: self.config,
         })
     }
+
+    // envelope signer and bundle tx payer keys
+    fn keys(&self) -> Result<(String, String), Error> {
+        let private_key = self.private_key.clone().ok_or(Error::PrivateKeyNeeded)?;
+        let payer_key = self
+            .payer_key
+            .clone()
+            .unwrap_or_else(|| private_key.clone());
+        Ok((private_key, payer_key))
+    }
+
     pub async fn propagate(self) -> Result<String, Error> {
+        let (private_key, payer_key) = self.keys()?;
         let envelopes = self.envelopes.ok_or(Error::EnvelopesNeeded)?;
-        let private_key = self.private_key.ok_or(Error::PrivateKeyNeeded)?;
 
-        let tx = create_bundle(envelopes, private_key)
-            .await
-            .map_err(|_| Error::BundleNotCreated)?;
+        let tx =
+            create_sponsored_bundle(envelopes, private_key, payer_key, self.header, &self.config)
+                .await?;
         let hash = tx.tx_hash().to_string();
         Ok(hash)
     }
 
-    pub async fn retrieve_envelopes(bundle_txid: String) -> Result<BundleData, Error> {
-        let bundle: BundleTxMetadata = retrieve_bundle_tx(bundle_txid)
-            .await
-            .map_err(|_| Error::BundleRetrievalProblem)?;
-        // assert the bundle versioning by checking target address
-        if bundle.to.to_lowercase() != ADDRESS_BABE1.to_string().to_ascii_lowercase() {
-            return Err(Error::UnverifiedAddress);
+    // with `auto_batch`, envelopes beyond `max_envelopes_per_bundle` (or `max_bundle_size`
+    // with `PackingStrategy::FirstFitDecreasing`) are split into several bundles submitted
+    // with consecutive nonces, in order
+    pub async fn propagate_batched(self) -> Result<Vec<Result<String, Error>>, Error> {
+        let envelopes = self.envelopes.ok_or(Error::EnvelopesNeeded)?;
+        let private_key = self.private_key.ok_or(Error::PrivateKeyNeeded)?;
+        let within_count = envelopes.len() <= self.config.max_envelopes_per_bundle;
+        let packed = self.config.packing != PackingStrategy::Sequential;
+        if !self.config.auto_batch || within_count && (!packed || self.header.stream.is_some()) {
+            let bundle = Bundle {
+                envelopes: Some(envelopes),
+                private_key: Some(private_key),
+                ..self
+            };
+            return Ok(vec![bundle.propagate().await]);
+        }
+        if self.header.stream.is_some() {
+            return Err(Error::TooManyEnvelopes(
+                envelopes.len(),
+                self.config.max_envelopes_per_bundle,
+            ));
+        }
+
+        let mut batches = pack_envelopes(envelopes, &self.header, &self.config).await?;
+        if batches.len() == 1 {
+            let bundle = Bundle {
+                envelopes: batches.pop(),
+                private_key: Some(private_key),
+                ..self
+            };
+            return Ok(vec![bundle.propagate().await]);
         }
+        let payer_key = self.payer_key.unwrap_or_else(|| private_key.clone());
+        submit_sponsored_bundles(batches, private_key, payer_key, &self.config).await
+    }
+
+    // posts the same bundle to every target, the submissions are in `targets` order
+    pub async fn propagate_to(
+        self,
+        targets: &[ChainTarget],
+    ) -> Result<Vec<TargetSubmission>, Error> {
+        let (private_key, payer_key) = self.keys()?;
+        let envelopes = self.envelopes.ok_or(Error::EnvelopesNeeded)?;
+        submit_to_targets(
+            envelopes,
+            private_key,
+            payer_key,
+            self.header,
+            targets,
+            &self.config,
+        )
+        .await
+    }
+
+    // within `broadcast_timeout`, tells a bundle never sent, sent but unconfirmed or
+    // confirmed at `level` apart
+    pub async fn propagate_with_timeout(
+        self,
+        level: ConfirmationLevel,
+        poll_interval: Duration,
+    ) -> Result<BroadcastOutcome, Error> {
+        let (private_key, payer_key) = self.keys()?;
+        let envelopes = self.envelopes.ok_or(Error::EnvelopesNeeded)?;
+        create_bundle_until(
+            envelopes,
+            private_key,
+            payer_key,
+            self.header,
+            &self.config,
+            level,
+            poll_interval,
+        )
+        .await
+    }
+
+    // propagates the bundle and waits for `level`, resubmitting it with a fresh nonce
+    // whenever a reorg drops it. All submissions share the same idempotency key.
+    pub async fn propagate_until(
+        self,
+        level: ConfirmationLevel,
+        poll_interval: Duration,
+    ) -> Result<Inclusion, Error> {
+        let (private_key, payer_key) = self.keys()?;
+        let envelopes = self.envelopes.ok_or(Error::EnvelopesNeeded)?;
+        let mut header = self.header;
+        header
+            .idempotency_key
+            .get_or_insert_with(BundleHeader::random_idempotency_key);
+
+        let mut resubmissions = 0;
+        loop {
+            let tx = sponsored_bundle(
+                envelopes.clone(),
+                private_key.clone(),
+                payer_key.clone(),
+                header.clone(),
+                &self.config,
+                resubmissions == 0,
+            )
+            .await?;
+            let txid = tx.tx_hash().to_string();
+
+            let rpc_url = &self.config.target.rpc_url;
+            match wait_for_confirmation_from(rpc_url, &txid, level, poll_interval).await? {
+                Some(inclusion) => return Ok(inclusion),
+                None if resubmissions < self.config.max_resubmissions => resubmissions += 1,
+                None => return Err(Error::BundleDropped(txid)),
+            }
+        }
+    }
+
+    // `propagate_until` the config (or profile) confirmation level
+    pub async fn propagate_confirmed(self, poll_interval: Duration) -> Result<Inclusion, Error> {
+        let level = self.config.confirmation_level;
+        self.propagate_until(level, poll_interval).await
+    }
+
+    pub async fn retrieve_envelopes(bundle_txid: String) -> Result<BundleData, Error> {
+        Self::retrieve_envelopes_with(bundle_txid, &DecodeConfig::default()).await
+    }
 
-        let res: BundleData = retrieve_bundle_data(bundle.calldata).await;
-        Ok(res)
+    pub async fn retrieve_envelopes_with(
+        bundle_txid: String,
+        config: &DecodeConfig,
+    ) -> Result<BundleData, Error> {
+        let (_, bundle) = retrieve_bundle_with(bundle_txid, config).await?;
+        Ok(bundle)
     }
 }
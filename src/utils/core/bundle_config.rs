@@ -0,0 +1,342 @@
+use crate::utils::audit_log::AuditLog;
+use crate::utils::cancellation::CancellationToken;
+use crate::utils::core::chain_target::ChainTarget;
+use crate::utils::core::compression::CompressionParams;
+use crate::utils::core::dictionary::Dictionary;
+use crate::utils::core::envelope::Envelope;
+use crate::utils::core::envelope_ordering::EnvelopeOrdering;
+use crate::utils::core::gas_cost::GasOptimizer;
+use crate::utils::core::packing::PackingStrategy;
+use crate::utils::core::policy::BundlePolicy;
+use crate::utils::core::profile::BundleProfile;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::dedup::BundleIndex;
+use crate::utils::errors::Error;
+use crate::utils::gas_oracle::{FeeHistoryGasOracle, GasFees, GasOracle};
+use crate::utils::inclusion::ConfirmationLevel;
+use crate::utils::receipt_store::ReceiptStore;
+use crate::utils::retry::RetryConfig;
+use crate::utils::sidecar::SidecarSink;
+use crate::utils::spend_guard::SpendGuard;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_INCOMPRESSIBLE_RATIO: f64 = 0.95;
+pub const DEFAULT_MAX_ENVELOPES_PER_BUNDLE: usize = 10_000;
+// bundle calldata must stay under 9 MB
+pub const DEFAULT_MAX_ENVELOPE_SIZE: usize = 9_000_000;
+pub const DEFAULT_BUNDLE_GAS_LIMIT: u64 = 490_000_000;
+pub const DEFAULT_MAX_BUNDLE_SIZE: usize = 9_000_000;
+
+#[derive(Debug, Clone)]
+pub struct BundleConfig {
+    pub dictionary: Option<Dictionary>,
+    pub compression: CompressionParams,
+    // envelopes whose compressed/raw size ratio is above this are stored uncompressed
+    pub incompressible_ratio: Option<f64>,
+    pub ordering: EnvelopeOrdering,
+    pub max_envelopes_per_bundle: usize,
+    // maximum envelope data size, in bytes
+    pub max_envelope_size: usize,
+    // split oversized bundles into several submissions instead of failing
+    pub auto_batch: bool,
+    pub packing: PackingStrategy,
+    // calldata size `PackingStrategy::FirstFitDecreasing` bundles are packed up to
+    pub max_bundle_size: usize,
+    // chain the bundle tx is posted to, WeaveVM by default
+    pub target: ChainTarget,
+    pub gas_oracle: Arc<dyn GasOracle>,
+    // gas limit of the bundle tx, to fit the chain block gas limit
+    pub gas_limit: u64,
+    // used instead of the gas oracle fees when set
+    pub max_fee_per_gas: Option<u128>,
+    pub max_priority_fee_per_gas: Option<u128>,
+    // transport level retries of every RPC call, by error class
+    pub retry: RetryConfig,
+    // broadcast retries on nonce-too-low and replacement-underpriced errors
+    pub broadcast_retries: u32,
+    // level awaited by `Bundle::propagate_confirmed`
+    pub confirmation_level: ConfirmationLevel,
+    // resubmissions of a bundle tx dropped by a reorg before reaching its confirmation level
+    pub max_resubmissions: u32,
+    // overall time to sign, broadcast and confirm a bundle, see `BroadcastOutcome`
+    pub broadcast_timeout: Option<Duration>,
+    // receipts of propagated bundles are saved here when set
+    pub receipt_store: Option<Arc<dyn ReceiptStore>>,
+    // total bytes the signing/encoding pipeline may buffer, unbounded when `None`
+    pub memory_budget: Option<usize>,
+    // stops signing and broadcasting once cancelled
+    pub cancellation: Option<CancellationToken>,
+    // checked on the signed envelopes before the bundle is encoded, not on estimates
+    pub policy: Option<BundlePolicy>,
+    // queried in order before a bundle is broadcasted, an already posted bundle is not
+    // posted again and its earlier txid is returned
+    pub dedup_indexes: Vec<Arc<dyn BundleIndex>>,
+    // picks the cheapest compression per bundle, not under a memory budget
+    pub gas_optimizer: Option<GasOptimizer>,
+    // checked before each bundle tx broadcast, retries with bumped fees included
+    pub spend_guard: Option<SpendGuard>,
+    // envelope signing, bundle tx signing and broadcast attempts are recorded here when set
+    pub audit_log: Option<Arc<dyn AuditLog>>,
+    // sent a `BundleSidecar` of every posted bundle, best effort
+    pub sidecar_sinks: Vec<Arc<dyn SidecarSink>>,
+}
+
+impl Default for BundleConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BundleConfig {
+    pub fn new() -> Self {
+        BundleConfig {
+            dictionary: None,
+            compression: CompressionParams::default(),
+            incompressible_ratio: Some(DEFAULT_INCOMPRESSIBLE_RATIO),
+            ordering: EnvelopeOrdering::default(),
+            max_envelopes_per_bundle: DEFAULT_MAX_ENVELOPES_PER_BUNDLE,
+            max_envelope_size: DEFAULT_MAX_ENVELOPE_SIZE,
+            auto_batch: false,
+            packing: PackingStrategy::default(),
+            max_bundle_size: DEFAULT_MAX_BUNDLE_SIZE,
+            target: ChainTarget::default(),
+            gas_oracle: Arc::new(FeeHistoryGasOracle::default()),
+            gas_limit: DEFAULT_BUNDLE_GAS_LIMIT,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            retry: RetryConfig::default(),
+            broadcast_retries: 3,
+            confirmation_level: ConfirmationLevel::default(),
+            max_resubmissions: 3,
+            broadcast_timeout: None,
+            receipt_store: None,
+            memory_budget: None,
+            cancellation: None,
+            policy: None,
+            dedup_indexes: vec![],
+            gas_optimizer: None,
+            spend_guard: None,
+            audit_log: None,
+            sidecar_sinks: vec![],
+        }
+    }
+
+    pub fn dictionary(mut self, dictionary: Dictionary) -> Self {
+        self.dictionary = Some(dictionary);
+        self
+    }
+
+    // trades compression ratio for speed, 0 (fastest) to 11 (smallest)
+    pub fn compression_quality(mut self, quality: u32) -> Self {
+        self.compression.quality = quality;
+        self
+    }
+
+    // `None` always compresses envelopes with their requested compression
+    pub fn incompressible_ratio(mut self, ratio: Option<f64>) -> Self {
+        self.incompressible_ratio = ratio;
+        self
+    }
+
+    pub fn ordering(mut self, ordering: EnvelopeOrdering) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    pub fn max_envelopes_per_bundle(mut self, max: usize) -> Self {
+        self.max_envelopes_per_bundle = max;
+        self
+    }
+
+    pub fn max_envelope_size(mut self, max: usize) -> Self {
+        self.max_envelope_size = max;
+        self
+    }
+
+    pub fn auto_batch(mut self, auto_batch: bool) -> Self {
+        self.auto_batch = auto_batch;
+        self
+    }
+
+    pub fn packing(mut self, packing: PackingStrategy) -> Self {
+        self.packing = packing;
+        self
+    }
+
+    pub fn max_bundle_size(mut self, max: usize) -> Self {
+        self.max_bundle_size = max;
+        self
+    }
+
+    pub fn check_envelope_count(&self, count: usize) -> Result<(), Error> {
+        if count > self.max_envelopes_per_bundle {
+            return Err(Error::TooManyEnvelopes(
+                count,
+                self.max_envelopes_per_bundle,
+            ));
+        }
+        Ok(())
+    }
+
+    // checked before any envelope is signed
+    pub fn validate_envelopes(&self, envelopes: &[Envelope]) -> Result<(), Error> {
+        for (i, envelope) in envelopes.iter().enumerate() {
+            envelope
+                .validate_with(self)
+                .map_err(|e| Error::InvalidEnvelope(i, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn target(mut self, target: ChainTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn gas_oracle(mut self, gas_oracle: impl GasOracle + 'static) -> Self {
+        self.gas_oracle = Arc::new(gas_oracle);
+        self
+    }
+
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    // the target fee rules take precedence over the config ones
+    pub fn target_gas_oracle(&self) -> &Arc<dyn GasOracle> {
+        self.target.gas_oracle.as_ref().unwrap_or(&self.gas_oracle)
+    }
+
+    pub fn target_gas_limit(&self) -> u64 {
+        self.target.gas_limit.unwrap_or(self.gas_limit)
+    }
+
+    // in wei
+    pub fn max_fee_per_gas(mut self, max_fee: u128) -> Self {
+        self.max_fee_per_gas = Some(max_fee);
+        self
+    }
+
+    pub fn max_priority_fee_per_gas(mut self, max_priority_fee: u128) -> Self {
+        self.max_priority_fee_per_gas = Some(max_priority_fee);
+        self
+    }
+
+    // both fees overridden make the gas oracle unnecessary
+    pub fn fee_overrides(&self) -> Option<GasFees> {
+        Some(GasFees {
+            max_fee_per_gas: self.max_fee_per_gas?,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas?,
+        })
+    }
+
+    pub fn override_fees(&self, fees: GasFees) -> GasFees {
+        GasFees {
+            max_fee_per_gas: self.max_fee_per_gas.unwrap_or(fees.max_fee_per_gas),
+            max_priority_fee_per_gas: self
+                .max_priority_fee_per_gas
+                .unwrap_or(fees.max_priority_fee_per_gas),
+        }
+    }
+
+    pub fn retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn broadcast_retries(mut self, retries: u32) -> Self {
+        self.broadcast_retries = retries;
+        self
+    }
+
+    pub fn from_profile(profile: BundleProfile) -> Self {
+        profile.apply(Self::new())
+    }
+
+    // overrides the knobs set by the profile, builders called later override it in turn
+    pub fn profile(self, profile: BundleProfile) -> Self {
+        profile.apply(self)
+    }
+
+    pub fn confirmation_level(mut self, level: ConfirmationLevel) -> Self {
+        self.confirmation_level = level;
+        self
+    }
+
+    pub fn max_resubmissions(mut self, resubmissions: u32) -> Self {
+        self.max_resubmissions = resubmissions;
+        self
+    }
+
+    pub fn broadcast_timeout(mut self, timeout: Duration) -> Self {
+        self.broadcast_timeout = Some(timeout);
+        self
+    }
+
+    pub fn receipt_store(mut self, store: Arc<dyn ReceiptStore>) -> Self {
+        self.receipt_store = Some(store);
+        self
+    }
+
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    pub fn policy(mut self, policy: BundlePolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    pub fn dedup_index(mut self, index: Arc<dyn BundleIndex>) -> Self {
+        self.dedup_indexes.push(index);
+        self
+    }
+
+    pub fn gas_optimizer(mut self, optimizer: GasOptimizer) -> Self {
+        self.gas_optimizer = Some(optimizer);
+        self
+    }
+
+    pub fn spend_guard(mut self, guard: SpendGuard) -> Self {
+        self.spend_guard = Some(guard);
+        self
+    }
+
+    pub fn audit_log(mut self, log: Arc<dyn AuditLog>) -> Self {
+        self.audit_log = Some(log);
+        self
+    }
+
+    pub fn sidecar_sink(mut self, sink: Arc<dyn SidecarSink>) -> Self {
+        self.sidecar_sinks.push(sink);
+        self
+    }
+
+    pub fn check_policy(&self, index: usize, envelope: &TxEnvelopeWrapper) -> Result<(), Error> {
+        match &self.policy {
+            Some(policy) => policy.check(index, envelope),
+            None => Ok(()),
+        }
+    }
+
+    // envelopes in flight at once under the memory budget. Each one may hold its raw
+    // data, the signed tx and its compressed item, all up to `max_envelope_size`.
+    pub fn envelope_concurrency(&self) -> Option<usize> {
+        self.memory_budget
+            .map(|budget| (budget / self.max_envelope_size.max(1).saturating_mul(3)).max(1))
+    }
+
+    // log2 of the brotli sliding window, 10 to 24
+    pub fn compression_window(mut self, window: u32) -> Self {
+        self.compression.window = window;
+        self
+    }
+}
@@ -1,11 +1,25 @@
+use crate::utils::constants::BUNDLE_FORMAT_VERSION;
+use crate::utils::core::bundle_config::BundleConfig;
+use crate::utils::core::bundle_header::BundleHeader;
+use crate::utils::core::bundle_item::{BundleItem, EncodeBuffers, EncodedItem};
+use crate::utils::core::compression::Compression;
+use crate::utils::core::decode_config::DecodeConfig;
 use crate::utils::core::envelope::Envelope;
+use crate::utils::core::envelope_stats::EnvelopeStats;
+#[cfg(feature = "rayon")]
+use crate::utils::core::parallel;
+use crate::utils::core::tag::path_has_prefix;
 use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
 use crate::utils::errors::Error;
 use crate::utils::evm::create_envelope;
 use alloy::consensus::TxEnvelope;
+use alloy::primitives::{keccak256, Address, B256};
+use borsh::{from_slice, to_vec};
 use borsh_derive::{BorshDeserialize, BorshSerialize};
+use std::io::Write;
 
 #[derive(
+    Clone,
     Debug,
     Default,
     serde::Serialize,
@@ -16,11 +30,155 @@ use borsh_derive::{BorshDeserialize, BorshSerialize};
 )]
 pub struct BundleData {
     pub envelopes: Vec<TxEnvelopeWrapper>,
+    #[borsh(skip)]
+    #[serde(default)]
+    pub header: BundleHeader,
 }
 
 impl BundleData {
     pub fn from(envelopes: Vec<TxEnvelopeWrapper>) -> Self {
-        BundleData { envelopes }
+        BundleData {
+            envelopes,
+            header: BundleHeader::default(),
+        }
+    }
+
+    pub fn encode_calldata(&self) -> Vec<u8> {
+        self.encode_calldata_with(&BundleConfig::default()).unwrap()
+    }
+
+    // headed bundles compress each envelope on its own, see `BundleItem`
+    pub fn encode_calldata_with(&self, config: &BundleConfig) -> Result<Vec<u8>, Error> {
+        let mut calldata = Vec::new();
+        self.to_calldata_writer(&mut calldata, config)?;
+        Ok(calldata)
+    }
+
+    // calldata and what each envelope weighs in it, in bundle order
+    pub fn encode_calldata_with_stats(
+        &self,
+        config: &BundleConfig,
+    ) -> Result<(Vec<u8>, Vec<EnvelopeStats>), Error> {
+        let mut calldata = Vec::new();
+        let items = self.write_calldata(&mut calldata, config)?;
+        let stats = EnvelopeStats::collect(&self.envelopes, &items, calldata.len());
+        Ok((calldata, stats))
+    }
+
+    // items are written as soon as their envelope is compressed, no body is buffered and
+    // the scratch buffers are shared by all the envelopes
+    pub fn to_calldata_writer<W: Write>(
+        &self,
+        writer: W,
+        config: &BundleConfig,
+    ) -> Result<(), Error> {
+        self.write_calldata(writer, config).map(|_| ())
+    }
+
+    fn write_calldata<W: Write>(
+        &self,
+        mut writer: W,
+        config: &BundleConfig,
+    ) -> Result<Vec<EncodedItem>, Error> {
+        config.compression.validate()?;
+        let mut header = self.header.clone();
+        header.dictionary_id = config.dictionary.as_ref().map(|d| d.id.clone());
+        header.write_prefix(&mut writer)?;
+        writer.write_all(&(self.envelopes.len() as u32).to_le_bytes())?;
+        #[cfg(feature = "rayon")]
+        if self.envelopes.len() >= parallel::PARALLEL_MIN_ENVELOPES {
+            return parallel::write_items(&self.envelopes, config, &mut writer);
+        }
+        let mut buffers = EncodeBuffers::new();
+        self.envelopes
+            .iter()
+            .map(|envelope| BundleItem::write_envelope(envelope, config, &mut buffers, &mut writer))
+            .collect()
+    }
+
+    // calldata size, without holding the calldata
+    pub fn encoded_size_with(&self, config: &BundleConfig) -> Result<usize, Error> {
+        let mut counter = ByteCounter(0);
+        self.to_calldata_writer(&mut counter, config)?;
+        Ok(counter.0)
+    }
+
+    // signer of each envelope, in bundle order
+    pub fn signers(&self) -> Result<Vec<Address>, Error> {
+        self.envelopes
+            .iter()
+            .map(TxEnvelopeWrapper::recover_signer)
+            .collect()
+    }
+
+    // envelopes whose `Path` tag starts with `prefix`, with their position in the bundle
+    pub fn find_by_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (usize, &'a TxEnvelopeWrapper)> + 'a {
+        self.envelopes
+            .iter()
+            .enumerate()
+            .filter(move |(_, envelope)| {
+                envelope.path().is_some_and(|p| path_has_prefix(p, prefix))
+            })
+    }
+
+    pub fn decode_calldata(calldata: &[u8]) -> Result<Self, Error> {
+        Self::decode_calldata_with(calldata, &DecodeConfig::default())
+    }
+
+    pub fn decode_calldata_with(calldata: &[u8], config: &DecodeConfig) -> Result<Self, Error> {
+        let (version, header, body) = BundleHeader::split_versioned_calldata(calldata)?;
+        let Some(header) = header else {
+            // legacy bundles are a single brotli compressed borsh blob
+            let serialized =
+                Compression::Brotli.decompress_capped(body, None, config.max_decompressed_size)?;
+            return from_slice(&serialized).map_err(Error::DeserializationError);
+        };
+
+        let dictionary = match &header.dictionary_id {
+            Some(id) => Some(
+                config
+                    .find_dictionary(id)
+                    .ok_or_else(|| Error::UnknownDictionary(id.clone()))?,
+            ),
+            None => None,
+        };
+        let items = BundleItem::decode_items(version, body)?;
+        let mut remaining = config.max_decompressed_size;
+        let mut envelopes = Vec::with_capacity(items.len());
+        for (i, item) in items.into_iter().enumerate() {
+            let (envelope, size) =
+                item.into_envelope(dictionary, remaining)
+                    .map_err(|e| match e {
+                        Error::DecompressedSizeExceeded(_) => {
+                            Error::DecompressedSizeExceeded(config.max_decompressed_size)
+                        }
+                        _ => Error::CorruptedEnvelope(i),
+                    })?;
+            remaining -= size;
+            envelopes.push(envelope);
+        }
+        Ok(BundleData { envelopes, header })
+    }
+
+    // rewrites older bundle calldata in the current format. Items of headed bundles are
+    // kept as they are (no recompression), legacy bundles are decoded and re-encoded.
+    pub fn migrate_calldata(calldata: &[u8]) -> Result<Vec<u8>, Error> {
+        let (version, header, body) = BundleHeader::split_versioned_calldata(calldata)?;
+        match header {
+            _ if version == BUNDLE_FORMAT_VERSION => Ok(calldata.to_vec()),
+            Some(header) => {
+                let items = BundleItem::decode_items(version, body)?;
+                Ok(header.encode_calldata(&to_vec(&items)?))
+            }
+            None => Self::decode_calldata(calldata)?.encode_calldata_with(&BundleConfig::default()),
+        }
+    }
+
+    pub fn id(&self) -> B256 {
+        bundle_id(self)
     }
 
     pub async fn create_envelope(
@@ -30,3 +188,43 @@ impl BundleData {
         create_envelope(private_key, envelope).await
     }
 }
+
+// canonical bundle id, independent of the carrier tx (and so stable across resubmissions)
+// and of how the envelopes are compressed: the header stream and idempotency key, then
+// the digest of every envelope in bundle order
+pub fn bundle_id(bundle: &BundleData) -> B256 {
+    let digests: Vec<B256> = bundle.envelopes.iter().map(envelope_digest).collect();
+    bundle_id_from_digests(&bundle.header, &digests)
+}
+
+// the signed envelope fields, its tags and extensions (when there are any, so that ids
+// of bundles older than extensions are unchanged)
+pub fn envelope_digest(envelope: &TxEnvelopeWrapper) -> B256 {
+    let mut preimage = to_vec(envelope).unwrap();
+    preimage.extend_from_slice(&to_vec(&envelope.tags).unwrap());
+    if !envelope.extensions.is_empty() {
+        preimage.extend_from_slice(&to_vec(&envelope.extensions).unwrap());
+    }
+    keccak256(preimage)
+}
+
+pub fn bundle_id_from_digests(header: &BundleHeader, digests: &[B256]) -> B256 {
+    let mut preimage = to_vec(&(&header.stream, &header.idempotency_key)).unwrap();
+    for digest in digests {
+        preimage.extend_from_slice(digest.as_slice());
+    }
+    keccak256(preimage)
+}
+
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
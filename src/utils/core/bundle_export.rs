@@ -0,0 +1,99 @@
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::bundle_header::BundleHeader;
+use crate::utils::core::compression::Compression;
+use crate::utils::core::tag::Tag;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use base64::Engine;
+use serde::Serialize;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PayloadEncoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+// explorer-friendly view of an envelope, free of borsh and wrapper details
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedEnvelope {
+    pub index: usize,
+    pub id: String,
+    // `None` when the envelope signature can't be recovered
+    pub signer: Option<String>,
+    pub target: String,
+    pub tags: Vec<Tag>,
+    pub compression: Compression,
+    pub size: usize,
+    pub payload: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ExportedBundle<'a> {
+    header: &'a BundleHeader,
+    envelopes: Vec<ExportedEnvelope>,
+}
+
+impl ExportedEnvelope {
+    pub fn new(
+        index: usize,
+        envelope: &TxEnvelopeWrapper,
+        encoding: PayloadEncoding,
+    ) -> Result<Self, Error> {
+        let data = alloy::hex::decode(&envelope.input)?;
+        let payload = match encoding {
+            PayloadEncoding::Hex => alloy::hex::encode_prefixed(&data),
+            PayloadEncoding::Base64 => base64::engine::general_purpose::STANDARD.encode(&data),
+        };
+        Ok(ExportedEnvelope {
+            index,
+            id: envelope.hash.clone(),
+            signer: envelope.recover_signer().ok().map(|a| a.to_string()),
+            target: envelope.to.clone(),
+            tags: envelope.tags.clone(),
+            compression: envelope.compression,
+            size: data.len(),
+            payload,
+        })
+    }
+}
+
+impl BundleData {
+    pub fn exported_envelopes(
+        &self,
+        encoding: PayloadEncoding,
+    ) -> Result<Vec<ExportedEnvelope>, Error> {
+        self.envelopes
+            .iter()
+            .enumerate()
+            .map(|(i, envelope)| ExportedEnvelope::new(i, envelope, encoding))
+            .collect()
+    }
+
+    pub fn to_json_pretty(&self) -> Result<String, Error> {
+        self.to_json_pretty_with(PayloadEncoding::default())
+    }
+
+    pub fn to_json_pretty_with(&self, encoding: PayloadEncoding) -> Result<String, Error> {
+        let bundle = ExportedBundle {
+            header: &self.header,
+            envelopes: self.exported_envelopes(encoding)?,
+        };
+        Ok(serde_json::to_string_pretty(&bundle)?)
+    }
+
+    // one JSON object per envelope and line
+    pub fn to_ndjson_writer<W: Write>(
+        &self,
+        writer: &mut W,
+        encoding: PayloadEncoding,
+    ) -> Result<(), Error> {
+        for (i, envelope) in self.envelopes.iter().enumerate() {
+            let exported = ExportedEnvelope::new(i, envelope, encoding)?;
+            serde_json::to_writer(&mut *writer, &exported)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
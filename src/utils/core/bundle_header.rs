@@ -0,0 +1,96 @@
+use crate::utils::constants::{BUNDLE_FORMAT_V1, BUNDLE_FORMAT_VERSION, BUNDLE_MAGIC};
+use crate::utils::core::stream::StreamPointer;
+use crate::utils::errors::Error;
+use borsh::from_slice;
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use std::io::Write;
+
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+    PartialEq,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+pub struct BundleHeader {
+    pub stream: Option<StreamPointer>,
+    pub dictionary_id: Option<String>,
+    // shared by every resubmission of the same bundle, for consumers to dedupe
+    pub idempotency_key: Option<String>,
+}
+
+impl BundleHeader {
+    pub fn new() -> Self {
+        BundleHeader {
+            stream: None,
+            dictionary_id: None,
+            idempotency_key: None,
+        }
+    }
+
+    pub fn random_idempotency_key() -> String {
+        hex::encode(rand::random::<[u8; 16]>())
+    }
+
+    // calldata layout: magic | version (u8) | header length (u32 LE) | borsh header | body
+    pub fn encode_calldata(&self, body: &[u8]) -> Vec<u8> {
+        let mut calldata = self.calldata_prefix(body.len());
+        calldata.extend_from_slice(body);
+        calldata
+    }
+
+    // calldata up to the body, with room reserved for a body of `body_len` bytes
+    pub fn calldata_prefix(&self, body_len: usize) -> Vec<u8> {
+        let header_len = borsh::object_length(self).unwrap();
+        let mut calldata = Vec::with_capacity(BUNDLE_MAGIC.len() + 5 + header_len + body_len);
+        self.write_prefix(&mut calldata).unwrap();
+        calldata
+    }
+
+    pub fn write_prefix<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let header_len = borsh::object_length(self)? as u32;
+        writer.write_all(&BUNDLE_MAGIC)?;
+        writer.write_all(&[BUNDLE_FORMAT_VERSION])?;
+        writer.write_all(&header_len.to_le_bytes())?;
+        borsh::to_writer(writer, self)
+    }
+
+    // returns `None` as header for legacy (headerless) bundles
+    pub fn split_calldata(calldata: &[u8]) -> Result<(Option<BundleHeader>, &[u8]), Error> {
+        let (_, header, body) = Self::split_versioned_calldata(calldata)?;
+        Ok((header, body))
+    }
+
+    // also returns the bundle format version, 0 for legacy bundles
+    pub fn split_versioned_calldata(
+        calldata: &[u8],
+    ) -> Result<(u8, Option<BundleHeader>, &[u8]), Error> {
+        let Some(rest) = calldata.strip_prefix(&BUNDLE_MAGIC[..]) else {
+            return Ok((0, None, calldata));
+        };
+        let (&version, rest) = rest.split_first().ok_or(Error::InvalidBundleHeader)?;
+        check_version(version)?;
+        if rest.len() < 4 {
+            return Err(Error::InvalidBundleHeader);
+        }
+        let (len, rest) = rest.split_at(4);
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        if rest.len() < len {
+            return Err(Error::InvalidBundleHeader);
+        }
+        let (header, body) = rest.split_at(len);
+        let header: BundleHeader = from_slice(header).map_err(|_| Error::InvalidBundleHeader)?;
+        Ok((version, Some(header), body))
+    }
+}
+
+// every headed format version up to the current one is decoded
+pub fn check_version(version: u8) -> Result<(), Error> {
+    if !(BUNDLE_FORMAT_V1..=BUNDLE_FORMAT_VERSION).contains(&version) {
+        return Err(Error::UnsupportedBundleVersion(version));
+    }
+    Ok(())
+}
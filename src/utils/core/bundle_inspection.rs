@@ -0,0 +1,97 @@
+use crate::utils::core::bundle_header::BundleHeader;
+use crate::utils::core::bundle_item::BundleItem;
+use crate::utils::core::compression::Compression;
+use crate::utils::core::envelope_kind::EnvelopeKind;
+use crate::utils::errors::Error;
+use alloy::primitives::{keccak256, B256};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemSummary {
+    pub compression: Compression,
+    // payload size in the calldata, compressed
+    pub size: usize,
+    pub tag_count: usize,
+    pub extension_count: usize,
+    // `None` for items written without a checksum
+    #[serde(default)]
+    pub checksum_ok: Option<bool>,
+    #[serde(default)]
+    pub kind: EnvelopeKind,
+}
+
+// what the calldata tells about a bundle without decompressing any envelope
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BundleInspection {
+    // 0 for legacy (headerless) bundles
+    pub version: u8,
+    pub header: Option<BundleHeader>,
+    pub calldata_size: usize,
+    // legacy bundles are a single brotli blob, their envelopes can't be counted
+    pub envelope_count: Option<usize>,
+    pub items: Vec<ItemSummary>,
+    pub merkle_root: Option<B256>,
+}
+
+impl BundleInspection {
+    pub fn from_calldata(calldata: &[u8]) -> Result<Self, Error> {
+        let (version, header, body) = BundleHeader::split_versioned_calldata(calldata)?;
+        let Some(header) = header else {
+            return Ok(BundleInspection {
+                version: 0,
+                header: None,
+                calldata_size: calldata.len(),
+                envelope_count: None,
+                items: vec![],
+                merkle_root: None,
+            });
+        };
+
+        let items = BundleItem::decode_items(version, body)?;
+        Ok(BundleInspection {
+            version,
+            header: Some(header),
+            calldata_size: calldata.len(),
+            envelope_count: Some(items.len()),
+            items: items
+                .iter()
+                .map(|item| ItemSummary {
+                    compression: item.compression,
+                    size: item.data.len(),
+                    tag_count: item.tags.len(),
+                    extension_count: item.extensions.len(),
+                    checksum_ok: item.checksum.map(|_| item.checksum_matches()),
+                    kind: EnvelopeKind::from_extensions(&item.extensions),
+                })
+                .collect(),
+            merkle_root: Some(items_root(&items)),
+        })
+    }
+
+    // indices of the items whose data does not match their checksum
+    pub fn corrupted_items(&self) -> Vec<usize> {
+        (0..self.items.len())
+            .filter(|&i| self.items[i].checksum_ok == Some(false))
+            .collect()
+    }
+}
+
+// binary keccak256 tree over the items payloads, an odd node being carried up as is.
+// Commits to the bundle content as stored, so it is computed without decompression.
+pub fn items_root(items: &[BundleItem]) -> B256 {
+    let mut level: Vec<B256> = items.iter().map(|item| keccak256(&item.data)).collect();
+    if level.is_empty() {
+        return B256::ZERO;
+    }
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => keccak256([left.as_slice(), right.as_slice()].concat()),
+                [node] => *node,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level[0]
+}
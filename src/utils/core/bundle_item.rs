@@ -0,0 +1,349 @@
+use crate::utils::constants::{BUNDLE_FORMAT_V1, ENVELOPE_FORMAT_V1, ENVELOPE_FORMAT_VERSION};
+use crate::utils::core::bundle_config::BundleConfig;
+use crate::utils::core::compression::{Compression, CompressionParams};
+use crate::utils::core::dictionary::Dictionary;
+use crate::utils::core::extension::Extension;
+use crate::utils::core::tag::Tag;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use alloy::primitives::Keccak256;
+use borsh::{from_slice, BorshDeserialize as _};
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use std::io::{Read, Write};
+
+const INCOMPRESSIBLE_SAMPLE_SIZE: usize = 16_384;
+pub const CHECKSUM_SIZE: usize = 8;
+
+pub type ItemChecksum = [u8; CHECKSUM_SIZE];
+
+// wire representation of an envelope inside a headed bundle body:
+// version (u8) | compression | data | tags | extension section (u32 LE length | bytes).
+// The fields before the section are frozen, new optional fields are added as extensions
+// and newer versions may append to the section, past the extensions older readers know.
+// Version 2 appends the item checksum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BundleItem {
+    pub version: u8,
+    pub compression: Compression,
+    pub data: Vec<u8>,
+    pub tags: Vec<Tag>,
+    pub extensions: Vec<Extension>,
+    // `None` for items written before version 2
+    pub checksum: Option<ItemChecksum>,
+}
+
+impl borsh::BorshSerialize for BundleItem {
+    fn serialize<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        write_item(
+            writer,
+            self.version,
+            self.compression,
+            &self.data,
+            &self.tags,
+            &self.extensions,
+            self.checksum.as_ref(),
+        )
+    }
+}
+
+// the item layout, written from borrowed parts so encoders need no owned item
+fn write_item<W: Write>(
+    writer: &mut W,
+    version: u8,
+    compression: Compression,
+    data: &[u8],
+    tags: &[Tag],
+    extensions: &[Extension],
+    checksum: Option<&ItemChecksum>,
+) -> std::io::Result<()> {
+    let checksum = checksum.map_or(&[][..], |checksum| &checksum[..]);
+    let section_len = (borsh::object_length(extensions)? + checksum.len()) as u32;
+    borsh::to_writer(
+        &mut *writer,
+        &(version, compression, data, tags, section_len, extensions),
+    )?;
+    writer.write_all(checksum)
+}
+
+struct HashWriter(Keccak256);
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// truncated keccak of everything the item stores but its version and checksum, so a
+// corrupted payload is caught before decompression and pinned to its item
+pub fn item_checksum(
+    compression: Compression,
+    data: &[u8],
+    tags: &[Tag],
+    extensions: &[Extension],
+) -> ItemChecksum {
+    let mut hasher = HashWriter(Keccak256::new());
+    borsh::to_writer(&mut hasher, &(compression, data, tags, extensions)).unwrap();
+    let mut checksum = [0; CHECKSUM_SIZE];
+    checksum.copy_from_slice(&hasher.0.finalize()[..CHECKSUM_SIZE]);
+    checksum
+}
+
+impl borsh::BorshDeserialize for BundleItem {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let version = u8::deserialize_reader(reader)?;
+        let compression = Compression::deserialize_reader(reader)?;
+        let data = Vec::<u8>::deserialize_reader(reader)?;
+        let tags = Vec::<Tag>::deserialize_reader(reader)?;
+        let section = Vec::<u8>::deserialize_reader(reader)?;
+        let mut section = section.as_slice();
+        let extensions = Vec::<Extension>::deserialize(&mut section)?;
+        // from version 2 on the checksum is required, or truncating the section would
+        // be enough to skip the check
+        let checksum = match section.get(..CHECKSUM_SIZE) {
+            _ if version <= ENVELOPE_FORMAT_V1 => None,
+            Some(checksum) => checksum.try_into().ok(),
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "item checksum is missing",
+                ))
+            }
+        };
+        Ok(BundleItem {
+            version,
+            compression,
+            data,
+            tags,
+            extensions,
+            checksum,
+        })
+    }
+}
+
+// item layout of bundle format version 1, before envelope versions and extensions
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct BundleItemV1 {
+    pub compression: Compression,
+    pub data: Vec<u8>,
+    pub tags: Vec<Tag>,
+}
+
+// in the current format, so migrated items are checksummed
+impl From<BundleItemV1> for BundleItem {
+    fn from(item: BundleItemV1) -> Self {
+        let checksum = item_checksum(item.compression, &item.data, &item.tags, &[]);
+        BundleItem {
+            version: ENVELOPE_FORMAT_VERSION,
+            compression: item.compression,
+            data: item.data,
+            tags: item.tags,
+            extensions: vec![],
+            checksum: Some(checksum),
+        }
+    }
+}
+
+// scratch space of the encode path, kept across the envelopes of a bundle so that each
+// one reuses the allocations of the previous ones
+#[derive(Debug, Default)]
+pub struct EncodeBuffers {
+    serialized: Vec<u8>,
+    compressed: Vec<u8>,
+}
+
+impl EncodeBuffers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn payload(&self, compression: Compression) -> &[u8] {
+        match compression {
+            Compression::None => &self.serialized,
+            _ => &self.compressed,
+        }
+    }
+
+    fn take_payload(&mut self, compression: Compression) -> Vec<u8> {
+        match compression {
+            Compression::None => std::mem::take(&mut self.serialized),
+            _ => std::mem::take(&mut self.compressed),
+        }
+    }
+}
+
+// sizes of an item written by `BundleItem::write_envelope`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EncodedItem {
+    pub compression: Compression,
+    // borsh serialized envelope
+    pub serialized_size: usize,
+    // payload as stored, after compression
+    pub stored_size: usize,
+    // whole item, with its tags and extensions
+    pub item_size: usize,
+}
+
+impl BundleItem {
+    pub fn from_envelope(
+        envelope: &TxEnvelopeWrapper,
+        config: &BundleConfig,
+    ) -> Result<Self, Error> {
+        let mut buffers = EncodeBuffers::new();
+        let compression = Self::encode_payload(envelope, config, &mut buffers)?;
+        let data = buffers.take_payload(compression);
+        let checksum = item_checksum(compression, &data, &envelope.tags, &envelope.extensions);
+        Ok(BundleItem {
+            version: ENVELOPE_FORMAT_VERSION,
+            compression,
+            data,
+            tags: envelope.tags.clone(),
+            extensions: envelope.extensions.clone(),
+            checksum: Some(checksum),
+        })
+    }
+
+    pub fn checksum_matches(&self) -> bool {
+        self.checksum.is_none_or(|checksum| {
+            checksum == item_checksum(self.compression, &self.data, &self.tags, &self.extensions)
+        })
+    }
+
+    // encodes the envelope item straight into `writer`, returns what it was stored as
+    pub fn write_envelope<W: Write>(
+        envelope: &TxEnvelopeWrapper,
+        config: &BundleConfig,
+        buffers: &mut EncodeBuffers,
+        writer: &mut W,
+    ) -> Result<EncodedItem, Error> {
+        let compression = Self::encode_payload(envelope, config, buffers)?;
+        let payload = buffers.payload(compression);
+        let checksum = item_checksum(compression, payload, &envelope.tags, &envelope.extensions);
+        write_item(
+            writer,
+            ENVELOPE_FORMAT_VERSION,
+            compression,
+            payload,
+            &envelope.tags,
+            &envelope.extensions,
+            Some(&checksum),
+        )?;
+        // version, compression, the two u32 length prefixes of data and the section, and
+        // the checksum
+        let item_size = 10
+            + CHECKSUM_SIZE
+            + payload.len()
+            + borsh::object_length(&envelope.tags)?
+            + borsh::object_length(&envelope.extensions)?;
+        Ok(EncodedItem {
+            compression,
+            serialized_size: buffers.serialized.len(),
+            stored_size: payload.len(),
+            item_size,
+        })
+    }
+
+    // leaves the stored payload in `buffers`. Brotli envelopes use the bundle dictionary
+    // when there is one.
+    fn encode_payload(
+        envelope: &TxEnvelopeWrapper,
+        config: &BundleConfig,
+        buffers: &mut EncodeBuffers,
+    ) -> Result<Compression, Error> {
+        let dictionary = config.dictionary.as_ref();
+        let compression = match (envelope.compression, dictionary) {
+            (Compression::Brotli, Some(_)) => Compression::BrotliDictionary,
+            (compression, _) => compression,
+        };
+        buffers.serialized.clear();
+        borsh::to_writer(&mut buffers.serialized, envelope)?;
+        if compression == Compression::None {
+            return Ok(compression);
+        }
+        let serialized = &buffers.serialized;
+        buffers.compressed.clear();
+        let Some(ratio) = config.incompressible_ratio else {
+            compression.compress_into(
+                serialized,
+                &config.compression,
+                dictionary,
+                &mut buffers.compressed,
+            )?;
+            return Ok(compression);
+        };
+
+        // probe large envelopes with a fast pass over a sample before the real compression
+        if serialized.len() > INCOMPRESSIBLE_SAMPLE_SIZE {
+            let sample = &serialized[..INCOMPRESSIBLE_SAMPLE_SIZE];
+            let probe = CompressionParams {
+                quality: 1,
+                window: 16,
+            };
+            Compression::Brotli.compress_into(sample, &probe, None, &mut buffers.compressed)?;
+            if buffers.compressed.len() as f64 > sample.len() as f64 * ratio {
+                return Ok(Compression::None);
+            }
+            buffers.compressed.clear();
+        }
+
+        compression.compress_into(
+            serialized,
+            &config.compression,
+            dictionary,
+            &mut buffers.compressed,
+        )?;
+        if buffers.compressed.len() as f64 > serialized.len() as f64 * ratio {
+            return Ok(Compression::None);
+        }
+        Ok(compression)
+    }
+
+    // items of a headed bundle body, `bundle_version` being its header format version. An
+    // item that can't be read is reported by its index.
+    pub fn decode_items(bundle_version: u8, body: &[u8]) -> Result<Vec<BundleItem>, Error> {
+        let mut reader = body;
+        let count = u32::deserialize_reader(&mut reader).map_err(Error::DeserializationError)?;
+        // the count is not trusted for the allocation
+        let mut items = Vec::with_capacity((count as usize).min(body.len()));
+        for index in 0..count as usize {
+            let item = match bundle_version {
+                BUNDLE_FORMAT_V1 => {
+                    BundleItemV1::deserialize_reader(&mut reader).map(BundleItem::from)
+                }
+                _ => BundleItem::deserialize_reader(&mut reader),
+            };
+            items.push(item.map_err(|_| Error::CorruptedEnvelope(index))?);
+        }
+        if !reader.is_empty() {
+            return Err(Error::DeserializationError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "bytes left after the bundle items",
+            )));
+        }
+        Ok(items)
+    }
+
+    // `max_size` caps the decompressed envelope size
+    pub fn into_envelope(
+        self,
+        dictionary: Option<&Dictionary>,
+        max_size: usize,
+    ) -> Result<(TxEnvelopeWrapper, usize), Error> {
+        if !self.checksum_matches() {
+            return Err(Error::ChecksumMismatch);
+        }
+        let serialized = self
+            .compression
+            .decompress_capped(&self.data, dictionary, max_size)?;
+        let mut envelope: TxEnvelopeWrapper =
+            from_slice(&serialized).map_err(Error::DeserializationError)?;
+        envelope.compression = self.compression;
+        envelope.tags = self.tags;
+        envelope.extensions = self.extensions;
+        Ok((envelope, serialized.len()))
+    }
+}
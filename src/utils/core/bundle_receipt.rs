@@ -0,0 +1,47 @@
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::gas_cost::OptimizedEncoding;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleReceipt {
+    pub bundle_txid: String,
+    // envelope tx hashes, in bundle order
+    pub envelope_ids: Vec<String>,
+    // of the bundle header, a later submission with the same key reuses this receipt
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    // `BundleData::id`, hex encoded
+    #[serde(default)]
+    pub bundle_id: Option<String>,
+    // compression chosen by the `GasOptimizer`, when there is one
+    #[serde(default)]
+    pub encoding: Option<OptimizedEncoding>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleLocation {
+    pub bundle_txid: String,
+    pub index: usize,
+}
+
+impl BundleReceipt {
+    pub fn new(bundle_txid: String, bundle: &BundleData) -> Self {
+        BundleReceipt {
+            bundle_txid,
+            envelope_ids: bundle.envelopes.iter().map(|e| e.hash.clone()).collect(),
+            idempotency_key: bundle.header.idempotency_key.clone(),
+            bundle_id: Some(bundle.id().to_string()),
+            encoding: None,
+        }
+    }
+
+    pub fn locate(&self, envelope_id: &str) -> Option<BundleLocation> {
+        self.envelope_ids
+            .iter()
+            .position(|id| id.eq_ignore_ascii_case(envelope_id))
+            .map(|index| BundleLocation {
+                bundle_txid: self.bundle_txid.clone(),
+                index,
+            })
+    }
+}
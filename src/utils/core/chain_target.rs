@@ -0,0 +1,63 @@
+use crate::utils::constants::{ADDRESS_BABE1, CHAIN_ID, WVM_RPC_URL};
+use crate::utils::errors::Error;
+use crate::utils::gas_oracle::GasOracle;
+use std::sync::Arc;
+
+// EVM chain the bundle tx is posted to. Envelopes keep the WeaveVM chain id whatever
+// the target, they are never broadcast on their own.
+#[derive(Debug, Clone)]
+pub struct ChainTarget {
+    pub name: String,
+    // http(s) or ws(s) url, or an IPC socket path for a node on the same host
+    pub rpc_url: String,
+    pub chain_id: u64,
+    pub bundle_address: String,
+    // fee rules of this chain, the bundle config ones apply when unset
+    pub gas_oracle: Option<Arc<dyn GasOracle>>,
+    pub gas_limit: Option<u64>,
+}
+
+impl Default for ChainTarget {
+    fn default() -> Self {
+        Self::weavevm()
+    }
+}
+
+impl ChainTarget {
+    pub fn new(name: &str, rpc_url: &str, chain_id: u64, bundle_address: &str) -> Self {
+        ChainTarget {
+            name: name.to_string(),
+            rpc_url: rpc_url.to_string(),
+            chain_id,
+            bundle_address: bundle_address.to_string(),
+            gas_oracle: None,
+            gas_limit: None,
+        }
+    }
+
+    pub fn weavevm() -> Self {
+        Self::new("weavevm", WVM_RPC_URL, CHAIN_ID, ADDRESS_BABE1)
+    }
+
+    pub fn rpc_url(mut self, rpc_url: &str) -> Self {
+        self.rpc_url = rpc_url.to_string();
+        self
+    }
+
+    pub fn gas_oracle(mut self, gas_oracle: impl GasOracle + 'static) -> Self {
+        self.gas_oracle = Some(Arc::new(gas_oracle));
+        self
+    }
+
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = Some(gas_limit);
+        self
+    }
+}
+
+// receipt of a bundle posted to one of several targets
+#[derive(Debug)]
+pub struct TargetSubmission {
+    pub target: String,
+    pub txid: Result<String, Error>,
+}
@@ -0,0 +1,129 @@
+use crate::utils::core::dictionary::Dictionary;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use std::io::Read;
+
+// bundle calldata is capped at 9 MB, this leaves room for very compressible envelopes
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
+
+// reads a decompressing reader to its end, failing as soon as it yields more than `max_size`
+pub fn read_capped<R: Read>(reader: R, max_size: usize) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    reader
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut output)
+        .map_err(Error::DecompressionError)?;
+    if output.len() > max_size {
+        return Err(Error::DecompressedSizeExceeded(max_size));
+    }
+    Ok(output)
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+    PartialEq,
+    Eq,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[borsh(use_discriminant = true)]
+pub enum Compression {
+    None = 0,
+    #[default]
+    Brotli = 1,
+    // brotli with the shared dictionary referenced by the bundle header
+    BrotliDictionary = 2,
+}
+
+// brotli quality (0-11) and window size as log2 (10-24)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CompressionParams {
+    pub quality: u32,
+    pub window: u32,
+}
+
+impl Default for CompressionParams {
+    fn default() -> Self {
+        CompressionParams {
+            quality: 9,
+            window: 22,
+        }
+    }
+}
+
+impl CompressionParams {
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.quality > 11 || !(10..=24).contains(&self.window) {
+            return Err(Error::InvalidCompressionParams);
+        }
+        Ok(())
+    }
+}
+
+impl Compression {
+    pub fn compress(
+        &self,
+        input: &[u8],
+        params: &CompressionParams,
+        dictionary: Option<&Dictionary>,
+    ) -> Result<Vec<u8>, Error> {
+        let mut output = Vec::new();
+        self.compress_into(input, params, dictionary, &mut output)?;
+        Ok(output)
+    }
+
+    // appends the compressed input to `output`, whose allocation can be reused
+    pub fn compress_into(
+        &self,
+        input: &[u8],
+        params: &CompressionParams,
+        dictionary: Option<&Dictionary>,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        match self {
+            Compression::None => output.extend_from_slice(input),
+            Compression::Brotli => TxEnvelopeWrapper::brotli_compress_into(
+                input,
+                params.quality,
+                params.window,
+                output,
+            ),
+            Compression::BrotliDictionary => dictionary
+                .ok_or(Error::DictionaryNeeded)?
+                .compress_into(input, params, output),
+        }
+        Ok(())
+    }
+
+    pub fn decompress(
+        &self,
+        input: &[u8],
+        dictionary: Option<&Dictionary>,
+    ) -> Result<Vec<u8>, Error> {
+        self.decompress_capped(input, dictionary, DEFAULT_MAX_DECOMPRESSED_SIZE)
+    }
+
+    pub fn decompress_capped(
+        &self,
+        input: &[u8],
+        dictionary: Option<&Dictionary>,
+        max_size: usize,
+    ) -> Result<Vec<u8>, Error> {
+        match self {
+            Compression::None if input.len() > max_size => {
+                Err(Error::DecompressedSizeExceeded(max_size))
+            }
+            Compression::None => Ok(input.to_vec()),
+            Compression::Brotli => read_capped(brotli::Decompressor::new(input, 32_768), max_size),
+            Compression::BrotliDictionary => dictionary
+                .ok_or(Error::DictionaryNeeded)?
+                .decompress_capped(input, max_size),
+        }
+    }
+}
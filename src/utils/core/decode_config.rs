@@ -0,0 +1,89 @@
+use crate::utils::cancellation::CancellationToken;
+use crate::utils::core::compression::DEFAULT_MAX_DECOMPRESSED_SIZE;
+use crate::utils::core::dictionary::Dictionary;
+use crate::utils::core::policy::BundlePolicy;
+
+// checks applied to the envelopes of a decoded bundle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationMode {
+    // zero nonce, gas limit and gas price, WeaveVM chain id
+    #[default]
+    Strict,
+    // only the chain id, for bundles that used other nonce/gas conventions
+    Lenient,
+    Skip,
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodeConfig {
+    pub dictionaries: Vec<Dictionary>,
+    // total decompressed size of a bundle, guards indexers against decompression bombs
+    pub max_decompressed_size: usize,
+    pub validation: ValidationMode,
+    // checked instead of the `validation` rules when set
+    pub policy: Option<BundlePolicy>,
+    // stops a retrieval waiting on the RPC once cancelled
+    pub cancellation: Option<CancellationToken>,
+    // leaves the raw calldata in the metadata returned by `retrieve_bundle_with`
+    pub keep_calldata: bool,
+}
+
+impl Default for DecodeConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DecodeConfig {
+    pub fn new() -> Self {
+        DecodeConfig {
+            dictionaries: vec![],
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+            validation: ValidationMode::default(),
+            policy: None,
+            cancellation: None,
+            keep_calldata: false,
+        }
+    }
+
+    pub fn max_decompressed_size(mut self, max_size: usize) -> Self {
+        self.max_decompressed_size = max_size;
+        self
+    }
+
+    pub fn dictionary(mut self, dictionary: Dictionary) -> Self {
+        self.dictionaries.push(dictionary);
+        self
+    }
+
+    pub fn validation(mut self, mode: ValidationMode) -> Self {
+        self.validation = mode;
+        self
+    }
+
+    // start from `BundlePolicy::strict()` to keep the envelope MUSTs
+    pub fn policy(mut self, policy: BundlePolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    pub fn envelope_policy(&self) -> BundlePolicy {
+        self.policy
+            .clone()
+            .unwrap_or_else(|| self.validation.into())
+    }
+
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    pub fn keep_calldata(mut self, keep: bool) -> Self {
+        self.keep_calldata = keep;
+        self
+    }
+
+    pub fn find_dictionary(&self, id: &str) -> Option<&Dictionary> {
+        self.dictionaries.iter().find(|d| d.id == id)
+    }
+}
@@ -0,0 +1,76 @@
+use crate::utils::core::compression::{
+    read_capped, CompressionParams, DEFAULT_MAX_DECOMPRESSED_SIZE,
+};
+use crate::utils::errors::Error;
+use {
+    alloy::primitives::keccak256,
+    brotli::enc::{
+        interface::{PredictionModeContextMap, StaticCommand},
+        BrotliEncoderParams, StandardAlloc,
+    },
+    brotli::{InputPair, InputReferenceMut, IoReaderWrapper, IoWriterWrapper},
+    std::io,
+};
+
+// shared brotli dictionary, identified by the keccak256 hash of its bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dictionary {
+    pub id: String,
+    pub bytes: Vec<u8>,
+    // envelope inputs are hex encoded within the wrapper, so is the dictionary fed to brotli
+    encoded: Vec<u8>,
+}
+
+impl Dictionary {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Dictionary {
+            id: keccak256(&bytes).to_string(),
+            encoded: hex::encode(&bytes).into_bytes(),
+            bytes,
+        }
+    }
+
+    pub fn compress(&self, input: &[u8], params: &CompressionParams) -> Vec<u8> {
+        let mut output = Vec::new();
+        self.compress_into(input, params, &mut output);
+        output
+    }
+
+    // appends the compressed input to `output`
+    pub fn compress_into(&self, input: &[u8], params: &CompressionParams, output: &mut Vec<u8>) {
+        let params = BrotliEncoderParams {
+            quality: params.quality as i32,
+            lgwin: params.window as i32,
+            ..Default::default()
+        };
+        let mut input_buffer = [0u8; 65_536];
+        let mut output_buffer = [0u8; 65_536];
+        let mut nop_callback = |_: &mut PredictionModeContextMap<InputReferenceMut>,
+                                _: &mut [StaticCommand],
+                                _: InputPair,
+                                _: &mut StandardAlloc| ();
+
+        brotli::BrotliCompressCustomIoCustomDict(
+            &mut IoReaderWrapper(&mut &input[..]),
+            &mut IoWriterWrapper(output),
+            &mut input_buffer,
+            &mut output_buffer,
+            &params,
+            StandardAlloc::default(),
+            &mut nop_callback,
+            &self.encoded,
+            io::Error::new(io::ErrorKind::UnexpectedEof, "Unexpected EOF"),
+        )
+        .unwrap();
+    }
+
+    pub fn decompress(&self, input: &[u8]) -> Result<Vec<u8>, Error> {
+        self.decompress_capped(input, DEFAULT_MAX_DECOMPRESSED_SIZE)
+    }
+
+    pub fn decompress_capped(&self, input: &[u8], max_size: usize) -> Result<Vec<u8>, Error> {
+        let decompressor =
+            brotli::Decompressor::new_with_custom_dict(input, 32_768, self.encoded.clone().into());
+        read_capped(decompressor, max_size)
+    }
+}
@@ -1,16 +1,40 @@
+use crate::utils::core::bundle_config::{BundleConfig, DEFAULT_MAX_ENVELOPE_SIZE};
+use crate::utils::core::compression::Compression;
+use crate::utils::core::envelope_builder::EnvelopeBuilder;
+use crate::utils::core::envelope_kind::{set_envelope_kind, EnvelopeKind};
+use crate::utils::core::extension::{set_extension, Extension};
+use crate::utils::core::tag::{
+    find_tag, is_valid_path, set_tag, Tag, EXPIRES_AT_TAG, MAX_TAGS_PER_ENVELOPE, PATH_TAG,
+    RETENTION_CLASS_TAG,
+};
+use crate::utils::errors::{EnvelopeError, Error};
+use alloy::primitives::{Address, Bytes};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Envelope {
     pub data: Option<Vec<u8>>,
     pub target: Option<String>,
+    #[serde(default)]
+    pub compression: Compression,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub extensions: Vec<Extension>,
 }
 
 impl Envelope {
+    pub fn builder() -> EnvelopeBuilder {
+        EnvelopeBuilder::new()
+    }
+
     pub fn new() -> Self {
         Self {
             data: None,
             target: None,
+            compression: Compression::default(),
+            tags: vec![],
+            extensions: vec![],
         }
     }
 
@@ -24,15 +48,144 @@ impl Envelope {
         self
     }
 
-    pub fn build(self) -> eyre::Result<Self> {
-        let data = self
-            .clone()
-            .data
-            .ok_or_else(|| eyre::eyre!("data field is required"))?;
-        assert_ne!(data.len(), 0);
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    // stored as the kind extension, see `EnvelopeKind`
+    pub fn kind(mut self, kind: EnvelopeKind) -> Self {
+        set_envelope_kind(&mut self.extensions, kind);
+        self
+    }
+
+    // pure data, without target
+    pub fn data_only(self) -> Self {
+        self.kind(EnvelopeKind::Data)
+    }
+
+    pub fn envelope_kind(&self) -> EnvelopeKind {
+        EnvelopeKind::from_extensions(&self.extensions)
+    }
+
+    pub fn tags(mut self, tags: Vec<Tag>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn add_tag(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push(Tag::new(name, value));
+        self
+    }
+
+    // replaces any previous extension of the same id
+    pub fn extension(mut self, id: u16, data: impl Into<Vec<u8>>) -> Self {
+        set_extension(&mut self.extensions, id, data);
+        self
+    }
+
+    // unix timestamp in seconds, stored as the `Expires-At` tag
+    pub fn expires_at(mut self, timestamp: u64) -> Self {
+        set_tag(&mut self.tags, EXPIRES_AT_TAG, timestamp.to_string());
+        self
+    }
+
+    pub fn retention_class(mut self, class: impl Into<String>) -> Self {
+        set_tag(&mut self.tags, RETENTION_CLASS_TAG, class);
+        self
+    }
+
+    // stored as the `Path` tag, see `PathIndex`
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        set_tag(&mut self.tags, PATH_TAG, path);
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), EnvelopeError> {
+        self.validate_max_size(DEFAULT_MAX_ENVELOPE_SIZE)
+    }
+
+    pub fn validate_with(&self, config: &BundleConfig) -> Result<(), EnvelopeError> {
+        self.validate_max_size(config.max_envelope_size)
+    }
+
+    pub fn validate_max_size(&self, max_size: usize) -> Result<(), EnvelopeError> {
+        let data = self.data.as_ref().ok_or(EnvelopeError::MissingData)?;
+        if data.is_empty() {
+            return Err(EnvelopeError::EmptyData);
+        }
+        if data.len() > max_size {
+            return Err(EnvelopeError::DataTooLarge(data.len(), max_size));
+        }
+        if let Some(target) = &self.target {
+            target
+                .parse::<Address>()
+                .map_err(|_| EnvelopeError::InvalidTarget(target.clone()))?;
+            if self.envelope_kind() == EnvelopeKind::Data {
+                return Err(EnvelopeError::DataOnlyTarget(target.clone()));
+            }
+        }
+        if self.tags.len() > MAX_TAGS_PER_ENVELOPE {
+            return Err(EnvelopeError::TooManyTags(self.tags.len()));
+        }
+        let malformed = |tag: &Tag| {
+            !tag.is_well_formed()
+                || (tag.name.eq_ignore_ascii_case(EXPIRES_AT_TAG)
+                    && tag.value.parse::<u64>().is_err())
+        };
+        if let Some(i) = self.tags.iter().position(malformed) {
+            return Err(EnvelopeError::MalformedTag(i));
+        }
+        if let Some(path) = find_tag(&self.tags, PATH_TAG) {
+            if !is_valid_path(path) {
+                return Err(EnvelopeError::InvalidPath(path.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    // JSON encodes `value` as the envelope data
+    pub fn from_serde<T: Serialize>(value: &T) -> Result<Self, Error> {
+        let data = serde_json::to_vec(value)?;
+        Ok(Self::new().data(Some(data)))
+    }
+
+    pub fn from_json<T: Serialize>(value: &T) -> Result<Self, Error> {
+        Self::from_serde(value)
+    }
+
+    pub fn from_bytes(data: impl Into<Bytes>) -> Self {
+        Self::new().data(Some(data.into().to_vec()))
+    }
+
+    // with or without the 0x prefix
+    pub fn from_hex(data: &str) -> Result<Self, Error> {
+        let data = data.trim();
+        let data = data
+            .strip_prefix("0x")
+            .or_else(|| data.strip_prefix("0X"))
+            .unwrap_or(data);
+        let data = alloy::hex::decode(data)?;
+        Ok(Self::new().data(Some(data)))
+    }
+
+    #[cfg(feature = "protobuf")]
+    pub fn from_protobuf<M: prost::Message>(message: &M) -> Self {
+        Self::new().data(Some(message.encode_to_vec()))
+    }
+
+    pub fn build(self) -> Result<Self, Error> {
+        match &self.data {
+            None => return Err(EnvelopeError::MissingData.into()),
+            Some(data) if data.is_empty() => return Err(EnvelopeError::EmptyData.into()),
+            Some(_) => {}
+        }
         Ok(Self {
             data: self.data,
             target: self.target,
+            compression: self.compression,
+            tags: self.tags,
+            extensions: self.extensions,
         })
     }
 }
@@ -0,0 +1,117 @@
+use crate::utils::core::bundle_config::{BundleConfig, DEFAULT_MAX_ENVELOPE_SIZE};
+use crate::utils::core::compression::Compression;
+use crate::utils::core::envelope::Envelope;
+use crate::utils::core::envelope_kind::{set_envelope_kind, EnvelopeKind};
+use crate::utils::core::extension::{set_extension, Extension};
+use crate::utils::core::tag::{
+    set_tag, Tag, CONTENT_TYPE_TAG, EXPIRES_AT_TAG, PATH_TAG, RETENTION_CLASS_TAG,
+};
+use crate::utils::errors::EnvelopeError;
+
+// validates the envelope against the size limits when built, not at signing time
+#[derive(Debug, Clone)]
+pub struct EnvelopeBuilder {
+    data: Option<Vec<u8>>,
+    target: Option<String>,
+    tags: Vec<Tag>,
+    extensions: Vec<Extension>,
+    compression: Compression,
+    max_size: usize,
+}
+
+impl Default for EnvelopeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnvelopeBuilder {
+    pub fn new() -> Self {
+        EnvelopeBuilder {
+            data: None,
+            target: None,
+            tags: vec![],
+            extensions: vec![],
+            compression: Compression::default(),
+            max_size: DEFAULT_MAX_ENVELOPE_SIZE,
+        }
+    }
+
+    // uses the limits of the bundle the envelope is meant for
+    pub fn limits(mut self, config: &BundleConfig) -> Self {
+        self.max_size = config.max_envelope_size;
+        self
+    }
+
+    pub fn data(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn tag(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push(Tag::new(name, value));
+        self
+    }
+
+    pub fn tags(mut self, tags: impl IntoIterator<Item = Tag>) -> Self {
+        self.tags.extend(tags);
+        self
+    }
+
+    // replaces any previous content type tag
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        set_tag(&mut self.tags, CONTENT_TYPE_TAG, content_type);
+        self
+    }
+
+    pub fn extension(mut self, id: u16, data: impl Into<Vec<u8>>) -> Self {
+        set_extension(&mut self.extensions, id, data);
+        self
+    }
+
+    pub fn expires_at(mut self, timestamp: u64) -> Self {
+        set_tag(&mut self.tags, EXPIRES_AT_TAG, timestamp.to_string());
+        self
+    }
+
+    pub fn retention_class(mut self, class: impl Into<String>) -> Self {
+        set_tag(&mut self.tags, RETENTION_CLASS_TAG, class);
+        self
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        set_tag(&mut self.tags, PATH_TAG, path);
+        self
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn kind(mut self, kind: EnvelopeKind) -> Self {
+        set_envelope_kind(&mut self.extensions, kind);
+        self
+    }
+
+    pub fn data_only(self) -> Self {
+        self.kind(EnvelopeKind::Data)
+    }
+
+    pub fn build(self) -> Result<Envelope, EnvelopeError> {
+        let envelope = Envelope {
+            data: self.data,
+            target: self.target,
+            compression: self.compression,
+            tags: self.tags,
+            extensions: self.extensions,
+        };
+        envelope.validate_max_size(self.max_size)?;
+        Ok(envelope)
+    }
+}
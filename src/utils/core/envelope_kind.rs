@@ -0,0 +1,42 @@
+use crate::utils::core::extension::{find_extension, set_extension, Extension};
+use serde::{Deserialize, Serialize};
+
+// extension ids from 0xff00 on are reserved for the library
+pub const ENVELOPE_KIND_EXTENSION: u16 = 0xff00;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvelopeKind {
+    // a tx to its target, the zero address one when it has none. Envelopes without the
+    // kind extension, like every envelope of older bundles, are calls.
+    #[default]
+    Call,
+    // pure data without a target. The signed tx still goes to the zero address, the kind
+    // extension tells it apart from an intentional zero address call.
+    Data,
+}
+
+impl EnvelopeKind {
+    fn as_byte(&self) -> u8 {
+        match self {
+            EnvelopeKind::Call => 0,
+            EnvelopeKind::Data => 1,
+        }
+    }
+
+    // kinds unknown to this version are read as calls
+    pub fn from_extensions(extensions: &[Extension]) -> Self {
+        match find_extension(extensions, ENVELOPE_KIND_EXTENSION) {
+            Some([1]) => EnvelopeKind::Data,
+            _ => EnvelopeKind::Call,
+        }
+    }
+}
+
+// calls are stored without extension, so their bundle ids are unchanged
+pub fn set_envelope_kind(extensions: &mut Vec<Extension>, kind: EnvelopeKind) {
+    match kind {
+        EnvelopeKind::Call => extensions.retain(|e| e.id != ENVELOPE_KIND_EXTENSION),
+        kind => set_extension(extensions, ENVELOPE_KIND_EXTENSION, [kind.as_byte()]),
+    }
+}
@@ -0,0 +1,32 @@
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use serde::{Deserialize, Serialize};
+
+// order of the envelopes within a bundle, applied after signing and before serialization
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnvelopeOrdering {
+    #[default]
+    Preserve,
+    // envelope tx hash
+    ById,
+    // target address, ties broken by envelope tx hash
+    ByTarget,
+}
+
+impl EnvelopeOrdering {
+    pub fn apply(&self, envelopes: &mut [TxEnvelopeWrapper]) {
+        if *self != EnvelopeOrdering::Preserve {
+            envelopes.sort_by_cached_key(|e| self.sort_key(e));
+        }
+    }
+
+    // envelopes are sorted by this key, it is constant for `Preserve`
+    pub fn sort_key(&self, envelope: &TxEnvelopeWrapper) -> (String, String) {
+        match self {
+            EnvelopeOrdering::Preserve => Default::default(),
+            EnvelopeOrdering::ById => (envelope.hash.to_lowercase(), String::new()),
+            EnvelopeOrdering::ByTarget => {
+                (envelope.to.to_lowercase(), envelope.hash.to_lowercase())
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::utils::core::bundle_item::EncodedItem;
+use crate::utils::core::compression::Compression;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use serde::{Deserialize, Serialize};
+
+// what one envelope weighs in its bundle calldata, see `BundleData::encode_calldata_with_stats`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EnvelopeStats {
+    // position in the bundle
+    pub index: usize,
+    pub hash: String,
+    // envelope data, before serialization
+    pub raw_size: usize,
+    pub serialized_size: usize,
+    // payload after compression
+    pub stored_size: usize,
+    pub compression: Compression,
+    // whole item over the calldata size
+    pub share: f64,
+}
+
+impl EnvelopeStats {
+    pub fn collect(
+        envelopes: &[TxEnvelopeWrapper],
+        items: &[EncodedItem],
+        calldata_size: usize,
+    ) -> Vec<Self> {
+        envelopes
+            .iter()
+            .zip(items)
+            .enumerate()
+            .map(|(index, (envelope, item))| EnvelopeStats {
+                index,
+                hash: envelope.hash.clone(),
+                raw_size: envelope.input.trim_start_matches("0x").len() / 2,
+                serialized_size: item.serialized_size,
+                stored_size: item.stored_size,
+                compression: item.compression,
+                share: item.item_size as f64 / calldata_size.max(1) as f64,
+            })
+            .collect()
+    }
+
+    // stored over serialized size, 1.0 for uncompressed envelopes
+    pub fn compression_ratio(&self) -> f64 {
+        self.stored_size as f64 / self.serialized_size.max(1) as f64
+    }
+}
+
+// most expensive envelopes first
+pub fn largest_envelopes(stats: &[EnvelopeStats], count: usize) -> Vec<&EnvelopeStats> {
+    let mut sorted: Vec<&EnvelopeStats> = stats.iter().collect();
+    sorted.sort_by(|a, b| b.share.total_cmp(&a.share));
+    sorted.truncate(count);
+    sorted
+}
@@ -0,0 +1,43 @@
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
+// optional envelope field stored in the extension section of its bundle item. New
+// fields get a new id, readers keep the extensions they don't know as is.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+pub struct Extension {
+    pub id: u16,
+    pub data: Vec<u8>,
+}
+
+impl Extension {
+    pub fn new(id: u16, data: impl Into<Vec<u8>>) -> Self {
+        Extension {
+            id,
+            data: data.into(),
+        }
+    }
+}
+
+pub fn find_extension(extensions: &[Extension], id: u16) -> Option<&[u8]> {
+    extensions
+        .iter()
+        .find(|extension| extension.id == id)
+        .map(|extension| extension.data.as_slice())
+}
+
+// replaces any previous extension of the same id
+pub fn set_extension(extensions: &mut Vec<Extension>, id: u16, data: impl Into<Vec<u8>>) {
+    extensions.retain(|extension| extension.id != id);
+    extensions.push(Extension::new(id, data));
+}
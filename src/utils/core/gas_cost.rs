@@ -0,0 +1,118 @@
+use crate::utils::core::bundle_config::BundleConfig;
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::compression::CompressionParams;
+use crate::utils::errors::Error;
+use serde::{Deserialize, Serialize};
+
+// EIP-2028 calldata pricing, on top of the base cost of every tx
+pub const TX_BASE_GAS: u64 = 21_000;
+pub const ZERO_BYTE_GAS: u64 = 4;
+pub const NONZERO_BYTE_GAS: u64 = 16;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalldataCost {
+    pub size: usize,
+    pub zero_bytes: usize,
+    // intrinsic gas of a tx carrying the calldata, what a bundle tx to 0xbabe1 uses
+    pub gas: u64,
+}
+
+impl CalldataCost {
+    pub fn of(calldata: &[u8]) -> Self {
+        let zero_bytes = calldata.iter().filter(|&&byte| byte == 0).count();
+        let nonzero_bytes = (calldata.len() - zero_bytes) as u64;
+        CalldataCost {
+            size: calldata.len(),
+            zero_bytes,
+            gas: TX_BASE_GAS + zero_bytes as u64 * ZERO_BYTE_GAS + nonzero_bytes * NONZERO_BYTE_GAS,
+        }
+    }
+
+    // in wei, at `fee_per_gas` (e.g. the max fee of the bundle tx)
+    pub fn fee(&self, fee_per_gas: u128) -> u128 {
+        self.gas as u128 * fee_per_gas
+    }
+}
+
+pub const DEFAULT_OPTIMIZER_CANDIDATES: [CompressionParams; 3] = [
+    CompressionParams {
+        quality: 11,
+        window: 24,
+    },
+    CompressionParams {
+        quality: 9,
+        window: 24,
+    },
+    CompressionParams {
+        quality: 5,
+        window: 22,
+    },
+];
+
+// the compression settings a bundle was encoded with by the optimizer, and what they cost
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptimizedEncoding {
+    pub compression: CompressionParams,
+    pub cost: CalldataCost,
+}
+
+// encodes a bundle with each candidate compression and keeps the cheapest calldata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasOptimizer {
+    // tried after the config compression, which wins ties
+    pub candidates: Vec<CompressionParams>,
+}
+
+impl Default for GasOptimizer {
+    fn default() -> Self {
+        GasOptimizer {
+            candidates: DEFAULT_OPTIMIZER_CANDIDATES.to_vec(),
+        }
+    }
+}
+
+impl GasOptimizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // no candidates besides the config compression
+    pub fn empty() -> Self {
+        GasOptimizer { candidates: vec![] }
+    }
+
+    pub fn candidate(mut self, compression: CompressionParams) -> Self {
+        self.candidates.push(compression);
+        self
+    }
+
+    pub fn optimize(
+        &self,
+        bundle: &BundleData,
+        config: &BundleConfig,
+    ) -> Result<(Vec<u8>, OptimizedEncoding), Error> {
+        let encode = |compression: CompressionParams| {
+            compression.validate()?;
+            let config = BundleConfig {
+                compression,
+                ..config.clone()
+            };
+            let calldata = bundle.encode_calldata_with(&config)?;
+            let cost = CalldataCost::of(&calldata);
+            Ok::<_, Error>((calldata, OptimizedEncoding { compression, cost }))
+        };
+        let mut best = encode(config.compression)?;
+        let mut tried = vec![config.compression];
+        for &compression in &self.candidates {
+            if tried.contains(&compression) {
+                continue;
+            }
+            tried.push(compression);
+            let candidate = encode(compression)?;
+            if candidate.1.cost.gas < best.1.cost.gas {
+                best = candidate;
+            }
+        }
+        Ok(best)
+    }
+}
@@ -1,6 +1,32 @@
+pub mod authorship;
 pub mod bundle;
+pub mod bundle_config;
 pub mod bundle_data;
+pub mod bundle_export;
+pub mod bundle_header;
+pub mod bundle_inspection;
+pub mod bundle_item;
+pub mod bundle_receipt;
 pub mod bundle_tx_metadata;
+pub mod chain_target;
+pub mod compression;
+pub mod decode_config;
+pub mod dictionary;
 pub mod envelope;
+pub mod envelope_builder;
+pub mod envelope_kind;
+pub mod envelope_ordering;
 pub mod envelope_signature;
+pub mod envelope_stats;
+pub mod extension;
+pub mod gas_cost;
+pub mod packing;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod policy;
+pub mod profile;
+pub mod retention;
+pub mod signed_bundle_tx;
+pub mod stream;
+pub mod tag;
 pub mod tx_envelope_writer;
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+// how `Bundle::propagate_batched` splits envelopes into bundles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PackingStrategy {
+    // consecutive chunks of `max_envelopes_per_bundle` envelopes, in order
+    #[default]
+    Sequential,
+    // fewest bundles under `max_bundle_size`, envelopes placed largest first (on their
+    // estimated encoded size) in the first bundle they fit in. Envelope order is kept
+    // within each bundle, not across bundles.
+    FirstFitDecreasing,
+}
+
+// bins of item indices, each bin in increasing index order. An item larger than
+// `capacity` gets a bin of its own.
+pub fn first_fit_decreasing(sizes: &[usize], capacity: usize, max_count: usize) -> Vec<Vec<usize>> {
+    let max_count = max_count.max(1);
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    // stable, equal sizes keep their order
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i]));
+
+    let mut bins: Vec<(usize, Vec<usize>)> = vec![];
+    for i in order {
+        let fits = |(used, items): &&mut (usize, Vec<usize>)| {
+            items.len() < max_count && *used + sizes[i] <= capacity
+        };
+        match bins.iter_mut().find(fits) {
+            Some((used, items)) => {
+                *used += sizes[i];
+                items.push(i);
+            }
+            None => bins.push((sizes[i], vec![i])),
+        }
+    }
+    bins.into_iter()
+        .map(|(_, mut items)| {
+            items.sort_unstable();
+            items
+        })
+        .collect()
+}
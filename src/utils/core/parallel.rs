@@ -0,0 +1,70 @@
+use crate::utils::core::bundle_config::BundleConfig;
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::bundle_item::{BundleItem, EncodeBuffers, EncodedItem};
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use rayon::prelude::*;
+use std::io::Write;
+
+// envelopes encoded by one rayon job
+pub const PARALLEL_CHUNK_SIZE: usize = 256;
+// smaller bundles are encoded on the calling thread
+pub const PARALLEL_MIN_ENVELOPES: usize = 1_024;
+
+// encodes the items of `envelopes` by chunks on the rayon pool, each chunk into its own
+// buffer, and writes the buffers in bundle order so the bytes match the sequential path.
+// One wave of a chunk per pool thread is buffered at a time.
+pub fn write_items<W: Write>(
+    envelopes: &[TxEnvelopeWrapper],
+    config: &BundleConfig,
+    writer: &mut W,
+) -> Result<Vec<EncodedItem>, Error> {
+    let wave = PARALLEL_CHUNK_SIZE * rayon::current_num_threads();
+    let mut items = Vec::with_capacity(envelopes.len());
+    for envelopes in envelopes.chunks(wave) {
+        let chunks = envelopes
+            .par_chunks(PARALLEL_CHUNK_SIZE)
+            .map(|chunk| {
+                let mut buffers = EncodeBuffers::new();
+                let mut output = vec![];
+                let mut chunk_items = Vec::with_capacity(chunk.len());
+                for envelope in chunk {
+                    chunk_items.push(BundleItem::write_envelope(
+                        envelope,
+                        config,
+                        &mut buffers,
+                        &mut output,
+                    )?);
+                }
+                Ok((output, chunk_items))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        for (chunk, chunk_items) in chunks {
+            writer.write_all(&chunk)?;
+            items.extend(chunk_items);
+        }
+    }
+    Ok(items)
+}
+
+// same bytes as `TxEnvelopeWrapper::borsh_ser`, the envelopes being serialized by chunks
+pub fn borsh_ser(bundle: &BundleData) -> Vec<u8> {
+    let chunks: Vec<Vec<u8>> = bundle
+        .envelopes
+        .par_chunks(PARALLEL_CHUNK_SIZE)
+        .map(|chunk| {
+            let mut output = vec![];
+            for envelope in chunk {
+                borsh::to_writer(&mut output, envelope).unwrap();
+            }
+            output
+        })
+        .collect();
+    let len: usize = chunks.iter().map(Vec::len).sum();
+    let mut output = Vec::with_capacity(4 + len);
+    output.extend_from_slice(&(bundle.envelopes.len() as u32).to_le_bytes());
+    for chunk in chunks {
+        output.extend_from_slice(&chunk);
+    }
+    output
+}
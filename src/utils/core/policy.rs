@@ -0,0 +1,220 @@
+use crate::utils::constants::CHAIN_ID;
+use crate::utils::core::decode_config::ValidationMode;
+use crate::utils::core::tag::find_tag;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use alloy::primitives::Address;
+use std::fmt;
+use std::sync::Arc;
+
+// one invariant of the envelopes of a bundle, `index` is the envelope position
+pub trait PolicyRule: fmt::Debug + Send + Sync {
+    fn check(&self, index: usize, envelope: &TxEnvelopeWrapper) -> Result<(), Error>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainIdRule(pub u64);
+
+impl PolicyRule for ChainIdRule {
+    fn check(&self, index: usize, envelope: &TxEnvelopeWrapper) -> Result<(), Error> {
+        if envelope.chain_id != self.0 {
+            return Err(Error::NonconformingEnvelope(index));
+        }
+        Ok(())
+    }
+}
+
+// envelopes are never broadcasted on their own, their nonce and gas fields stay zero
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZeroNonceAndGasRule;
+
+impl PolicyRule for ZeroNonceAndGasRule {
+    fn check(&self, index: usize, envelope: &TxEnvelopeWrapper) -> Result<(), Error> {
+        if envelope.nonce != 0 || envelope.gas_limit != 0 || envelope.gas_price != 0 {
+            return Err(Error::NonconformingEnvelope(index));
+        }
+        Ok(())
+    }
+}
+
+// envelope data size, in bytes (after decompression)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxEnvelopeSizeRule(pub usize);
+
+impl PolicyRule for MaxEnvelopeSizeRule {
+    fn check(&self, index: usize, envelope: &TxEnvelopeWrapper) -> Result<(), Error> {
+        let size = envelope.input.trim_start_matches("0x").len() / 2;
+        if size > self.0 {
+            return Err(Error::PolicyViolation(
+                index,
+                format!("data is {size} bytes, more than {}", self.0),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// envelopes created without a target have the zero address one, allow it to accept them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowedTargetsRule(pub Vec<Address>);
+
+impl PolicyRule for AllowedTargetsRule {
+    fn check(&self, index: usize, envelope: &TxEnvelopeWrapper) -> Result<(), Error> {
+        let allowed = envelope
+            .to
+            .parse::<Address>()
+            .is_ok_and(|to| self.0.contains(&to));
+        if !allowed {
+            return Err(Error::PolicyViolation(
+                index,
+                format!("target {} is not allowed", envelope.to),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// any value when `value` is `None`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequiredTagRule {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl PolicyRule for RequiredTagRule {
+    fn check(&self, index: usize, envelope: &TxEnvelopeWrapper) -> Result<(), Error> {
+        let found = find_tag(&envelope.tags, &self.name);
+        let matching = match &self.value {
+            Some(value) => found == Some(value.as_str()),
+            None => found.is_some(),
+        };
+        if !matching {
+            return Err(Error::PolicyViolation(
+                index,
+                format!("tag {} is missing", self.name),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// recovers the signer of every envelope, by far the most expensive of the built-in rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowedSignersRule(pub Vec<Address>);
+
+impl PolicyRule for AllowedSignersRule {
+    fn check(&self, index: usize, envelope: &TxEnvelopeWrapper) -> Result<(), Error> {
+        let signer = envelope.recover_signer()?;
+        if !self.0.contains(&signer) {
+            return Err(Error::PolicyViolation(
+                index,
+                format!("signer {signer} is not allowed"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// a closure returning the violation reason, see `BundlePolicy::check_fn`
+pub struct FnRule<F> {
+    pub name: String,
+    pub check: F,
+}
+
+impl<F> fmt::Debug for FnRule<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnRule").field("name", &self.name).finish()
+    }
+}
+
+impl<F> PolicyRule for FnRule<F>
+where
+    F: Fn(&TxEnvelopeWrapper) -> Result<(), String> + Send + Sync,
+{
+    fn check(&self, index: usize, envelope: &TxEnvelopeWrapper) -> Result<(), Error> {
+        (self.check)(envelope)
+            .map_err(|reason| Error::PolicyViolation(index, format!("{}: {reason}", self.name)))
+    }
+}
+
+// rules every envelope of a bundle must pass, checked in order when a bundle is created
+// (after its envelopes are signed) and when it is decoded
+#[derive(Debug, Clone, Default)]
+pub struct BundlePolicy {
+    pub rules: Vec<Arc<dyn PolicyRule>>,
+}
+
+impl BundlePolicy {
+    // no rule
+    pub fn new() -> Self {
+        BundlePolicy { rules: vec![] }
+    }
+
+    // the envelope MUSTs, zero nonce, gas limit and gas price, WeaveVM chain id
+    pub fn strict() -> Self {
+        Self::lenient().rule(ZeroNonceAndGasRule)
+    }
+
+    pub fn lenient() -> Self {
+        Self::new().rule(ChainIdRule(CHAIN_ID))
+    }
+
+    pub fn rule(mut self, rule: impl PolicyRule + 'static) -> Self {
+        self.rules.push(Arc::new(rule));
+        self
+    }
+
+    pub fn check_fn<F>(self, name: &str, check: F) -> Self
+    where
+        F: Fn(&TxEnvelopeWrapper) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.rule(FnRule {
+            name: name.to_string(),
+            check,
+        })
+    }
+
+    pub fn max_envelope_size(self, max_size: usize) -> Self {
+        self.rule(MaxEnvelopeSizeRule(max_size))
+    }
+
+    pub fn allowed_targets(self, targets: Vec<Address>) -> Self {
+        self.rule(AllowedTargetsRule(targets))
+    }
+
+    pub fn require_tag(self, name: &str, value: Option<&str>) -> Self {
+        self.rule(RequiredTagRule {
+            name: name.to_string(),
+            value: value.map(str::to_string),
+        })
+    }
+
+    pub fn allowed_signers(self, signers: Vec<Address>) -> Self {
+        self.rule(AllowedSignersRule(signers))
+    }
+
+    // the first failing rule error
+    pub fn check(&self, index: usize, envelope: &TxEnvelopeWrapper) -> Result<(), Error> {
+        for rule in &self.rules {
+            rule.check(index, envelope)?;
+        }
+        Ok(())
+    }
+
+    pub fn check_all(&self, envelopes: &[TxEnvelopeWrapper]) -> Result<(), Error> {
+        for (i, envelope) in envelopes.iter().enumerate() {
+            self.check(i, envelope)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<ValidationMode> for BundlePolicy {
+    fn from(mode: ValidationMode) -> Self {
+        match mode {
+            ValidationMode::Strict => BundlePolicy::strict(),
+            ValidationMode::Lenient => BundlePolicy::lenient(),
+            ValidationMode::Skip => BundlePolicy::new(),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::utils::core::bundle_config::BundleConfig;
+use crate::utils::core::compression::CompressionParams;
+use crate::utils::errors::Error;
+use crate::utils::gas_oracle::FeeHistoryGasOracle;
+use crate::utils::inclusion::ConfirmationLevel;
+use std::str::FromStr;
+
+// presets of the compression, fee and confirmation knobs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleProfile {
+    // smallest calldata, waits for finality and resubmits reorged bundles longer
+    Archive,
+    // fastest compression and inclusion, confirmed at the latest block
+    Realtime,
+    // smallest calldata at low priority fees. There is no blob mode, bundles are
+    // always posted as calldata.
+    Cheap,
+}
+
+impl BundleProfile {
+    pub fn apply(&self, config: BundleConfig) -> BundleConfig {
+        match self {
+            BundleProfile::Archive => BundleConfig {
+                compression: CompressionParams {
+                    quality: 11,
+                    window: 24,
+                },
+                confirmation_level: ConfirmationLevel::Finalized,
+                max_resubmissions: 10,
+                ..config
+            },
+            BundleProfile::Realtime => BundleConfig {
+                compression: CompressionParams {
+                    quality: 1,
+                    window: 18,
+                },
+                confirmation_level: ConfirmationLevel::Latest,
+                ..config
+            }
+            .gas_oracle(FeeHistoryGasOracle::default().percentile(90.0)),
+            BundleProfile::Cheap => BundleConfig {
+                compression: CompressionParams {
+                    quality: 11,
+                    window: 24,
+                },
+                confirmation_level: ConfirmationLevel::Latest,
+                ..config
+            }
+            .gas_oracle(FeeHistoryGasOracle::default().percentile(10.0)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BundleProfile::Archive => "archive",
+            BundleProfile::Realtime => "realtime",
+            BundleProfile::Cheap => "cheap",
+        }
+    }
+}
+
+impl FromStr for BundleProfile {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "archive" => Ok(BundleProfile::Archive),
+            "realtime" => Ok(BundleProfile::Realtime),
+            "cheap" => Ok(BundleProfile::Cheap),
+            _ => Err(Error::UnknownProfile(name.to_string())),
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// how exporters treat envelopes past their `Expires-At` tag
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExpiryPolicy {
+    // kept, with an `expired` flag
+    #[default]
+    Flag,
+    Skip,
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
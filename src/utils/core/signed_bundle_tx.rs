@@ -0,0 +1,21 @@
+use alloy::primitives::B256;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadStats {
+    pub envelope_count: usize,
+    // raw envelope data, before compression
+    pub data_size: usize,
+    pub calldata_size: usize,
+}
+
+// bundle tx signed but not broadcasted, e.g. on an air-gapped machine
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedBundleTx {
+    // EIP-2718 encoding, as expected by `eth_sendRawTransaction`
+    pub raw_bytes: Vec<u8>,
+    pub hash: String,
+    // see `bundle_id`, unchanged if the bundle is re-signed with another nonce or fees
+    pub bundle_id: B256,
+    pub payload_stats: PayloadStats,
+}
@@ -0,0 +1,85 @@
+use crate::utils::core::bundle::Bundle;
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::errors::Error;
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+    PartialEq,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+pub struct StreamPointer {
+    pub stream_id: String,
+    pub sequence: u64,
+    pub previous_txid: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamDirection {
+    Backward,
+    Forward,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamEntry {
+    pub txid: String,
+    pub bundle: BundleData,
+}
+
+impl StreamPointer {
+    pub fn genesis(stream_id: String) -> Self {
+        StreamPointer {
+            stream_id,
+            sequence: 0,
+            previous_txid: None,
+        }
+    }
+
+    // pointer for the bundle appended after the bundle carried by `txid`
+    pub fn next(&self, txid: String) -> Self {
+        StreamPointer {
+            stream_id: self.stream_id.clone(),
+            sequence: self.sequence + 1,
+            previous_txid: Some(txid),
+        }
+    }
+}
+
+// walks a stream from its head bundle down to the genesis bundle
+pub async fn follow_stream(
+    head_txid: String,
+    direction: StreamDirection,
+) -> Result<Vec<StreamEntry>, Error> {
+    let mut entries: Vec<StreamEntry> = vec![];
+    let mut next_txid = Some(head_txid);
+    let mut expected: Option<(String, u64)> = None;
+
+    while let Some(txid) = next_txid {
+        let bundle = Bundle::retrieve_envelopes(txid.clone()).await?;
+        let pointer = bundle.header.stream.clone().ok_or(Error::BrokenStream)?;
+
+        if let Some((stream_id, sequence)) = &expected {
+            if &pointer.stream_id != stream_id || pointer.sequence != *sequence {
+                return Err(Error::BrokenStream);
+            }
+        }
+        next_txid = match pointer.sequence {
+            0 => None,
+            sequence => {
+                expected = Some((pointer.stream_id.clone(), sequence - 1));
+                Some(pointer.previous_txid.clone().ok_or(Error::BrokenStream)?)
+            }
+        };
+        entries.push(StreamEntry { txid, bundle });
+    }
+
+    if direction == StreamDirection::Forward {
+        entries.reverse();
+    }
+    Ok(entries)
+}
@@ -0,0 +1,81 @@
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
+pub const CONTENT_TYPE_TAG: &str = "Content-Type";
+// unix timestamp, in seconds, after which the envelope data may be dropped
+pub const EXPIRES_AT_TAG: &str = "Expires-At";
+pub const RETENTION_CLASS_TAG: &str = "Retention-Class";
+// hierarchical name of the envelope, `/` separated segments like "app/collection/item"
+pub const PATH_TAG: &str = "Path";
+pub const PATH_SEPARATOR: char = '/';
+pub const MAX_TAGS_PER_ENVELOPE: usize = 128;
+pub const MAX_TAG_NAME_LEN: usize = 1024;
+pub const MAX_TAG_VALUE_LEN: usize = 3072;
+
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+pub struct Tag {
+    pub name: String,
+    pub value: String,
+}
+
+impl Tag {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Tag {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn is_well_formed(&self) -> bool {
+        !self.name.is_empty()
+            && self.name.len() <= MAX_TAG_NAME_LEN
+            && self.value.len() <= MAX_TAG_VALUE_LEN
+            && !self.name.chars().any(char::is_control)
+    }
+}
+
+// tag names are case insensitive
+pub fn find_tag<'a>(tags: &'a [Tag], name: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|tag| tag.name.eq_ignore_ascii_case(name))
+        .map(|tag| tag.value.as_str())
+}
+
+// replaces any previous tag of the same name
+pub fn set_tag(tags: &mut Vec<Tag>, name: &str, value: impl Into<String>) {
+    tags.retain(|tag| !tag.name.eq_ignore_ascii_case(name));
+    tags.push(Tag::new(name, value));
+}
+
+// non-empty segments, without leading or trailing separator
+pub fn is_valid_path(path: &str) -> bool {
+    !path.is_empty()
+        && path.len() <= MAX_TAG_VALUE_LEN
+        && path
+            .split(PATH_SEPARATOR)
+            .all(|segment| !segment.is_empty())
+}
+
+// a plain string prefix: "app/collection/" matches the paths under the collection,
+// "app/collection" the collection itself too, and "app/collections"
+pub fn path_has_prefix(path: &str, prefix: &str) -> bool {
+    path.starts_with(prefix)
+}
+
+// `path_has_prefix` as a SQL condition on `column`, `param` being the prefix parameter.
+// `LIKE` would treat `%` and `_` in the prefix as wildcards.
+#[cfg(feature = "sqlite")]
+pub(crate) fn sql_path_has_prefix(column: &str, param: &str) -> String {
+    format!("substr({column}, 1, length({param})) = {param}")
+}
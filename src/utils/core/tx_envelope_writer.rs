@@ -1,10 +1,21 @@
 use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::compression::{read_capped, Compression, DEFAULT_MAX_DECOMPRESSED_SIZE};
+use crate::utils::core::envelope::Envelope;
+use crate::utils::core::envelope_kind::EnvelopeKind;
 use crate::utils::core::envelope_signature::EnvelopeSignature;
+use crate::utils::core::extension::{find_extension, Extension};
+use crate::utils::core::tag::{find_tag, Tag, EXPIRES_AT_TAG, PATH_TAG, RETENTION_CLASS_TAG};
+use crate::utils::errors::Error;
 use alloy::consensus::Transaction;
 use alloy::consensus::TxEnvelope;
+use alloy::consensus::{SignableTransaction, TxLegacy};
+use alloy::eips::eip2718::Decodable2718;
+use alloy::primitives::{Address, Bytes, PrimitiveSignature, TxKind, U256};
 use borsh::{from_slice, to_vec};
 use borsh_derive::{BorshDeserialize, BorshSerialize};
-use std::io::{Read, Write};
+use serde::de::DeserializeOwned;
+use std::io::{Cursor, Read, Write};
+use std::str::FromStr;
 
 #[derive(
     Clone,
@@ -26,11 +37,21 @@ pub struct TxEnvelopeWrapper {
     pub input: String,
     pub hash: String,
     pub signature: EnvelopeSignature,
+    // how the envelope is compressed within its bundle, not part of the signed tx
+    #[borsh(skip)]
+    #[serde(default)]
+    pub compression: Compression,
+    #[borsh(skip)]
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[borsh(skip)]
+    #[serde(default)]
+    pub extensions: Vec<Extension>,
 }
 
 impl TxEnvelopeWrapper {
     pub fn from_envelope(envelope: TxEnvelope) -> Self {
-        let sig: alloy::signers::Signature = envelope.signature().clone();
+        let sig: alloy::signers::Signature = *envelope.signature();
 
         let env_sig = EnvelopeSignature {
             y_parity: sig.v(),
@@ -48,9 +69,132 @@ impl TxEnvelopeWrapper {
             input: envelope.input().to_string(),
             hash: envelope.tx_hash().to_string(),
             signature: env_sig,
+            compression: Compression::default(),
+            tags: vec![],
+            extensions: vec![],
         }
     }
 
+    // an EIP-2718 encoded envelope signed elsewhere (a browser wallet, another service),
+    // which must be an EIP-155 legacy tx to an address, like the ones `create_envelope`
+    // signs. Its tags and extensions are not part of the signed tx and start empty.
+    pub fn from_signed_raw(raw: &[u8]) -> Result<Self, Error> {
+        let envelope =
+            TxEnvelope::decode_2718(&mut &raw[..]).map_err(|_| Error::InvalidSignedEnvelope)?;
+        let TxEnvelope::Legacy(tx) = &envelope else {
+            return Err(Error::InvalidSignedEnvelope);
+        };
+        if tx.tx().chain_id.is_none() || envelope.to().is_none() {
+            return Err(Error::InvalidSignedEnvelope);
+        }
+        let wrapper = Self::from_envelope(envelope);
+        wrapper.recover_signer()?;
+        Ok(wrapper)
+    }
+
+    pub fn expires_at(&self) -> Option<u64> {
+        find_tag(&self.tags, EXPIRES_AT_TAG).and_then(|value| value.parse().ok())
+    }
+
+    pub fn retention_class(&self) -> Option<&str> {
+        find_tag(&self.tags, RETENTION_CLASS_TAG)
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        find_tag(&self.tags, PATH_TAG)
+    }
+
+    pub fn extension(&self, id: u16) -> Option<&[u8]> {
+        find_extension(&self.extensions, id)
+    }
+
+    pub fn kind(&self) -> EnvelopeKind {
+        EnvelopeKind::from_extensions(&self.extensions)
+    }
+
+    pub fn is_data_only(&self) -> bool {
+        self.kind() == EnvelopeKind::Data
+    }
+
+    // `None` for data-only envelopes, the zero address is kept for calls to it. Envelopes
+    // of older bundles created without a target are calls to the zero address.
+    pub fn target(&self) -> Result<Option<Address>, Error> {
+        if self.is_data_only() {
+            return Ok(None);
+        }
+        Ok(Some(self.to.parse::<Address>()?))
+    }
+
+    // `now` is a unix timestamp in seconds
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at()
+            .is_some_and(|expires_at| expires_at <= now)
+    }
+
+    // rebuilds the signed legacy tx from the wrapped fields to recover its signer
+    pub fn recover_signer(&self) -> Result<Address, Error> {
+        let parse_u256 = |value: &str| U256::from_str(value).map_err(|_| Error::InvalidSignature);
+        let tx = TxLegacy {
+            chain_id: Some(self.chain_id),
+            nonce: self.nonce,
+            gas_price: self.gas_price,
+            gas_limit: self.gas_limit,
+            to: TxKind::Call(self.to.parse::<Address>()?),
+            value: parse_u256(&self.value)?,
+            input: self.input_bytes()?.into(),
+        };
+        let signature = PrimitiveSignature::new(
+            parse_u256(&self.signature.r)?,
+            parse_u256(&self.signature.s)?,
+            self.signature.y_parity,
+        );
+        signature
+            .recover_address_from_prehash(&tx.signature_hash())
+            .map_err(|_| Error::InvalidSignature)
+    }
+
+    // unsigned envelope with the same data, target, tags and extensions (so the same kind),
+    // to sign again in another bundle. The zero address target of envelopes created
+    // without one is dropped.
+    pub fn to_envelope(&self) -> Result<Envelope, Error> {
+        let target = Some(self.to.parse::<Address>()?)
+            .filter(|to| *to != Address::ZERO)
+            .map(|to| to.to_string());
+        Ok(Envelope {
+            data: Some(self.input_bytes()?),
+            target,
+            compression: self.compression,
+            tags: self.tags.clone(),
+            extensions: self.extensions.clone(),
+        })
+    }
+
+    fn input_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(alloy::hex::decode(&self.input)?)
+    }
+
+    // envelope data as it was submitted, the per-envelope compression is already undone
+    // when the bundle is decoded
+    pub fn payload(&self) -> Result<Bytes, Error> {
+        Ok(self.input_bytes()?.into())
+    }
+
+    pub fn payload_reader(&self) -> Result<impl Read, Error> {
+        Ok(Cursor::new(self.payload()?))
+    }
+
+    // decodes a JSON payload created with `Envelope::from_serde`
+    pub fn decode_payload<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        let data = self.input_bytes()?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    #[cfg(feature = "protobuf")]
+    pub fn decode_protobuf_payload<M: prost::Message + Default>(&self) -> Result<M, Error> {
+        let data = self.input_bytes()?;
+        Ok(M::decode(data.as_slice())?)
+    }
+
     pub fn brotli_compress_stream<R: Read>(reader: &mut R) -> Vec<u8> {
         let mut writer = brotli::CompressorWriter::new(Vec::new(), 65_536, 8, 22); // 65536 -- 64 KiB
         let mut buffer = [0u8; 65_536];
@@ -66,31 +210,50 @@ impl TxEnvelopeWrapper {
         writer.into_inner()
     }
 
-    pub fn brotli_decompress_stream<R: Read>(reader: &mut R) -> Vec<u8> {
-        let mut writer = Vec::new();
-        let mut decoder = brotli::Decompressor::new(reader, 65_536);
-        std::io::copy(&mut decoder, &mut writer).unwrap();
-        writer
+    pub fn brotli_decompress_stream<R: Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
+        let decoder = brotli::Decompressor::new(reader, 65_536);
+        read_capped(decoder, DEFAULT_MAX_DECOMPRESSED_SIZE)
     }
 
     pub fn brotli_compress(input: &[u8]) -> Vec<u8> {
-        let mut writer = brotli::CompressorWriter::new(Vec::new(), 65536, 9, 22);
+        Self::brotli_compress_with(input, 9, 22)
+    }
+
+    pub fn brotli_compress_with(input: &[u8], quality: u32, lgwin: u32) -> Vec<u8> {
+        let mut output = Vec::new();
+        Self::brotli_compress_into(input, quality, lgwin, &mut output);
+        output
+    }
+
+    // appends the compressed input to `output`
+    pub fn brotli_compress_into(input: &[u8], quality: u32, lgwin: u32, output: &mut Vec<u8>) {
+        let mut writer = brotli::CompressorWriter::new(output, 65536, quality, lgwin);
         writer.write_all(input).unwrap();
-        writer.into_inner()
+        writer.into_inner();
     }
 
     pub fn brotli_decompress(input: Vec<u8>) -> Vec<u8> {
-        let mut decompressed_data = Vec::new();
-        let mut decompressor = brotli::Decompressor::new(input.as_slice(), 32_768); // 32_768 -- 32 KiB
+        Self::brotli_decompress_capped(&input, DEFAULT_MAX_DECOMPRESSED_SIZE)
+            .expect("Decompression failed")
+    }
 
-        decompressor
-            .read_to_end(&mut decompressed_data)
-            .expect("Decompression failed");
-        decompressed_data
+    pub fn brotli_decompress_capped(input: &[u8], max_size: usize) -> Result<Vec<u8>, Error> {
+        let decompressor = brotli::Decompressor::new(input, 32_768); // 32_768 -- 32 KiB
+        read_capped(decompressor, max_size)
     }
     pub fn borsh_ser(input: &BundleData) -> Vec<u8> {
+        #[cfg(feature = "rayon")]
+        if input.envelopes.len() >= crate::utils::core::parallel::PARALLEL_MIN_ENVELOPES {
+            return crate::utils::core::parallel::borsh_ser(input);
+        }
         to_vec(input).unwrap()
     }
+
+    // serializes into `output`, with a single allocation sized up front when it is empty
+    pub fn borsh_ser_into(input: &BundleData, output: &mut Vec<u8>) {
+        output.reserve(borsh::object_length(input).unwrap());
+        borsh::to_writer(output, input).unwrap();
+    }
     pub fn borsh_der(input: Vec<u8>) -> BundleData {
         let res: BundleData = from_slice(&input).expect("error deseriliazing the calldata");
         res
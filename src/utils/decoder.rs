@@ -0,0 +1,103 @@
+use crate::utils::constants::BUNDLE_MAGIC;
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::decode_config::{DecodeConfig, ValidationMode};
+use crate::utils::core::policy::BundlePolicy;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use base64::Engine;
+use std::borrow::Cow;
+
+// raw calldata bytes, their hex encoding with or without the 0x prefix, or base64
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalldataEncoding {
+    Raw,
+    Hex,
+    Base64,
+}
+
+impl CalldataEncoding {
+    // headed raw calldata starts with the bundle magic, hex and base64 inputs are
+    // told apart by their alphabet (all-hex-digit inputs are taken as hex)
+    pub fn detect(input: &[u8]) -> Self {
+        if input.starts_with(&BUNDLE_MAGIC) {
+            return CalldataEncoding::Raw;
+        }
+        if input.len() >= 2 && input[..2].eq_ignore_ascii_case(b"0x") {
+            return CalldataEncoding::Hex;
+        }
+        if input.is_empty() {
+            return CalldataEncoding::Raw;
+        }
+        if input.len().is_multiple_of(2) && input.iter().all(u8::is_ascii_hexdigit) {
+            return CalldataEncoding::Hex;
+        }
+        let base64_alphabet = |b: &u8| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'=');
+        if input.len().is_multiple_of(4) && input.iter().all(base64_alphabet) {
+            return CalldataEncoding::Base64;
+        }
+        CalldataEncoding::Raw
+    }
+
+    pub fn decode<'a>(&self, input: &'a [u8]) -> Result<Cow<'a, [u8]>, Error> {
+        match self {
+            CalldataEncoding::Raw => Ok(Cow::Borrowed(input)),
+            CalldataEncoding::Hex => {
+                let digits = match input.get(..2) {
+                    Some(prefix) if prefix.eq_ignore_ascii_case(b"0x") => &input[2..],
+                    _ => input,
+                };
+                Ok(Cow::Owned(alloy::hex::decode(digits)?))
+            }
+            CalldataEncoding::Base64 => Ok(Cow::Owned(
+                base64::engine::general_purpose::STANDARD.decode(input)?,
+            )),
+        }
+    }
+}
+
+// entry point for untrusted calldata (fuzzed by fuzz/fuzz_targets/decode_bundle_bytes.rs):
+// never panics, every length field is checked against the remaining input
+pub fn decode_bundle_bytes(calldata: &[u8]) -> Result<BundleData, Error> {
+    decode_bundle_bytes_with(calldata, &DecodeConfig::default())
+}
+
+pub fn decode_bundle_bytes_with(
+    calldata: &[u8],
+    config: &DecodeConfig,
+) -> Result<BundleData, Error> {
+    let bundle = BundleData::decode_calldata_with(calldata, config)?;
+    config.envelope_policy().check_all(&bundle.envelopes)?;
+    Ok(bundle)
+}
+
+// validate envelopes MUSTs, see `BundlePolicy`
+pub fn check_envelope(
+    index: usize,
+    envelope: &TxEnvelopeWrapper,
+    mode: ValidationMode,
+) -> Result<(), Error> {
+    BundlePolicy::from(mode).check(index, envelope)
+}
+
+// hex calldata, with or without the 0x prefix
+pub fn decode_bundle_hex(calldata: &str) -> Result<BundleData, Error> {
+    decode_bundle_hex_with(calldata, &DecodeConfig::default())
+}
+
+pub fn decode_bundle_hex_with(calldata: &str, config: &DecodeConfig) -> Result<BundleData, Error> {
+    let bytes = CalldataEncoding::Hex.decode(calldata.as_bytes())?;
+    decode_bundle_bytes_with(&bytes, config)
+}
+
+// raw, hex or base64 calldata, see `CalldataEncoding::detect`
+pub fn decode_bundle_calldata(input: &[u8]) -> Result<BundleData, Error> {
+    decode_bundle_calldata_with(input, &DecodeConfig::default())
+}
+
+pub fn decode_bundle_calldata_with(
+    input: &[u8],
+    config: &DecodeConfig,
+) -> Result<BundleData, Error> {
+    let bytes = CalldataEncoding::detect(input).decode(input)?;
+    decode_bundle_bytes_with(&bytes, config)
+}
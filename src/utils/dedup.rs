@@ -0,0 +1,64 @@
+use crate::utils::core::decode_config::DecodeConfig;
+use crate::utils::errors::Error;
+use crate::utils::evm::{decode_bundle_tx, retrieve_bundle_tx_from};
+use crate::utils::receipt_store::ReceiptStore;
+use crate::utils::registry::{bundle_id_name, Registry};
+use alloy::primitives::B256;
+use async_trait::async_trait;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+// knows bundles already posted, by their canonical id (see `BundleData::id`)
+#[async_trait]
+pub trait BundleIndex: Debug + Send + Sync {
+    // txid of an earlier bundle tx with this bundle id
+    async fn find_bundle(&self, bundle_id: B256) -> Result<Option<String>, Error>;
+}
+
+// the bundles whose receipts were saved in the store
+#[derive(Debug, Clone)]
+pub struct ReceiptIndex(pub Arc<dyn ReceiptStore>);
+
+#[async_trait]
+impl BundleIndex for ReceiptIndex {
+    async fn find_bundle(&self, bundle_id: B256) -> Result<Option<String>, Error> {
+        let receipt = self.0.find_bundle(&bundle_id.to_string())?;
+        Ok(receipt.map(|receipt| receipt.bundle_txid))
+    }
+}
+
+// bundle ids published with `Registry::publish_bundle_id`. Anyone can publish a name, so
+// a hit counts only once its tx, fetched from the registry node, decodes to the same id.
+#[async_trait]
+impl BundleIndex for Registry {
+    async fn find_bundle(&self, bundle_id: B256) -> Result<Option<String>, Error> {
+        let txid = match self.resolve(&bundle_id_name(bundle_id)).await {
+            Ok(txid) => txid,
+            Err(Error::NameNotRegistered(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let metadata = match retrieve_bundle_tx_from(&self.rpc_url, txid.clone()).await {
+            Ok(metadata) => metadata,
+            Err(Error::BundleRetrievalProblem) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        // any tx that is not the same bundle is no duplicate
+        match decode_bundle_tx(metadata, &DecodeConfig::default()) {
+            Ok((_, bundle)) if bundle.id() == bundle_id => Ok(Some(txid)),
+            _ => Ok(None),
+        }
+    }
+}
+
+// the first index, in order, knowing the bundle
+pub async fn find_duplicate(
+    indexes: &[Arc<dyn BundleIndex>],
+    bundle_id: B256,
+) -> Result<Option<String>, Error> {
+    for index in indexes {
+        if let Some(txid) = index.find_bundle(bundle_id).await? {
+            return Ok(Some(txid));
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,148 @@
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use alloy::primitives::Address;
+use async_trait::async_trait;
+use futures::future::join_all;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutedEnvelope {
+    // position in the bundle
+    pub index: usize,
+    pub envelope: TxEnvelopeWrapper,
+}
+
+// receives the envelopes of one target, `None` for envelopes created without a target
+#[async_trait]
+pub trait EnvelopeHandler: Debug + Send + Sync {
+    async fn handle(
+        &self,
+        target: Option<Address>,
+        envelopes: Vec<RoutedEnvelope>,
+    ) -> Result<(), Error>;
+}
+
+// the zero address target of envelopes created without one is `None`
+pub fn envelope_target(envelope: &TxEnvelopeWrapper) -> Result<Option<Address>, Error> {
+    let to = envelope.to.parse::<Address>()?;
+    Ok(Some(to).filter(|to| *to != Address::ZERO))
+}
+
+pub type TargetGroup = (Option<Address>, Vec<RoutedEnvelope>);
+
+// groups in order of first appearance, envelopes in bundle order within each group.
+// Envelopes with an unparsable target are returned apart.
+pub fn group_by_target(bundle: &BundleData) -> (Vec<TargetGroup>, Vec<usize>) {
+    let mut groups: Vec<TargetGroup> = vec![];
+    let mut positions: HashMap<Option<Address>, usize> = HashMap::new();
+    let mut invalid = vec![];
+    for (index, envelope) in bundle.envelopes.iter().enumerate() {
+        let Ok(target) = envelope_target(envelope) else {
+            invalid.push(index);
+            continue;
+        };
+        let position = *positions.entry(target).or_insert_with(|| {
+            groups.push((target, vec![]));
+            groups.len() - 1
+        });
+        groups[position].1.push(RoutedEnvelope {
+            index,
+            envelope: envelope.clone(),
+        });
+    }
+    (groups, invalid)
+}
+
+#[derive(Debug)]
+pub struct GroupOutcome {
+    pub target: Option<Address>,
+    pub indices: Vec<usize>,
+    pub result: Result<(), Error>,
+}
+
+#[derive(Debug, Default)]
+pub struct DispatchReport {
+    // handled groups, in order of first appearance
+    pub groups: Vec<GroupOutcome>,
+    // envelopes no handler was registered for, or with an unparsable target
+    pub unrouted: Vec<usize>,
+}
+
+impl DispatchReport {
+    pub fn is_ok(&self) -> bool {
+        self.groups.iter().all(|g| g.result.is_ok())
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &GroupOutcome> {
+        self.groups.iter().filter(|g| g.result.is_err())
+    }
+}
+
+// fans the envelopes of a decoded bundle out to the handler of their target
+#[derive(Debug, Clone, Default)]
+pub struct EnvelopeDispatcher {
+    routes: HashMap<Address, Arc<dyn EnvelopeHandler>>,
+    untargeted: Option<Arc<dyn EnvelopeHandler>>,
+    // targets without a route
+    fallback: Option<Arc<dyn EnvelopeHandler>>,
+}
+
+impl EnvelopeDispatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route(mut self, target: Address, handler: Arc<dyn EnvelopeHandler>) -> Self {
+        self.routes.insert(target, handler);
+        self
+    }
+
+    pub fn untargeted(mut self, handler: Arc<dyn EnvelopeHandler>) -> Self {
+        self.untargeted = Some(handler);
+        self
+    }
+
+    pub fn fallback(mut self, handler: Arc<dyn EnvelopeHandler>) -> Self {
+        self.fallback = Some(handler);
+        self
+    }
+
+    pub fn handler(&self, target: Option<Address>) -> Option<&Arc<dyn EnvelopeHandler>> {
+        match target {
+            Some(target) => self.routes.get(&target).or(self.fallback.as_ref()),
+            None => self.untargeted.as_ref().or(self.fallback.as_ref()),
+        }
+    }
+
+    // handlers run concurrently, one call per target, and a failing handler does not
+    // stop the others
+    pub async fn dispatch(&self, bundle: &BundleData) -> DispatchReport {
+        let (groups, mut unrouted) = group_by_target(bundle);
+        let mut routed = vec![];
+        for (target, envelopes) in groups {
+            match self.handler(target) {
+                Some(handler) => routed.push((target, handler.clone(), envelopes)),
+                None => unrouted.extend(envelopes.iter().map(|e| e.index)),
+            }
+        }
+        unrouted.sort_unstable();
+
+        let calls = routed
+            .into_iter()
+            .map(|(target, handler, envelopes)| async move {
+                let indices = envelopes.iter().map(|e| e.index).collect();
+                GroupOutcome {
+                    target,
+                    indices,
+                    result: handler.handle(target, envelopes).await,
+                }
+            });
+        DispatchReport {
+            groups: join_all(calls).await,
+            unrouted,
+        }
+    }
+}
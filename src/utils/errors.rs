@@ -1,4 +1,4 @@
-use crate::utils::errors;
+use crate::utils::quorum::EndpointReply;
 use alloy::hex::FromHexError;
 use alloy::network::{Ethereum, TransactionBuilderError};
 use alloy::signers::local::LocalSignerError;
@@ -6,6 +6,26 @@ use alloy::transports::{RpcError, TransportErrorKind};
 use eyre::ErrReport;
 use thiserror::Error;
 
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EnvelopeError {
+    #[error("envelope has no data")]
+    MissingData,
+    #[error("envelope data is empty")]
+    EmptyData,
+    #[error("envelope data is {0} bytes, more than the maximum of {1}")]
+    DataTooLarge(usize, usize),
+    #[error("envelope target {0} is not an address")]
+    InvalidTarget(String),
+    #[error("envelope has {0} tags, more than the maximum allowed")]
+    TooManyTags(usize),
+    #[error("envelope tag {0} is malformed")]
+    MalformedTag(usize),
+    #[error("data-only envelope has target {0}")]
+    DataOnlyTarget(String),
+    #[error("envelope path {0} is malformed")]
+    InvalidPath(String),
+}
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Bundle must have envelopes")]
@@ -16,8 +36,11 @@ pub enum Error {
     BundleRetrievalProblem,
     #[error("Address is not verified")]
     UnverifiedAddress,
+    // kept for its stable code, a bundle that can't be created returns the error that
+    // stopped it
     #[error("Bundle could not be created")]
     BundleNotCreated,
+    // kept for callers building their own errors, the library itself uses specific variants
     #[error("Other")]
     Other(String),
     #[error("Error parsing private key")]
@@ -26,12 +49,327 @@ pub enum Error {
     InvalidRpcUrl,
     #[error("There's been an issue with the current RPC call")]
     RpcTransportError(#[from] RpcError<TransportErrorKind>),
+    #[error("HTTP request failed")]
+    HttpError(#[from] reqwest::Error),
+    #[error("Fee history has no base fee")]
+    MissingBaseFee,
     #[error("Hex could not be parsed")]
     HexError(#[from] FromHexError),
+    #[error("Base64 could not be parsed")]
+    Base64Error(#[from] base64::DecodeError),
+    #[error("String is not valid UTF-8")]
+    Utf8Error(#[from] std::str::Utf8Error),
+    #[error("String contains a NUL byte")]
+    NulError(#[from] std::ffi::NulError),
     #[error("Signature or its keys have errors")]
     SigningError(#[from] LocalSignerError),
     #[error("Eyre Other")]
     ReportError(#[from] ErrReport),
+    #[error("Bundle header is malformed")]
+    InvalidBundleHeader,
+    #[error("Unsupported bundle format version {0}")]
+    UnsupportedBundleVersion(u8),
+    #[error("Compression quality must be 0-11 and window 10-24")]
+    InvalidCompressionParams,
+    #[error("Bundle data could not be decompressed")]
+    DecompressionError(#[source] std::io::Error),
+    #[error("Bundle data decompresses to more than {0} bytes")]
+    DecompressedSizeExceeded(usize),
+    #[error("A shared dictionary is needed to (de)compress the envelopes")]
+    DictionaryNeeded,
+    #[error("Shared dictionary {0} is unknown")]
+    UnknownDictionary(String),
+    #[error("Bundle data could not be deserialized")]
+    DeserializationError(#[source] std::io::Error),
+    #[error("Envelope {0} of the bundle is corrupted")]
+    CorruptedEnvelope(usize),
+    #[error("Bundle item does not match its checksum")]
+    ChecksumMismatch,
+    #[error("Envelope signature is invalid")]
+    InvalidSignature,
+    #[error("Authorship proof does not match the envelope or its author")]
+    AuthorshipMismatch,
+    #[error("Envelope {0} breaks the bundle envelope MUSTs")]
+    NonconformingEnvelope(usize),
+    #[error("Envelope {0} breaks the bundle policy: {1}")]
+    PolicyViolation(usize, String),
+    #[error("Raw transaction is not a valid bundle tx")]
+    InvalidRawTransaction,
+    #[error("Signed envelope is not an EIP-155 legacy tx to an address")]
+    InvalidSignedEnvelope,
+    #[error("Bundle tx inclusion could not be verified against its block header")]
+    UnverifiedInclusion,
+    #[error("Quorum of {0} needs between 1 and the {1} endpoints")]
+    InvalidQuorum(usize, usize),
+    #[error("RPC endpoints did not reach a quorum on the bundle payload: {0:?}")]
+    QuorumMismatch(Vec<EndpointReply>),
+    #[error("Stream pointers do not link up")]
+    BrokenStream,
+    #[error("Payload could not be serialized or deserialized")]
+    PayloadCodecError(#[from] serde_json::Error),
+    #[cfg(feature = "protobuf")]
+    #[error("Protobuf payload could not be decoded")]
+    ProtobufError(#[from] prost::DecodeError),
+    #[error("Envelope could not be found")]
+    EnvelopeNotFound,
+    #[error("Name {0} is not registered")]
+    NameNotRegistered(String),
+    #[error("Registry contract call failed")]
+    ContractError(#[from] alloy::contract::Error),
+    #[error("Envelope is invalid: {0}")]
+    Envelope(#[from] EnvelopeError),
+    #[error("Envelope {0} is invalid: {1}")]
+    InvalidEnvelope(usize, EnvelopeError),
+    #[error("Bundle has {0} envelopes, more than the maximum of {1}")]
+    TooManyEnvelopes(usize, usize),
+    #[error("Bundle {0} was dropped by a reorg and not resubmitted")]
+    BundleDropped(String),
+    #[error("Bundle profile {0} is unknown")]
+    UnknownProfile(String),
+    #[error("Operation was cancelled")]
+    Cancelled,
+    #[error("Daemon is draining and no longer accepts envelopes")]
+    DaemonDraining,
+    #[error("Link signature is invalid")]
+    InvalidLinkSignature,
+    #[error("Link expired at {0}")]
+    LinkExpired(u64),
+    #[error("Continuation token is malformed")]
+    InvalidContinuationToken,
+    #[error("Bundle tx fee of {0} wei is above the maximum of {1}")]
+    BundleFeeExceeded(u128, u128),
+    #[error("Payer balance of {0} wei is below the {1} needed")]
+    InsufficientBalance(u128, u128),
+    #[error("Spend limit of {1} wei reached, {0} wei were spent within its window")]
+    SpendLimitExceeded(u128, u128),
+    #[error("Webhook signature is invalid")]
+    InvalidWebhookSignature,
+    #[error("Webhook {0} answered with status {1}")]
+    WebhookRejected(String, u16),
+    #[error("Sidecar sink {0} answered with status {1}")]
+    SidecarRejected(String, u16),
+    #[error("Sidecar sink {0} is misconfigured")]
+    InvalidSidecarSink(String),
+    #[error("Receipt store is unavailable")]
+    ReceiptStoreError,
+    #[error("Checkpoint store is unavailable")]
+    CheckpointStoreError,
+    #[error("Audit log is unavailable")]
+    AuditLogError,
+    #[error("Path index is unavailable")]
+    PathIndexError,
+    #[error("Search index is unavailable")]
+    SearchIndexError,
+    #[error("State snapshot version {0} is not supported")]
+    UnsupportedSnapshot(u32),
+    #[error("State snapshot expects nonce {0}, the RPC node is at {1}")]
+    SnapshotNonceAhead(u64, u64),
+    #[error("Tenant {0} is unknown")]
+    UnknownTenant(String),
+    #[error("Tenant {0} API key is missing or invalid")]
+    TenantUnauthorized(String),
+    #[error("Tenant {0} has no API key")]
+    TenantKeyNeeded(String),
+    #[error("Daemon admin key is missing or invalid")]
+    AdminUnauthorized,
+    #[error("Gateway API key is missing or invalid")]
+    GatewayUnauthorized,
+    #[error("Tenant {0} quota exceeded: {1}")]
+    TenantQuotaExceeded(String, String),
+    #[error("Bundle round trip mismatch: {0}")]
+    RoundTripMismatch(String),
+    #[error("Rate limit of {0} requests per second is not a positive finite rate")]
+    InvalidRateLimit(f64),
+    #[error("Bundler call panicked: {0}")]
+    Panicked(String),
+    #[error("I/O failed")]
+    IoError(#[from] std::io::Error),
+    #[cfg(feature = "parquet")]
+    #[error("Parquet export failed")]
+    ParquetError(#[from] parquet::errors::ParquetError),
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite store query failed")]
+    SqliteError(#[from] rusqlite::Error),
+    #[cfg(feature = "grpc")]
+    #[error("gRPC transport failed")]
+    GrpcTransportError(#[from] tonic::transport::Error),
     #[error("Allow Tx Error")]
     TransactionError(#[from] TransactionBuilderError<Ethereum>),
 }
+
+// coarse error categories, for callers deciding how to react (retry, reject, alert...)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    // RPC node and HTTP endpoint failures
+    Rpc,
+    // keys, signatures and tx signing
+    Signing,
+    // calldata, header, envelope and payload codecs
+    Serialization,
+    Compression,
+    // inputs rejected before any work
+    Validation,
+    // the bundle tx did not land
+    Broadcast,
+    NotFound,
+    // receipt stores, exports and local I/O
+    Storage,
+    Unavailable,
+    Cancelled,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Rpc => "rpc",
+            ErrorKind::Signing => "signing",
+            ErrorKind::Serialization => "serialization",
+            ErrorKind::Compression => "compression",
+            ErrorKind::Validation => "validation",
+            ErrorKind::Broadcast => "broadcast",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Storage => "storage",
+            ErrorKind::Unavailable => "unavailable",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::Other => "other",
+        }
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        self.describe().0
+    }
+
+    // stable identifier of the variant, `<kind>.<name>`. Codes are never renamed nor
+    // reused, unlike messages which may change.
+    pub fn code(&self) -> &'static str {
+        self.describe().1
+    }
+
+    fn describe(&self) -> (ErrorKind, &'static str) {
+        use ErrorKind::*;
+        match self {
+            Error::EnvelopesNeeded => (Validation, "validation.envelopes_needed"),
+            Error::PrivateKeyNeeded => (Validation, "validation.private_key_needed"),
+            Error::BundleRetrievalProblem => (NotFound, "not_found.bundle"),
+            Error::UnverifiedAddress => (Validation, "validation.unverified_address"),
+            Error::BundleNotCreated => (Broadcast, "broadcast.bundle_not_created"),
+            Error::Other(_) => (Other, "other"),
+            Error::PrivateKeyParsingError => (Signing, "signing.invalid_private_key"),
+            Error::InvalidRpcUrl => (Validation, "validation.invalid_rpc_url"),
+            Error::RpcTransportError(_) => (Rpc, "rpc.transport"),
+            Error::HttpError(_) => (Rpc, "rpc.http"),
+            Error::MissingBaseFee => (Rpc, "rpc.missing_base_fee"),
+            Error::HexError(_) => (Serialization, "serialization.hex"),
+            Error::Base64Error(_) => (Serialization, "serialization.base64"),
+            Error::Utf8Error(_) => (Validation, "validation.utf8"),
+            Error::NulError(_) => (Validation, "validation.nul_byte"),
+            Error::SigningError(_) => (Signing, "signing.signer"),
+            Error::ReportError(_) => (Rpc, "rpc.report"),
+            Error::InvalidBundleHeader => (Serialization, "serialization.invalid_header"),
+            Error::UnsupportedBundleVersion(_) => {
+                (Serialization, "serialization.unsupported_version")
+            }
+            Error::InvalidCompressionParams => (Validation, "validation.compression_params"),
+            Error::DecompressionError(_) => (Compression, "compression.decompression"),
+            Error::DecompressedSizeExceeded(_) => (Compression, "compression.size_exceeded"),
+            Error::DictionaryNeeded => (Compression, "compression.dictionary_needed"),
+            Error::UnknownDictionary(_) => (Compression, "compression.unknown_dictionary"),
+            Error::DeserializationError(_) => (Serialization, "serialization.deserialization"),
+            Error::CorruptedEnvelope(_) => (Serialization, "serialization.corrupted_envelope"),
+            Error::ChecksumMismatch => (Serialization, "serialization.checksum_mismatch"),
+            Error::InvalidSignature => (Signing, "signing.invalid_signature"),
+            Error::AuthorshipMismatch => (Signing, "signing.authorship_mismatch"),
+            Error::NonconformingEnvelope(_) => (Validation, "validation.nonconforming_envelope"),
+            Error::PolicyViolation(..) => (Validation, "validation.policy_violation"),
+            Error::InvalidRawTransaction => (Serialization, "serialization.raw_transaction"),
+            Error::InvalidSignedEnvelope => (Serialization, "serialization.signed_envelope"),
+            Error::UnverifiedInclusion => (Rpc, "rpc.unverified_inclusion"),
+            Error::InvalidQuorum(..) => (Validation, "validation.quorum"),
+            Error::QuorumMismatch(_) => (Rpc, "rpc.quorum_mismatch"),
+            Error::BrokenStream => (Validation, "validation.broken_stream"),
+            Error::PayloadCodecError(_) => (Serialization, "serialization.payload"),
+            #[cfg(feature = "protobuf")]
+            Error::ProtobufError(_) => (Serialization, "serialization.protobuf"),
+            Error::EnvelopeNotFound => (NotFound, "not_found.envelope"),
+            Error::NameNotRegistered(_) => (NotFound, "not_found.name"),
+            Error::ContractError(_) => (Rpc, "rpc.contract"),
+            // a standalone envelope, and one of a bundle at its index
+            Error::Envelope(_) => (Validation, "validation.envelope"),
+            Error::InvalidEnvelope(..) => (Validation, "validation.invalid_envelope"),
+            Error::TooManyEnvelopes(..) => (Validation, "validation.too_many_envelopes"),
+            Error::BundleDropped(_) => (Broadcast, "broadcast.bundle_dropped"),
+            Error::UnknownProfile(_) => (Validation, "validation.unknown_profile"),
+            Error::Cancelled => (Cancelled, "cancelled"),
+            Error::DaemonDraining => (Unavailable, "unavailable.daemon_draining"),
+            Error::InvalidLinkSignature => (Signing, "signing.invalid_link"),
+            Error::LinkExpired(_) => (Validation, "validation.link_expired"),
+            Error::InvalidContinuationToken => (Validation, "validation.continuation_token"),
+            Error::BundleFeeExceeded(..) => (Broadcast, "broadcast.bundle_fee_exceeded"),
+            Error::InsufficientBalance(..) => (Broadcast, "broadcast.insufficient_balance"),
+            Error::SpendLimitExceeded(..) => (Broadcast, "broadcast.spend_limit_exceeded"),
+            Error::InvalidWebhookSignature => (Signing, "signing.invalid_webhook"),
+            Error::WebhookRejected(..) => (Unavailable, "unavailable.webhook_rejected"),
+            Error::SidecarRejected(..) => (Unavailable, "unavailable.sidecar_rejected"),
+            Error::InvalidSidecarSink(_) => (Validation, "validation.sidecar_sink"),
+            Error::ReceiptStoreError => (Storage, "storage.receipt_store"),
+            Error::CheckpointStoreError => (Storage, "storage.checkpoint_store"),
+            Error::AuditLogError => (Storage, "storage.audit_log"),
+            Error::PathIndexError => (Storage, "storage.path_index"),
+            Error::SearchIndexError => (Storage, "storage.search_index"),
+            Error::UnsupportedSnapshot(_) => (Validation, "validation.snapshot_version"),
+            Error::SnapshotNonceAhead(..) => (Unavailable, "unavailable.snapshot_nonce"),
+            Error::UnknownTenant(_) => (NotFound, "not_found.tenant"),
+            Error::TenantUnauthorized(_) => (Validation, "validation.tenant_unauthorized"),
+            Error::TenantKeyNeeded(_) => (Validation, "validation.tenant_key_needed"),
+            Error::AdminUnauthorized => (Validation, "validation.admin_unauthorized"),
+            Error::GatewayUnauthorized => (Validation, "validation.gateway_unauthorized"),
+            Error::TenantQuotaExceeded(..) => (Unavailable, "unavailable.tenant_quota"),
+            Error::RoundTripMismatch(_) => (Serialization, "serialization.roundtrip_mismatch"),
+            Error::InvalidRateLimit(_) => (Validation, "validation.invalid_rate_limit"),
+            Error::Panicked(_) => (Other, "other.panicked"),
+            Error::IoError(_) => (Storage, "storage.io"),
+            #[cfg(feature = "parquet")]
+            Error::ParquetError(_) => (Storage, "storage.parquet"),
+            #[cfg(feature = "sqlite")]
+            Error::SqliteError(_) => (Storage, "storage.sqlite"),
+            #[cfg(feature = "grpc")]
+            Error::GrpcTransportError(_) => (Rpc, "rpc.grpc_transport"),
+            Error::TransactionError(_) => (Signing, "signing.transaction"),
+        }
+    }
+
+    // status of the error answered by the HTTP servers, from its kind, credential and
+    // quota errors aside
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Error::GatewayUnauthorized
+            | Error::AdminUnauthorized
+            | Error::TenantUnauthorized(_) => 401,
+            Error::PrivateKeyNeeded | Error::InvalidLinkSignature | Error::LinkExpired(_) => 403,
+            Error::TenantQuotaExceeded(..) => 429,
+            _ => match self.kind() {
+                ErrorKind::Validation | ErrorKind::Serialization | ErrorKind::Compression => 400,
+                ErrorKind::NotFound => 404,
+                // the RPC node failed or rejected the bundle tx
+                ErrorKind::Rpc | ErrorKind::Broadcast => 502,
+                ErrorKind::Unavailable | ErrorKind::Cancelled => 503,
+                ErrorKind::Signing | ErrorKind::Storage | ErrorKind::Other => 500,
+            },
+        }
+    }
+
+    // `Display` of the error and of each of its sources, outermost first
+    pub fn chain(&self) -> Vec<String> {
+        let mut messages = vec![self.to_string()];
+        let mut source = std::error::Error::source(self);
+        while let Some(e) = source {
+            messages.push(e.to_string());
+            source = e.source();
+        }
+        messages
+    }
+}
@@ -1,32 +1,99 @@
-use crate::utils::core::bundle_data::BundleData;
+use crate::utils::audit_log::{AuditAction, AuditOutcome, AuditRecord};
+use crate::utils::cancellation::{check_cancellation, with_cancellation};
+use crate::utils::core::bundle_config::BundleConfig;
+use crate::utils::core::bundle_data::{bundle_id_from_digests, envelope_digest, BundleData};
+use crate::utils::core::bundle_header::BundleHeader;
+use crate::utils::core::bundle_inspection::BundleInspection;
+use crate::utils::core::bundle_item::{BundleItem, EncodeBuffers};
+use crate::utils::core::bundle_receipt::BundleReceipt;
 use crate::utils::core::bundle_tx_metadata::BundleTxMetadata;
+use crate::utils::core::chain_target::{ChainTarget, TargetSubmission};
+use crate::utils::core::decode_config::DecodeConfig;
 use crate::utils::core::envelope::Envelope;
+use crate::utils::core::envelope_stats::EnvelopeStats;
+use crate::utils::core::gas_cost::{CalldataCost, OptimizedEncoding};
+use crate::utils::core::packing::{first_fit_decreasing, PackingStrategy};
+use crate::utils::core::policy::BundlePolicy;
+use crate::utils::core::signed_bundle_tx::{PayloadStats, SignedBundleTx};
 use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
-use crate::utils::errors::Error;
+use crate::utils::decoder::{
+    decode_bundle_bytes_with, decode_bundle_calldata_with, CalldataEncoding,
+};
+use crate::utils::dedup::find_duplicate;
+use crate::utils::errors::{EnvelopeError, Error};
+use crate::utils::gas_oracle::GasFees;
+use crate::utils::inclusion::{
+    wait_for_inclusion_timeout_from, with_timeout, BroadcastOutcome, ConfirmationLevel,
+};
+use crate::utils::rate_limit::RateLimitLayer;
+use crate::utils::replay::{RpcReplay, RpcReplayLayer};
+use crate::utils::retry::{RetryConfig, RetryLayer};
+use crate::utils::sidecar::{publish_sidecars, BundleSidecar};
+use crate::utils::spend_guard::{SpendGuard, SpendReservation};
+pub use crate::utils::testing::generate_random_calldata;
 use {
     crate::utils::constants::{ADDRESS_BABE1, CHAIN_ID, WVM_RPC_URL},
     alloy::{
-        consensus::TxEnvelope,
-        network::{EthereumWallet, TransactionBuilder},
-        primitives::{Address, B256, U256},
-        providers::{Provider, ProviderBuilder, RootProvider},
+        consensus::{Transaction, TxEnvelope},
+        eips::eip2718::{Decodable2718, Encodable2718},
+        network::{Ethereum, EthereumWallet, TransactionBuilder},
+        primitives::{keccak256, Address, B256, U256},
+        providers::{PendingTransactionBuilder, Provider, RootProvider},
+        rpc::client::{BuiltInConnectionString, ClientBuilder},
         rpc::types::TransactionRequest,
         signers::local::PrivateKeySigner,
-        transports::http::{Client, Http},
+        transports::{BoxTransport, RpcError, TransportErrorKind},
     },
-    eyre::OptionExt,
-    futures::future::join_all,
-    hex,
-    rand::Rng,
+    futures::future::{join_all, try_join_all},
+    futures::stream::{self, StreamExt, TryStreamExt},
     serde_json,
     std::str::FromStr,
-    tokio::task,
+    std::time::{Duration, Instant},
 };
 
-async fn create_evm_http_client(rpc_url: &str) -> Result<RootProvider<Http<Client>>, Error> {
+pub type EvmProvider = RootProvider<BoxTransport>;
+pub type PendingBundleTx = PendingTransactionBuilder<BoxTransport, Ethereum>;
+
+// http(s) and ws(s) urls, or the path (optionally `ipc://`) of a co-located node IPC
+// socket. WS and IPC connections are driven by a task on the tokio runtime.
+pub async fn create_evm_client(rpc_url: &str) -> Result<EvmProvider, Error> {
+    create_evm_client_with(rpc_url, &RetryConfig::default()).await
+}
+
+// calls are retried by `RetryLayer` according to the class of their errors, each attempt
+// waiting on the `set_rpc_rate_limit` limiter and recorded or replayed by `set_rpc_replay`.
+// A replayed client connects to nothing, whatever its url.
+pub async fn create_evm_client_with(
+    rpc_url: &str,
+    retry: &RetryConfig,
+) -> Result<EvmProvider, Error> {
+    let replay = RpcReplayLayer::global();
+    let replaying = matches!(replay.mode(), Some(RpcReplay::Replay(_)));
+    let builder = ClientBuilder::default()
+        .layer(RetryLayer::new(*retry))
+        .layer(RateLimitLayer::global())
+        .layer(replay);
+    if replaying {
+        let placeholder = WVM_RPC_URL.parse().map_err(|_| Error::InvalidRpcUrl)?;
+        return Ok(RootProvider::new(builder.http(placeholder).boxed()));
+    }
+    if rpc_url.starts_with("http://") || rpc_url.starts_with("https://") {
+        let rpc_url = rpc_url.parse().map_err(|_| Error::InvalidRpcUrl)?;
+        return Ok(RootProvider::new(builder.http(rpc_url).boxed()));
+    }
+    let connect: BuiltInConnectionString = rpc_url.parse().map_err(|_| Error::InvalidRpcUrl)?;
+    let client = builder.connect_boxed(connect).await?;
+    Ok(RootProvider::new(client.boxed()))
+}
+
+// http(s) client without retries, rate limited like `create_evm_client`
+pub fn create_http_client(rpc_url: &str) -> Result<EvmProvider, Error> {
     let rpc_url = rpc_url.parse().map_err(|_| Error::InvalidRpcUrl)?;
-    let provider = ProviderBuilder::new().on_http(rpc_url);
-    Ok(provider)
+    let client = ClientBuilder::default()
+        .layer(RateLimitLayer::global())
+        .layer(RpcReplayLayer::global())
+        .http(rpc_url);
+    Ok(RootProvider::new(client.boxed()))
 }
 
 pub async fn create_envelope(
@@ -43,9 +110,7 @@ pub async fn create_envelope(
             .map(|t| t.parse::<Address>().unwrap_or(Address::ZERO))
             .unwrap_or(Address::ZERO);
 
-        let envelope_data = envelope
-            .data
-            .ok_or_else(|| Error::Other("Data Required".to_string()))?;
+        let envelope_data = envelope.data.ok_or(EnvelopeError::MissingData)?;
 
         let tx = TransactionRequest::default()
             .with_to(envelope_target_address)
@@ -63,117 +128,1029 @@ pub async fn create_envelope(
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BroadcastErrorKind {
+    NonceTooLow,
+    ReplacementUnderpriced,
+    Other,
+}
+
+pub fn classify_broadcast_error(error: &RpcError<TransportErrorKind>) -> BroadcastErrorKind {
+    let Some(payload) = error.as_error_resp() else {
+        return BroadcastErrorKind::Other;
+    };
+    let message = payload.message.to_lowercase();
+    if message.contains("nonce too low") {
+        BroadcastErrorKind::NonceTooLow
+    } else if message.contains("replacement transaction underpriced")
+        || message.contains("transaction underpriced")
+    {
+        BroadcastErrorKind::ReplacementUnderpriced
+    } else {
+        BroadcastErrorKind::Other
+    }
+}
+
+// replacement txs must pay at least 10% more than the tx they replace, rounded up so
+// small fees still grow (a 0 fee stays 0)
+pub(crate) fn bump_fees(fees: GasFees) -> GasFees {
+    let bump = |fee: u128| fee + fee.div_ceil(8);
+    GasFees {
+        max_fee_per_gas: bump(fees.max_fee_per_gas),
+        max_priority_fee_per_gas: bump(fees.max_priority_fee_per_gas),
+    }
+}
+
+pub(crate) async fn sign_envelope(
+    input: Envelope,
+    private_key: &str,
+) -> Result<TxEnvelopeWrapper, Error> {
+    let (compression, tags) = (input.compression, input.tags.clone());
+    let extensions = input.extensions.clone();
+    let tx = create_envelope(Some(private_key), input).await?;
+    let mut wrapper = TxEnvelopeWrapper::from_envelope(tx);
+    wrapper.compression = compression;
+    wrapper.tags = tags;
+    wrapper.extensions = extensions;
+    Ok(wrapper)
+}
+
+// signs envelopes concurrently on the caller's executor, no task is spawned
+async fn sign_envelopes(
+    envelope_inputs: Vec<Envelope>,
+    private_key: &str,
+) -> Result<Vec<TxEnvelopeWrapper>, Error> {
+    let futures = envelope_inputs
+        .into_iter()
+        .map(|input| sign_envelope(input, private_key));
+    try_join_all(futures).await
+}
+
+// calldata of a signed bundle, with what the callers keep once it is built
+struct EncodedBundle {
+    calldata: Vec<u8>,
+    envelope_ids: Vec<String>,
+    bundle_id: B256,
+    encoding: Option<OptimizedEncoding>,
+}
+
+// memory budget mode: at most `envelope_concurrency` envelopes are signed and compressed
+// at once, each signed envelope is dropped as soon as its item is ready and items are
+// serialized straight into the calldata. Returns the calldata and envelope ids.
+pub async fn encode_bundle_budgeted(
+    envelope_inputs: Vec<Envelope>,
+    private_key: &str,
+    header: &BundleHeader,
+    config: &BundleConfig,
+) -> Result<(Vec<u8>, Vec<String>), Error> {
+    let encoded = encode_budgeted(envelope_inputs, private_key, header, config).await?;
+    Ok((encoded.calldata, encoded.envelope_ids))
+}
+
+async fn encode_budgeted(
+    envelope_inputs: Vec<Envelope>,
+    private_key: &str,
+    header: &BundleHeader,
+    config: &BundleConfig,
+) -> Result<EncodedBundle, Error> {
+    config.compression.validate()?;
+    let concurrency = config
+        .envelope_concurrency()
+        .unwrap_or(envelope_inputs.len().max(1));
+    let mut items: Vec<_> = stream::iter(envelope_inputs.into_iter().enumerate())
+        .map(|(i, input)| async move {
+            let envelope = sign_envelope(input, private_key).await?;
+            config.check_policy(i, &envelope)?;
+            let item = BundleItem::from_envelope(&envelope, config)?;
+            let digest = envelope_digest(&envelope);
+            Ok::<_, Error>((
+                config.ordering.sort_key(&envelope),
+                envelope.hash,
+                digest,
+                item,
+            ))
+        })
+        .buffered(concurrency)
+        .try_collect()
+        .await?;
+    items.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut header = header.clone();
+    header.dictionary_id = config.dictionary.as_ref().map(|d| d.id.clone());
+    let mut body_len = 4;
+    for (_, _, _, item) in &items {
+        body_len += borsh::object_length(item)?;
+    }
+    let mut calldata = header.calldata_prefix(body_len);
+    calldata.extend_from_slice(&(items.len() as u32).to_le_bytes());
+    let mut envelope_ids = Vec::with_capacity(items.len());
+    let mut digests = Vec::with_capacity(items.len());
+    for (_, id, digest, item) in items {
+        borsh::to_writer(&mut calldata, &item)?;
+        envelope_ids.push(id);
+        digests.push(digest);
+    }
+    Ok(EncodedBundle {
+        calldata,
+        envelope_ids,
+        bundle_id: bundle_id_from_digests(&header, &digests),
+        encoding: None,
+    })
+}
+
+// appends to the config audit log, if any
+fn audit(config: &BundleConfig, record: impl FnOnce() -> AuditRecord) -> Result<(), Error> {
+    match &config.audit_log {
+        Some(log) => log.append(&record()),
+        None => Ok(()),
+    }
+}
+
+fn key_address(private_key: &str) -> String {
+    private_key
+        .parse::<PrivateKeySigner>()
+        .map(|signer| signer.address().to_string())
+        .unwrap_or_default()
+}
+
+// envelopes of headed bundle calldata, 0 for legacy calldata
+fn calldata_envelope_count(calldata: &[u8]) -> usize {
+    match BundleHeader::split_versioned_calldata(calldata) {
+        Ok((_, Some(_), body)) => body.get(..4).map_or(0, |count| {
+            u32::from_le_bytes(count.try_into().unwrap()) as usize
+        }),
+        _ => 0,
+    }
+}
+
+async fn sign_and_encode(
+    envelope_inputs: Vec<Envelope>,
+    private_key: &str,
+    header: BundleHeader,
+    config: &BundleConfig,
+) -> Result<EncodedBundle, Error> {
+    let envelope_count = envelope_inputs.len();
+    let encoded = sign_and_encode_unaudited(envelope_inputs, private_key, header, config).await;
+    audit(config, || {
+        let payload_hash = match &encoded {
+            Ok(encoded) => keccak256(&encoded.calldata).to_string(),
+            Err(_) => String::new(),
+        };
+        let record = AuditRecord::new(
+            AuditAction::SignEnvelopes,
+            key_address(private_key),
+            payload_hash,
+        )
+        .envelope_count(envelope_count);
+        match &encoded {
+            Ok(_) => record,
+            Err(e) => record.failed(e),
+        }
+    })?;
+    encoded
+}
+
+async fn sign_and_encode_unaudited(
+    envelope_inputs: Vec<Envelope>,
+    private_key: &str,
+    header: BundleHeader,
+    config: &BundleConfig,
+) -> Result<EncodedBundle, Error> {
+    if config.memory_budget.is_some() {
+        return encode_budgeted(envelope_inputs, private_key, &header, config).await;
+    }
+    let envelopes = sign_envelopes(envelope_inputs, private_key).await?;
+    if let Some(policy) = &config.policy {
+        policy.check_all(&envelopes)?;
+    }
+    encode_signed(envelopes, header, config)
+}
+
+fn encode_signed(
+    mut envelopes: Vec<TxEnvelopeWrapper>,
+    header: BundleHeader,
+    config: &BundleConfig,
+) -> Result<EncodedBundle, Error> {
+    config.ordering.apply(&mut envelopes);
+    let envelope_ids = envelopes.iter().map(|e| e.hash.clone()).collect();
+    let bundle = BundleData { envelopes, header };
+    let (calldata, encoding) = match &config.gas_optimizer {
+        Some(optimizer) => {
+            let (calldata, encoding) = optimizer.optimize(&bundle, config)?;
+            (calldata, Some(encoding))
+        }
+        None => (bundle.encode_calldata_with(config)?, None),
+    };
+    Ok(EncodedBundle {
+        calldata,
+        envelope_ids,
+        bundle_id: bundle.id(),
+        encoding,
+    })
+}
+
+// gas oracle fees, with the config fee overrides applied
+async fn bundle_fees(provider: &EvmProvider, config: &BundleConfig) -> Result<GasFees, Error> {
+    if let Some(fees) = config.fee_overrides() {
+        return Ok(fees);
+    }
+    let fees = config.target_gas_oracle().fees(provider).await?;
+    Ok(config.override_fees(fees))
+}
+
+async fn sign_bundle_tx(
+    calldata: &[u8],
+    wallet: &EthereumWallet,
+    nonce: u64,
+    fees: GasFees,
+    config: &BundleConfig,
+) -> Result<TxEnvelope, Error> {
+    let tx = TransactionRequest::default()
+        .with_to(config.target.bundle_address.parse::<Address>()?)
+        .with_nonce(nonce)
+        .with_chain_id(config.target.chain_id)
+        .with_input(calldata.to_vec())
+        .with_value(U256::from(0))
+        .with_gas_limit(config.target_gas_limit())
+        .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas)
+        .with_max_fee_per_gas(fees.max_fee_per_gas);
+    Ok(tx.build(wallet).await?)
+}
+
+// sends the bundle tx, retrying with bumped fees or (when allowed) a refetched nonce
+async fn send_bundle_tx(
+    calldata: &[u8],
+    provider: &EvmProvider,
+    signer: &PrivateKeySigner,
+    mut nonce: u64,
+    mut fees: GasFees,
+    refetch_nonce: bool,
+    config: &BundleConfig,
+) -> Result<PendingBundleTx, Error> {
+    let wallet = EthereumWallet::from(signer.clone());
+    let payload_hash = keccak256(calldata).to_string();
+    let envelope_count = calldata_envelope_count(calldata);
+    let mut attempt = 0;
+
+    loop {
+        // a send already in flight is never interrupted, the tx may reach the mempool
+        check_cancellation(config.cancellation.as_ref())?;
+        let record = AuditRecord::new(
+            AuditAction::Broadcast,
+            signer.address().to_string(),
+            payload_hash.clone(),
+        )
+        .envelope_count(envelope_count)
+        .tx(
+            config.target.chain_id,
+            nonce,
+            config.target_gas_limit(),
+            fees,
+        )
+        .endpoint(&config.target.rpc_url);
+        let reservation = match &config.spend_guard {
+            Some(guard) => match reserve_spend(guard, provider, signer, fees, config).await {
+                Ok(reservation) => Some(HeldReservation {
+                    guard,
+                    reservation: Some(reservation),
+                }),
+                Err(e) => {
+                    audit(config, || record.failed(&e))?;
+                    return Err(e);
+                }
+            },
+            None => None,
+        };
+        let tx_envelope = match sign_bundle_tx(calldata, &wallet, nonce, fees, config).await {
+            Ok(tx_envelope) => tx_envelope,
+            Err(e) => {
+                audit(config, || record.failed(&e))?;
+                return Err(e);
+            }
+        };
+        let record = record.txid(tx_envelope.tx_hash().to_string());
+        // nothing is sent unless the attempt is on record
+        audit(config, || record.clone().outcome(AuditOutcome::Attempted))?;
+
+        let error = match provider.send_tx_envelope(tx_envelope).await {
+            Ok(tx) => {
+                if let Some(reservation) = reservation {
+                    reservation.keep();
+                }
+                // the tx is out, failing now would only lose its hash, and the attempt
+                // is already on record
+                let _ = audit(config, || record);
+                return Ok(tx);
+            }
+            Err(e) => Error::from(e),
+        };
+        // a replacement with bumped fees is reserved again, in place of this one
+        drop(reservation);
+        audit(config, || record.failed(&error))?;
+        if attempt >= config.broadcast_retries {
+            return Err(error);
+        }
+        attempt += 1;
+
+        let Error::RpcTransportError(rpc_error) = &error else {
+            return Err(error);
+        };
+        match classify_broadcast_error(rpc_error) {
+            BroadcastErrorKind::NonceTooLow if refetch_nonce => {
+                nonce = provider
+                    .get_transaction_count(signer.address())
+                    .pending()
+                    .await?;
+            }
+            BroadcastErrorKind::ReplacementUnderpriced => fees = bump_fees(fees),
+            _ => return Err(error),
+        }
+    }
+}
+
+// a reservation given back to its guard unless the tx it was made for was sent, so no
+// early return of a send leaves it held against the window
+struct HeldReservation<'a> {
+    guard: &'a SpendGuard,
+    reservation: Option<SpendReservation>,
+}
+
+impl HeldReservation<'_> {
+    fn keep(mut self) {
+        self.reservation = None;
+    }
+}
+
+impl Drop for HeldReservation<'_> {
+    fn drop(&mut self) {
+        if let Some(reservation) = self.reservation.take() {
+            self.guard.release(reservation);
+        }
+    }
+}
+
+// the worst case fee of the bundle tx, the payer balance being fetched only when the
+// guard keeps a minimum balance
+async fn reserve_spend(
+    guard: &SpendGuard,
+    provider: &EvmProvider,
+    signer: &PrivateKeySigner,
+    fees: GasFees,
+    config: &BundleConfig,
+) -> Result<SpendReservation, Error> {
+    let fee = (config.target_gas_limit() as u128).saturating_mul(fees.max_fee_per_gas);
+    let balance = match guard.min_balance {
+        Some(_) => {
+            let balance = with_cancellation(config.cancellation.as_ref(), async {
+                Ok(provider.get_balance(signer.address()).await?)
+            })
+            .await?;
+            Some(balance.saturating_to::<u128>())
+        }
+        None => None,
+    };
+    guard.reserve(fee, balance)
+}
+
 async fn broadcast_bundle(
-    envelopes: Vec<u8>,
-    provider: &RootProvider<Http<Client>>,
+    calldata: &[u8],
+    provider: &EvmProvider,
     private_key: Option<String>,
-) -> Result<
-    alloy::providers::PendingTransactionBuilder<Http<Client>, alloy::network::Ethereum>,
-    Error,
-> {
+    config: &BundleConfig,
+) -> Result<PendingBundleTx, Error> {
     if let Some(priv_key) = private_key {
         let signer: PrivateKeySigner = priv_key.parse()?;
-        let wallet = EthereumWallet::from(signer.clone());
-        let nonce = provider
-            .get_transaction_count(signer.clone().address())
-            .await?;
-
-        let tx = TransactionRequest::default()
-            .with_to(ADDRESS_BABE1.parse::<Address>()?)
-            .with_nonce(nonce)
-            .with_chain_id(CHAIN_ID)
-            .with_input(envelopes)
-            .with_value(U256::from(0))
-            .with_gas_limit(490_000_000)
-            .with_max_priority_fee_per_gas(1_000_000_000)
-            .with_max_fee_per_gas(2_000_000_000);
-        let tx_envelope: alloy::consensus::TxEnvelope = tx.build(&wallet).await?;
-        let tx: alloy::providers::PendingTransactionBuilder<
-            Http<Client>,
-            alloy::network::Ethereum,
-        > = provider.send_tx_envelope(tx_envelope).await?;
-
-        Ok(tx)
+        let (nonce, fees) = with_cancellation(config.cancellation.as_ref(), async {
+            let nonce = provider
+                .get_transaction_count(signer.clone().address())
+                .await?;
+            Ok((nonce, bundle_fees(provider, config).await?))
+        })
+        .await?;
+        send_bundle_tx(calldata, provider, &signer, nonce, fees, true, config).await
     } else {
         Err(Error::PrivateKeyNeeded)
     }
 }
 
+// a call of another contract of the config chain (e.g. the registry), sent like a bundle
+// tx: audited, spend guarded and retried, its gas limit being estimated
+pub(crate) async fn send_contract_call(
+    to: &str,
+    input: &[u8],
+    private_key: &str,
+    config: &BundleConfig,
+) -> Result<PendingBundleTx, Error> {
+    let signer: PrivateKeySigner = private_key.parse()?;
+    let provider = create_evm_client_with(&config.target.rpc_url, &config.retry).await?;
+    let call = TransactionRequest::default()
+        .with_from(signer.address())
+        .with_to(to.parse::<Address>()?)
+        .with_input(input.to_vec());
+    let (nonce, fees, gas_limit) = with_cancellation(config.cancellation.as_ref(), async {
+        let nonce = provider.get_transaction_count(signer.address()).await?;
+        let gas_limit = provider.estimate_gas(&call).await?;
+        Ok((nonce, bundle_fees(&provider, config).await?, gas_limit))
+    })
+    .await?;
+    let mut target = config.target.clone();
+    target.bundle_address = to.to_string();
+    let config = config.clone().target(target.gas_limit(gas_limit));
+    send_bundle_tx(input, &provider, &signer, nonce, fees, true, &config).await
+}
+
 pub async fn create_bundle(
     envelope_inputs: Vec<Envelope>,
     private_key: String,
-) -> Result<
-    alloy::providers::PendingTransactionBuilder<Http<Client>, alloy::network::Ethereum>,
-    Error,
-> {
-    let provider = create_evm_http_client(WVM_RPC_URL).await?;
-    let provider = std::sync::Arc::new(provider);
-    let private_key = private_key.clone();
-
-    // Create vector of futures
-    let futures: Vec<_> = envelope_inputs
-        .into_iter()
-        .enumerate()
-        .map(|(i, input)| {
-            let pk = private_key.clone();
-            task::spawn(async move {
-                match create_envelope(Some(&pk), input).await {
-                    Ok(tx) => {
-                        println!("created tx count {}", i);
-                        Ok(TxEnvelopeWrapper::from_envelope(tx))
-                    }
-                    Err(e) => Err(e),
-                }
-            })
-        })
-        .collect();
-
-    // Rest of the function remains the same
-    let results = join_all(futures).await;
-    let envelopes: Vec<TxEnvelopeWrapper> = results
-        .into_iter()
-        .filter_map(|r| r.ok())
-        .filter_map(|r| r.ok())
-        .collect();
+    header: BundleHeader,
+    config: &BundleConfig,
+) -> Result<PendingBundleTx, Error> {
+    let payer_key = private_key.clone();
+    create_sponsored_bundle(envelope_inputs, private_key, payer_key, header, config).await
+}
 
-    let bundle = BundleData::from(envelopes.clone());
-    let serialized = TxEnvelopeWrapper::borsh_ser(&bundle);
-    let compressed = TxEnvelopeWrapper::brotli_compress(&serialized);
+// envelopes are signed by `signer_key` while the bundle tx is signed, and its gas paid,
+// by `payer_key` (e.g. a relayer). A bundle whose idempotency key already has a receipt
+// in the config receipt store is not sent again, the earlier bundle tx is returned.
+pub async fn create_sponsored_bundle(
+    envelope_inputs: Vec<Envelope>,
+    signer_key: String,
+    payer_key: String,
+    header: BundleHeader,
+    config: &BundleConfig,
+) -> Result<PendingBundleTx, Error> {
+    sponsored_bundle(envelope_inputs, signer_key, payer_key, header, config, true).await
+}
 
-    let tx: alloy::providers::PendingTransactionBuilder<Http<Client>, alloy::network::Ethereum> =
-        broadcast_bundle(compressed, &provider, Some(private_key)).await?;
+// without `reuse_receipt`, the bundle is sent even if its idempotency key has a receipt
+// or its id is known to the dedup indexes, e.g. after a reorg dropped the earlier bundle tx
+pub(crate) async fn sponsored_bundle(
+    envelope_inputs: Vec<Envelope>,
+    signer_key: String,
+    payer_key: String,
+    header: BundleHeader,
+    config: &BundleConfig,
+    reuse_receipt: bool,
+) -> Result<PendingBundleTx, Error> {
+    config.check_envelope_count(envelope_inputs.len())?;
+    config.validate_envelopes(&envelope_inputs)?;
+    let provider = create_evm_client_with(&config.target.rpc_url, &config.retry).await?;
+    if reuse_receipt {
+        if let Some(receipt) = idempotent_receipt(&header, config)? {
+            let hash = B256::from_str(&receipt.bundle_txid)?;
+            return Ok(PendingBundleTx::new(provider, hash));
+        }
+    }
+    let idempotency_key = header.idempotency_key.clone();
+    let encoded = with_cancellation(
+        config.cancellation.as_ref(),
+        sign_and_encode(envelope_inputs, &signer_key, header, config),
+    )
+    .await?;
+    send_encoded(
+        encoded,
+        &provider,
+        idempotency_key,
+        payer_key,
+        config,
+        reuse_receipt,
+    )
+    .await
+}
 
+// with `deduplicate`, a bundle known to `config.dedup_indexes` is not broadcasted again
+async fn send_encoded(
+    encoded: EncodedBundle,
+    provider: &EvmProvider,
+    idempotency_key: Option<String>,
+    payer_key: String,
+    config: &BundleConfig,
+    deduplicate: bool,
+) -> Result<PendingBundleTx, Error> {
+    let EncodedBundle {
+        calldata,
+        envelope_ids,
+        bundle_id,
+        encoding,
+    } = encoded;
+    let indexes: &[_] = match deduplicate {
+        true => &config.dedup_indexes,
+        false => &[],
+    };
+    let duplicate = with_cancellation(
+        config.cancellation.as_ref(),
+        find_duplicate(indexes, bundle_id),
+    )
+    .await?;
+    if let Some(txid) = duplicate {
+        return Ok(PendingBundleTx::new(
+            provider.clone(),
+            B256::from_str(&txid)?,
+        ));
+    }
+    let tx: PendingBundleTx =
+        broadcast_bundle(&calldata, provider, Some(payer_key), config).await?;
+    let txid = tx.tx_hash().to_string();
+    // no await between the send and the receipt, dropping the future can't lose it
+    save_receipt(
+        txid.clone(),
+        envelope_ids.clone(),
+        bundle_id,
+        encoding,
+        idempotency_key,
+        config,
+    )?;
+    publish_sidecar(txid, envelope_ids, bundle_id, &calldata, config).await;
     Ok(tx)
 }
 
-pub fn generate_random_calldata(length: usize) -> String {
-    let mut rng = rand::thread_rng();
+// bundles envelopes signed elsewhere, e.g. decoded with `TxEnvelopeWrapper::from_signed_raw`:
+// only the bundle tx is signed here, by `payer_key`. Without a config policy the envelopes
+// are held to the strict envelope MUSTs, as they were not built by this crate.
+pub async fn create_bundle_from_signed(
+    envelopes: Vec<TxEnvelopeWrapper>,
+    payer_key: String,
+    header: BundleHeader,
+    config: &BundleConfig,
+) -> Result<PendingBundleTx, Error> {
+    config.check_envelope_count(envelopes.len())?;
+    for (i, envelope) in envelopes.iter().enumerate() {
+        envelope.recover_signer()?;
+        envelope
+            .to_envelope()?
+            .validate_with(config)
+            .map_err(|e| Error::InvalidEnvelope(i, e))?;
+    }
+    let policy = config.policy.clone().unwrap_or_else(BundlePolicy::strict);
+    policy.check_all(&envelopes)?;
+    let provider = create_evm_client_with(&config.target.rpc_url, &config.retry).await?;
+    if let Some(receipt) = idempotent_receipt(&header, config)? {
+        let hash = B256::from_str(&receipt.bundle_txid)?;
+        return Ok(PendingBundleTx::new(provider, hash));
+    }
+    let idempotency_key = header.idempotency_key.clone();
+    let encoded = encode_signed(envelopes, header, config)?;
+    send_encoded(encoded, &provider, idempotency_key, payer_key, config, true).await
+}
 
-    // Ensure minimum length of 10 (0x + 4 bytes function selector)
-    let min_length = 10;
-    let actual_length = length.max(min_length);
+// signs envelopes and the bundle tx without any RPC call, the nonce and fees being
+// given. The raw tx can be broadcasted from another machine with `broadcast_raw`.
+pub async fn build_bundle_tx(
+    envelope_inputs: Vec<Envelope>,
+    signer_key: String,
+    nonce: u64,
+    fees: GasFees,
+    header: BundleHeader,
+    config: &BundleConfig,
+) -> Result<SignedBundleTx, Error> {
+    config.check_envelope_count(envelope_inputs.len())?;
+    config.validate_envelopes(&envelope_inputs)?;
+    let signer: PrivateKeySigner = signer_key.parse()?;
+    let envelope_count = envelope_inputs.len();
+    let data_size = envelope_inputs
+        .iter()
+        .map(|e| e.data.as_ref().map_or(0, Vec::len))
+        .sum();
+    let EncodedBundle {
+        calldata,
+        bundle_id,
+        ..
+    } = with_cancellation(
+        config.cancellation.as_ref(),
+        sign_and_encode(envelope_inputs, &signer_key, header, config),
+    )
+    .await?;
 
-    // Start with 0x prefix
-    let mut calldata = String::with_capacity(actual_length);
-    calldata.push_str("0x");
+    let address = signer.address().to_string();
+    let wallet = EthereumWallet::from(signer);
+    let record = AuditRecord::new(
+        AuditAction::SignBundleTx,
+        address,
+        keccak256(&calldata).to_string(),
+    )
+    .envelope_count(envelope_count)
+    .tx(
+        config.target.chain_id,
+        nonce,
+        config.target_gas_limit(),
+        fees,
+    );
+    let tx = match sign_bundle_tx(&calldata, &wallet, nonce, fees, config).await {
+        Ok(tx) => tx,
+        Err(e) => {
+            audit(config, || record.failed(&e))?;
+            return Err(e);
+        }
+    };
+    audit(config, || record.txid(tx.tx_hash().to_string()))?;
+    Ok(SignedBundleTx {
+        raw_bytes: tx.encoded_2718(),
+        hash: tx.tx_hash().to_string(),
+        bundle_id,
+        payload_stats: PayloadStats {
+            envelope_count,
+            data_size,
+            calldata_size: calldata.len(),
+        },
+    })
+}
 
-    // Generate random hex characters for the remaining length
-    for _ in 2..actual_length {
-        let random_hex = rng.gen_range(0..16);
-        calldata.push_str(&format!("{:x}", random_hex));
+// checks a raw tx signed by another party before relaying it: a signed tx of the
+// `target` chain to its bundle address, whose calldata decodes as a bundle under `config`
+pub fn validate_raw_bundle_tx(
+    raw_tx: &[u8],
+    target: &ChainTarget,
+    config: &DecodeConfig,
+) -> Result<(TxEnvelope, BundleData), Error> {
+    let tx = TxEnvelope::decode_2718(&mut &raw_tx[..]).map_err(|_| Error::InvalidRawTransaction)?;
+    if tx.chain_id() != Some(target.chain_id) {
+        return Err(Error::InvalidRawTransaction);
+    }
+    tx.recover_signer().map_err(|_| Error::InvalidSignature)?;
+    if tx.to() != Some(target.bundle_address.parse::<Address>()?) {
+        return Err(Error::UnverifiedAddress);
     }
+    let bundle = decode_bundle_bytes_with(tx.input(), config)?;
+    Ok((tx, bundle))
+}
+
+pub async fn broadcast_raw(raw_tx: &[u8]) -> Result<PendingBundleTx, Error> {
+    broadcast_raw_with(raw_tx, &ChainTarget::weavevm(), &DecodeConfig::default()).await
+}
+
+// relays a pre-signed bundle tx, e.g. from `build_bundle_tx`, to the `target` node once
+// validated for that chain
+pub async fn broadcast_raw_with(
+    raw_tx: &[u8],
+    target: &ChainTarget,
+    config: &DecodeConfig,
+) -> Result<PendingBundleTx, Error> {
+    validate_raw_bundle_tx(raw_tx, target, config)?;
+    let provider = create_evm_client(&target.rpc_url).await?;
+    check_cancellation(config.cancellation.as_ref())?;
+    Ok(provider.send_raw_transaction(raw_tx).await?)
+}
+
+// signs and encodes the envelopes once, then posts the same calldata to every target
+// (e.g. a backup chain mirroring WeaveVM), each with its own nonce, fees and receipt.
+// `config.target` is replaced by each of `targets`.
+pub async fn submit_to_targets(
+    envelope_inputs: Vec<Envelope>,
+    signer_key: String,
+    payer_key: String,
+    header: BundleHeader,
+    targets: &[ChainTarget],
+    config: &BundleConfig,
+) -> Result<Vec<TargetSubmission>, Error> {
+    config.check_envelope_count(envelope_inputs.len())?;
+    config.validate_envelopes(&envelope_inputs)?;
+    let EncodedBundle {
+        calldata,
+        envelope_ids,
+        bundle_id,
+        encoding,
+    } = with_cancellation(
+        config.cancellation.as_ref(),
+        sign_and_encode(envelope_inputs, &signer_key, header, config),
+    )
+    .await?;
+
+    let futures = targets.iter().map(|target| {
+        let config = config.clone().target(target.clone());
+        let (calldata, envelope_ids, payer_key) = (&calldata, &envelope_ids, &payer_key);
+        async move {
+            let txid = async {
+                let provider =
+                    create_evm_client_with(&config.target.rpc_url, &config.retry).await?;
+                let tx =
+                    broadcast_bundle(calldata, &provider, Some(payer_key.clone()), &config).await?;
+                let txid = tx.tx_hash().to_string();
+                // receipts of the other targets would answer for the key, it is not kept
+                save_receipt(
+                    txid.clone(),
+                    envelope_ids.clone(),
+                    bundle_id,
+                    encoding,
+                    None,
+                    &config,
+                )?;
+                let ids = envelope_ids.clone();
+                publish_sidecar(txid.clone(), ids, bundle_id, calldata, &config).await;
+                Ok(txid)
+            };
+            TargetSubmission {
+                target: target.name.clone(),
+                txid: txid.await,
+            }
+        }
+    });
+    Ok(join_all(futures).await)
+}
+
+// creates the bundle and waits for `level` within `config.broadcast_timeout`. Signing is
+// given up on once it elapses, but a started broadcast always completes.
+pub async fn create_bundle_until(
+    envelope_inputs: Vec<Envelope>,
+    signer_key: String,
+    payer_key: String,
+    header: BundleHeader,
+    config: &BundleConfig,
+    level: ConfirmationLevel,
+    poll_interval: Duration,
+) -> Result<BroadcastOutcome, Error> {
+    config.check_envelope_count(envelope_inputs.len())?;
+    config.validate_envelopes(&envelope_inputs)?;
+    let deadline = config.broadcast_timeout.map(|t| Instant::now() + t);
+    let remaining = || deadline.map(|d| d.saturating_duration_since(Instant::now()));
 
-    calldata
+    let provider = create_evm_client_with(&config.target.rpc_url, &config.retry).await?;
+    let idempotency_key = header.idempotency_key.clone();
+    let txid = match idempotent_receipt(&header, config)? {
+        Some(receipt) => receipt.bundle_txid,
+        None => {
+            let prepared = with_cancellation(
+                config.cancellation.as_ref(),
+                sign_and_encode(envelope_inputs, &signer_key, header, config),
+            );
+            let EncodedBundle {
+                calldata,
+                envelope_ids,
+                bundle_id,
+                encoding,
+            } = match with_timeout(remaining(), prepared).await {
+                Some(prepared) => prepared?,
+                None => return Ok(BroadcastOutcome::NotSent),
+            };
+            if remaining() == Some(Duration::ZERO) {
+                return Ok(BroadcastOutcome::NotSent);
+            }
+
+            let duplicate = with_cancellation(
+                config.cancellation.as_ref(),
+                find_duplicate(&config.dedup_indexes, bundle_id),
+            )
+            .await?;
+            if let Some(txid) = duplicate {
+                txid
+            } else {
+                let tx = broadcast_bundle(&calldata, &provider, Some(payer_key), config).await?;
+                let txid = tx.tx_hash().to_string();
+                save_receipt(
+                    txid.clone(),
+                    envelope_ids.clone(),
+                    bundle_id,
+                    encoding,
+                    idempotency_key,
+                    config,
+                )?;
+                publish_sidecar(txid.clone(), envelope_ids, bundle_id, &calldata, config).await;
+                txid
+            }
+        }
+    };
+    wait_for_inclusion_timeout_from(
+        &config.target.rpc_url,
+        &txid,
+        level,
+        poll_interval,
+        remaining(),
+    )
+    .await
+}
+
+// best effort, the bundle is posted whatever the sinks answer. A failed publication can
+// be retried with `publish_sidecars`.
+async fn publish_sidecar(
+    bundle_txid: String,
+    envelope_ids: Vec<String>,
+    bundle_id: B256,
+    calldata: &[u8],
+    config: &BundleConfig,
+) {
+    if config.sidecar_sinks.is_empty() {
+        return;
+    }
+    let chain_id = config.target.chain_id;
+    // the calldata was just encoded, its items always decode
+    if let Ok(sidecar) =
+        BundleSidecar::new(bundle_txid, chain_id, bundle_id, envelope_ids, calldata)
+    {
+        publish_sidecars(&config.sidecar_sinks, &sidecar).await;
+    }
+}
+
+fn save_receipt(
+    bundle_txid: String,
+    envelope_ids: Vec<String>,
+    bundle_id: B256,
+    encoding: Option<OptimizedEncoding>,
+    idempotency_key: Option<String>,
+    config: &BundleConfig,
+) -> Result<(), Error> {
+    match &config.receipt_store {
+        Some(store) => store.save(&BundleReceipt {
+            bundle_txid,
+            envelope_ids,
+            idempotency_key,
+            bundle_id: Some(bundle_id.to_string()),
+            encoding,
+        }),
+        None => Ok(()),
+    }
+}
+
+// receipt of an earlier submission of the header idempotency key
+fn idempotent_receipt(
+    header: &BundleHeader,
+    config: &BundleConfig,
+) -> Result<Option<BundleReceipt>, Error> {
+    match (&header.idempotency_key, &config.receipt_store) {
+        (Some(key), Some(store)) => store.find_idempotent(key),
+        _ => Ok(None),
+    }
+}
+
+// signs and broadcasts several bundles concurrently, bundle `i` being sent with the
+// signer's pending nonce + `i` so they settle in input order. A failed bundle leaves
+// a nonce gap that holds back the following ones until it is filled. Under a memory
+// budget the bundles are built one at a time.
+pub async fn submit_bundles(
+    bundles: Vec<Vec<Envelope>>,
+    private_key: String,
+    config: &BundleConfig,
+) -> Result<Vec<Result<String, Error>>, Error> {
+    let payer_key = private_key.clone();
+    submit_sponsored_bundles(bundles, private_key, payer_key, config).await
+}
+
+// like `submit_bundles`, the nonces being the payer's
+pub async fn submit_sponsored_bundles(
+    bundles: Vec<Vec<Envelope>>,
+    signer_key: String,
+    payer_key: String,
+    config: &BundleConfig,
+) -> Result<Vec<Result<String, Error>>, Error> {
+    for envelopes in &bundles {
+        config.check_envelope_count(envelopes.len())?;
+        config.validate_envelopes(envelopes)?;
+    }
+    let provider = create_evm_client_with(&config.target.rpc_url, &config.retry).await?;
+    let payer: PrivateKeySigner = payer_key.parse()?;
+    let (base_nonce, fees) = with_cancellation(config.cancellation.as_ref(), async {
+        let nonce = provider
+            .get_transaction_count(payer.address())
+            .pending()
+            .await?;
+        Ok((nonce, bundle_fees(&provider, config).await?))
+    })
+    .await?;
+
+    let concurrency = match config.memory_budget {
+        Some(_) => 1,
+        None => bundles.len().max(1),
+    };
+    let futures = bundles.into_iter().enumerate().map(|(i, envelope_inputs)| {
+        let (provider, payer, signer_key) = (&provider, &payer, &signer_key);
+        async move {
+            let EncodedBundle {
+                calldata,
+                envelope_ids,
+                bundle_id,
+                encoding,
+            } = with_cancellation(
+                config.cancellation.as_ref(),
+                sign_and_encode(envelope_inputs, signer_key, BundleHeader::default(), config),
+            )
+            .await?;
+            let nonce = base_nonce + i as u64;
+            let tx = send_bundle_tx(&calldata, provider, payer, nonce, fees, false, config).await?;
+            let txid = tx.tx_hash().to_string();
+            save_receipt(
+                txid.clone(),
+                envelope_ids.clone(),
+                bundle_id,
+                encoding,
+                None,
+                config,
+            )?;
+            publish_sidecar(txid.clone(), envelope_ids, bundle_id, &calldata, config).await;
+            Ok(txid)
+        }
+    });
+    Ok(stream::iter(futures).buffered(concurrency).collect().await)
+}
+
+// throwaway key for size estimation, estimated envelopes are never broadcasted
+const ESTIMATION_PRIVATE_KEY: &str =
+    "0000000000000000000000000000000000000000000000000000000000000001";
+
+// predicts the calldata size of a bundle of `envelopes` without touching any user key
+pub async fn estimate_bundle_size(
+    envelopes: &[Envelope],
+    header: &BundleHeader,
+    config: &BundleConfig,
+) -> Result<usize, Error> {
+    config.validate_envelopes(envelopes)?;
+    let bundle = BundleData {
+        envelopes: sign_envelopes(envelopes.to_vec(), ESTIMATION_PRIVATE_KEY).await?,
+        header: header.clone(),
+    };
+    bundle.encoded_size_with(config)
+}
+
+// calldata gas of a bundle of `envelopes`, encoded as it would be submitted (with the
+// config gas optimizer, if any)
+pub async fn estimate_bundle_cost(
+    envelopes: &[Envelope],
+    header: &BundleHeader,
+    config: &BundleConfig,
+) -> Result<CalldataCost, Error> {
+    config.validate_envelopes(envelopes)?;
+    let bundle = BundleData {
+        envelopes: sign_envelopes(envelopes.to_vec(), ESTIMATION_PRIVATE_KEY).await?,
+        header: header.clone(),
+    };
+    Ok(match &config.gas_optimizer {
+        Some(optimizer) => optimizer.optimize(&bundle, config)?.1.cost,
+        None => CalldataCost::of(&bundle.encode_calldata_with(config)?),
+    })
+}
+
+// per-envelope sizes of a bundle of `envelopes`, signed with the estimation key (so the
+// hashes are not the ones of the submitted envelopes, match them by index)
+pub async fn estimate_envelope_stats(
+    envelopes: &[Envelope],
+    header: &BundleHeader,
+    config: &BundleConfig,
+) -> Result<Vec<EnvelopeStats>, Error> {
+    config.validate_envelopes(envelopes)?;
+    let bundle = BundleData {
+        envelopes: sign_envelopes(envelopes.to_vec(), ESTIMATION_PRIVATE_KEY).await?,
+        header: header.clone(),
+    };
+    Ok(bundle.encode_calldata_with_stats(config)?.1)
+}
+
+// encoded item size of each envelope in its bundle, signed with the estimation key
+pub async fn estimate_item_sizes(
+    envelopes: &[Envelope],
+    config: &BundleConfig,
+) -> Result<Vec<usize>, Error> {
+    let signed = sign_envelopes(envelopes.to_vec(), ESTIMATION_PRIVATE_KEY).await?;
+    let mut buffers = EncodeBuffers::new();
+    signed
+        .iter()
+        .map(|envelope| {
+            let item =
+                BundleItem::write_envelope(envelope, config, &mut buffers, &mut std::io::sink())?;
+            Ok(item.item_size)
+        })
+        .collect()
+}
+
+// splits envelopes into bundles with `config.packing`, bundles share `header`
+pub async fn pack_envelopes(
+    envelopes: Vec<Envelope>,
+    header: &BundleHeader,
+    config: &BundleConfig,
+) -> Result<Vec<Vec<Envelope>>, Error> {
+    let max_count = config.max_envelopes_per_bundle.max(1);
+    if config.packing == PackingStrategy::Sequential {
+        return Ok(envelopes.chunks(max_count).map(|c| c.to_vec()).collect());
+    }
+    let sizes = estimate_item_sizes(&envelopes, config).await?;
+    let mut header = header.clone();
+    header.dictionary_id = config.dictionary.as_ref().map(|d| d.id.clone());
+    // the bundle prefix and the item count
+    let overhead = header.calldata_prefix(0).len() + 4;
+    let capacity = config.max_bundle_size.saturating_sub(overhead);
+    let mut envelopes: Vec<Option<Envelope>> = envelopes.into_iter().map(Some).collect();
+    Ok(first_fit_decreasing(&sizes, capacity, max_count)
+        .into_iter()
+        .map(|bin| {
+            bin.into_iter()
+                .filter_map(|i| envelopes[i].take())
+                .collect()
+        })
+        .collect())
 }
 
 pub async fn retrieve_bundle_tx(txid: String) -> Result<BundleTxMetadata, Error> {
-    let provider = create_evm_http_client(WVM_RPC_URL).await?;
+    retrieve_bundle_tx_from(WVM_RPC_URL, txid).await
+}
+
+pub async fn retrieve_bundle_tx_from(
+    rpc_url: &str,
+    txid: String,
+) -> Result<BundleTxMetadata, Error> {
+    let provider = create_evm_client(rpc_url).await?;
     let txid = B256::from_str(&txid)?;
+    // an unknown tx is not found, RPC failures keep their own error
     let tx = provider
         .get_transaction_by_hash(txid)
         .await?
-        .ok_or_eyre("error retrieving tx");
-    let tx_json = serde_json::json!(&tx?);
+        .ok_or(Error::BundleRetrievalProblem)?;
+    let tx_json = serde_json::json!(&tx);
 
     let block_hash: &str = tx_json["blockHash"].as_str().unwrap_or("0x");
     let block_number_hex: &str = tx_json["blockNumber"].as_str().unwrap_or("0x");
@@ -192,16 +1169,56 @@ pub async fn retrieve_bundle_tx(txid: String) -> Result<BundleTxMetadata, Error>
     Ok(res)
 }
 
-pub async fn retrieve_bundle_data(calldata: String) -> BundleData {
-    let byte_array = hex::decode(calldata.trim_start_matches("0x")).expect("decoding failed");
-    let unbrotli = TxEnvelopeWrapper::brotli_decompress(byte_array);
-    let unborsh: BundleData = TxEnvelopeWrapper::borsh_der(unbrotli);
-    // validate envelopes MUSTs
-    for envelope in &unborsh.envelopes {
-        assert_eq!(envelope.nonce, 0);
-        assert_eq!(envelope.gas_limit, 0);
-        assert_eq!(envelope.gas_price, 0);
+// hex (with or without 0x) or base64 calldata
+pub async fn retrieve_bundle_data(calldata: String) -> Result<BundleData, Error> {
+    retrieve_bundle_data_with(calldata, &DecodeConfig::default()).await
+}
+
+pub async fn retrieve_bundle_data_with(
+    calldata: String,
+    config: &DecodeConfig,
+) -> Result<BundleData, Error> {
+    decode_bundle_calldata_with(calldata.as_bytes(), config)
+}
+
+// fetches and decodes a bundle tx in one call
+pub async fn retrieve_bundle(txid: String) -> Result<(BundleTxMetadata, BundleData), Error> {
+    retrieve_bundle_with(txid, &DecodeConfig::default()).await
+}
+
+// the returned metadata has an empty calldata unless `config.keep_calldata` is set
+pub async fn retrieve_bundle_with(
+    txid: String,
+    config: &DecodeConfig,
+) -> Result<(BundleTxMetadata, BundleData), Error> {
+    let metadata =
+        with_cancellation(config.cancellation.as_ref(), retrieve_bundle_tx(txid)).await?;
+    decode_bundle_tx(metadata, config)
+}
+
+pub fn decode_bundle_tx(
+    mut metadata: BundleTxMetadata,
+    config: &DecodeConfig,
+) -> Result<(BundleTxMetadata, BundleData), Error> {
+    // assert the bundle versioning by checking target address
+    if !metadata.to.eq_ignore_ascii_case(ADDRESS_BABE1) {
+        return Err(Error::UnverifiedAddress);
     }
 
-    unborsh
+    let calldata = std::mem::take(&mut metadata.calldata);
+    let bundle = decode_bundle_calldata_with(calldata.as_bytes(), config)?;
+    if config.keep_calldata {
+        metadata.calldata = calldata;
+    }
+    Ok((metadata, bundle))
+}
+
+// header and item index of a bundle tx, the envelopes are not decompressed
+pub async fn inspect_bundle(txid: String) -> Result<BundleInspection, Error> {
+    let metadata = retrieve_bundle_tx(txid).await?;
+    if !metadata.to.eq_ignore_ascii_case(ADDRESS_BABE1) {
+        return Err(Error::UnverifiedAddress);
+    }
+    let calldata = CalldataEncoding::Hex.decode(metadata.calldata.as_bytes())?;
+    BundleInspection::from_calldata(&calldata)
 }
@@ -0,0 +1,216 @@
+use crate::utils::errors::Error;
+use crate::utils::evm::EvmProvider;
+use {
+    alloy::{eips::BlockNumberOrTag, providers::Provider},
+    async_trait::async_trait,
+    serde::{Deserialize, Serialize},
+    std::fmt::Debug,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasFees {
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+// fee source of the EIP-1559 bundle transaction
+#[async_trait]
+pub trait GasOracle: Debug + Send + Sync {
+    async fn fees(&self, provider: &EvmProvider) -> Result<GasFees, Error>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StaticGasOracle {
+    pub fees: GasFees,
+}
+
+impl Default for StaticGasOracle {
+    fn default() -> Self {
+        StaticGasOracle {
+            fees: GasFees {
+                max_fee_per_gas: 2_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl GasOracle for StaticGasOracle {
+    async fn fees(&self, _provider: &EvmProvider) -> Result<GasFees, Error> {
+        Ok(self.fees)
+    }
+}
+
+// alloy's default EIP-1559 estimation over the provider fee history
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProviderGasOracle;
+
+#[async_trait]
+impl GasOracle for ProviderGasOracle {
+    async fn fees(&self, provider: &EvmProvider) -> Result<GasFees, Error> {
+        let estimation = provider.estimate_eip1559_fees(None).await?;
+        Ok(GasFees {
+            max_fee_per_gas: estimation.max_fee_per_gas,
+            max_priority_fee_per_gas: estimation.max_priority_fee_per_gas,
+        })
+    }
+}
+
+// priority fee at `percentile` of the rewards paid over the last `block_count` blocks
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PercentileGasOracle {
+    pub percentile: f64,
+    pub block_count: u64,
+}
+
+impl Default for PercentileGasOracle {
+    fn default() -> Self {
+        PercentileGasOracle {
+            percentile: 50.0,
+            block_count: 10,
+        }
+    }
+}
+
+#[async_trait]
+impl GasOracle for PercentileGasOracle {
+    async fn fees(&self, provider: &EvmProvider) -> Result<GasFees, Error> {
+        let history = provider
+            .get_fee_history(
+                self.block_count,
+                BlockNumberOrTag::Latest,
+                &[self.percentile],
+            )
+            .await?;
+        let base_fee = history.next_block_base_fee().ok_or(Error::MissingBaseFee)?;
+
+        let mut rewards: Vec<u128> = history
+            .reward
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|block| block.first().copied())
+            .collect();
+        rewards.sort_unstable();
+        let priority_fee = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+
+        Ok(GasFees {
+            max_fee_per_gas: base_fee * 2 + priority_fee,
+            max_priority_fee_per_gas: priority_fee,
+        })
+    }
+}
+
+// default oracle: the priority fee at `percentile` of the recent rewards, or the node
+// `eth_maxPriorityFeePerGas` suggestion when it is higher, scaled by the multipliers
+// so the bundle tx keeps up with a rising base fee under congestion
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeeHistoryGasOracle {
+    pub percentile: f64,
+    pub block_count: u64,
+    pub base_fee_multiplier: f64,
+    pub priority_fee_multiplier: f64,
+}
+
+impl Default for FeeHistoryGasOracle {
+    fn default() -> Self {
+        FeeHistoryGasOracle {
+            percentile: 50.0,
+            block_count: 10,
+            base_fee_multiplier: 2.0,
+            priority_fee_multiplier: 1.0,
+        }
+    }
+}
+
+impl FeeHistoryGasOracle {
+    pub fn percentile(mut self, percentile: f64) -> Self {
+        self.percentile = percentile;
+        self
+    }
+
+    pub fn block_count(mut self, block_count: u64) -> Self {
+        self.block_count = block_count;
+        self
+    }
+
+    pub fn base_fee_multiplier(mut self, multiplier: f64) -> Self {
+        self.base_fee_multiplier = multiplier;
+        self
+    }
+
+    pub fn priority_fee_multiplier(mut self, multiplier: f64) -> Self {
+        self.priority_fee_multiplier = multiplier;
+        self
+    }
+
+    // 1559 fields from the next block base fee, the per block percentile rewards and
+    // the node suggested priority fee
+    pub fn compute(
+        &self,
+        base_fee: u128,
+        rewards: &[u128],
+        suggested_priority_fee: u128,
+    ) -> GasFees {
+        let mut rewards = rewards.to_vec();
+        rewards.sort_unstable();
+        let reward = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+        let priority_fee = scale(
+            reward.max(suggested_priority_fee),
+            self.priority_fee_multiplier,
+        );
+
+        GasFees {
+            max_fee_per_gas: scale(base_fee, self.base_fee_multiplier) + priority_fee,
+            max_priority_fee_per_gas: priority_fee,
+        }
+    }
+}
+
+#[async_trait]
+impl GasOracle for FeeHistoryGasOracle {
+    async fn fees(&self, provider: &EvmProvider) -> Result<GasFees, Error> {
+        let percentiles = [self.percentile];
+        let (history, suggested_priority_fee) = futures::try_join!(
+            provider.get_fee_history(self.block_count, BlockNumberOrTag::Latest, &percentiles),
+            provider.get_max_priority_fee_per_gas(),
+        )?;
+        let base_fee = history.next_block_base_fee().ok_or(Error::MissingBaseFee)?;
+
+        let rewards: Vec<u128> = history
+            .reward
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|block| block.first().copied())
+            .collect();
+        Ok(self.compute(base_fee, &rewards, suggested_priority_fee))
+    }
+}
+
+fn scale(fee: u128, multiplier: f64) -> u128 {
+    (fee as f64 * multiplier.max(0.0)).ceil() as u128
+}
+
+// fetches `GasFees` as JSON from an external endpoint
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UrlGasOracle {
+    pub url: String,
+}
+
+impl UrlGasOracle {
+    pub fn new(url: String) -> Self {
+        UrlGasOracle { url }
+    }
+}
+
+#[async_trait]
+impl GasOracle for UrlGasOracle {
+    async fn fees(&self, _provider: &EvmProvider) -> Result<GasFees, Error> {
+        let fees = reqwest::get(&self.url)
+            .await
+            .and_then(|res| res.error_for_status())?
+            .json::<GasFees>()
+            .await?;
+        Ok(fees)
+    }
+}
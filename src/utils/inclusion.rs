@@ -0,0 +1,311 @@
+use crate::utils::constants::WVM_RPC_URL;
+use crate::utils::errors::Error;
+use crate::utils::evm::create_http_client;
+use {
+    alloy::{
+        eips::BlockNumberOrTag, primitives::B256, providers::Provider,
+        rpc::types::BlockTransactionsKind,
+    },
+    futures::future::{self, Either},
+    futures::stream::{self, Stream},
+    serde::{Deserialize, Serialize},
+    std::collections::HashMap,
+    std::future::Future,
+    std::pin::pin,
+    std::str::FromStr,
+    std::time::Duration,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inclusion {
+    pub txid: String,
+    pub block_number: u64,
+    pub block_hash: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfirmationLevel {
+    #[default]
+    Latest,
+    Safe,
+    Finalized,
+}
+
+impl ConfirmationLevel {
+    pub fn block_tag(&self) -> BlockNumberOrTag {
+        match self {
+            ConfirmationLevel::Latest => BlockNumberOrTag::Latest,
+            ConfirmationLevel::Safe => BlockNumberOrTag::Safe,
+            ConfirmationLevel::Finalized => BlockNumberOrTag::Finalized,
+        }
+    }
+}
+
+// where a bundle stands once its broadcast timeout elapsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BroadcastOutcome {
+    // the bundle tx never left the bundler, it is safe to submit it again
+    NotSent,
+    // the bundle tx was sent but has not reached its confirmation level yet
+    Unconfirmed(String),
+    Confirmed(Inclusion),
+}
+
+// resolves to `None`, dropping `future`, once `timeout` elapsed. Never times out when
+// `timeout` is `None`.
+pub async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    future: impl Future<Output = T>,
+) -> Option<T> {
+    let Some(timeout) = timeout else {
+        return Some(future.await);
+    };
+    match future::select(pin!(future), futures_timer::Delay::new(timeout)).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+// `current` is `None` when the bundle tx is no longer part of the canonical chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReorgEvent {
+    pub previous: Inclusion,
+    pub current: Option<Inclusion>,
+}
+
+pub async fn inclusion(txid: &str) -> Result<Option<Inclusion>, Error> {
+    inclusion_from(WVM_RPC_URL, txid).await
+}
+
+pub async fn inclusion_from(rpc_url: &str, txid: &str) -> Result<Option<Inclusion>, Error> {
+    let provider = create_http_client(rpc_url)?;
+    let Some(receipt) = provider
+        .get_transaction_receipt(B256::from_str(txid)?)
+        .await?
+    else {
+        return Ok(None);
+    };
+    match (receipt.block_number, receipt.block_hash) {
+        (Some(block_number), Some(block_hash)) => Ok(Some(Inclusion {
+            txid: txid.to_string(),
+            block_number,
+            block_hash: block_hash.to_string(),
+        })),
+        _ => Ok(None),
+    }
+}
+
+// whether the inclusion block is at or below the `level` block and still canonical
+pub async fn is_confirmed(inclusion: &Inclusion, level: ConfirmationLevel) -> Result<bool, Error> {
+    is_confirmed_from(WVM_RPC_URL, inclusion, level).await
+}
+
+pub async fn is_confirmed_from(
+    rpc_url: &str,
+    inclusion: &Inclusion,
+    level: ConfirmationLevel,
+) -> Result<bool, Error> {
+    let provider = create_http_client(rpc_url)?;
+    let Some(tagged) = provider
+        .get_block_by_number(level.block_tag(), BlockTransactionsKind::Hashes)
+        .await?
+    else {
+        return Ok(false);
+    };
+    if tagged.header.number < inclusion.block_number {
+        return Ok(false);
+    }
+    let canonical = provider
+        .get_block_by_number(
+            BlockNumberOrTag::Number(inclusion.block_number),
+            BlockTransactionsKind::Hashes,
+        )
+        .await?;
+    Ok(canonical.is_some_and(|block| block.header.hash.to_string() == inclusion.block_hash))
+}
+
+// polls until the bundle tx is included in a block that reached `level`
+pub async fn wait_for_inclusion(
+    txid: &str,
+    level: ConfirmationLevel,
+    poll_interval: Duration,
+) -> Result<Inclusion, Error> {
+    wait_for_inclusion_from(WVM_RPC_URL, txid, level, poll_interval).await
+}
+
+pub async fn wait_for_inclusion_from(
+    rpc_url: &str,
+    txid: &str,
+    level: ConfirmationLevel,
+    poll_interval: Duration,
+) -> Result<Inclusion, Error> {
+    loop {
+        if let Some(inclusion) = inclusion_from(rpc_url, txid).await? {
+            if level == ConfirmationLevel::Latest
+                || is_confirmed_from(rpc_url, &inclusion, level).await?
+            {
+                return Ok(inclusion);
+            }
+        }
+        futures_timer::Delay::new(poll_interval).await;
+    }
+}
+
+pub async fn wait_for_inclusion_timeout(
+    txid: &str,
+    level: ConfirmationLevel,
+    poll_interval: Duration,
+    timeout: Option<Duration>,
+) -> Result<BroadcastOutcome, Error> {
+    wait_for_inclusion_timeout_from(WVM_RPC_URL, txid, level, poll_interval, timeout).await
+}
+
+pub async fn wait_for_inclusion_timeout_from(
+    rpc_url: &str,
+    txid: &str,
+    level: ConfirmationLevel,
+    poll_interval: Duration,
+    timeout: Option<Duration>,
+) -> Result<BroadcastOutcome, Error> {
+    let waiting = wait_for_inclusion_from(rpc_url, txid, level, poll_interval);
+    match with_timeout(timeout, waiting).await {
+        Some(inclusion) => Ok(BroadcastOutcome::Confirmed(inclusion?)),
+        None => Ok(BroadcastOutcome::Unconfirmed(txid.to_string())),
+    }
+}
+
+// like `wait_for_inclusion`, but returns `None` once the tx has been reorged out and is
+// no longer known to the node, i.e. it will not be re-mined
+pub async fn wait_for_confirmation(
+    txid: &str,
+    level: ConfirmationLevel,
+    poll_interval: Duration,
+) -> Result<Option<Inclusion>, Error> {
+    wait_for_confirmation_from(WVM_RPC_URL, txid, level, poll_interval).await
+}
+
+pub async fn wait_for_confirmation_from(
+    rpc_url: &str,
+    txid: &str,
+    level: ConfirmationLevel,
+    poll_interval: Duration,
+) -> Result<Option<Inclusion>, Error> {
+    let provider = create_http_client(rpc_url)?;
+    let mut included = false;
+
+    loop {
+        match inclusion_from(rpc_url, txid).await? {
+            Some(inclusion) => {
+                included = true;
+                if level == ConfirmationLevel::Latest
+                    || is_confirmed_from(rpc_url, &inclusion, level).await?
+                {
+                    return Ok(Some(inclusion));
+                }
+            }
+            None if included => {
+                let pending = provider
+                    .get_transaction_by_hash(B256::from_str(txid)?)
+                    .await?;
+                if pending.is_none() {
+                    return Ok(None);
+                }
+            }
+            None => {}
+        }
+        futures_timer::Delay::new(poll_interval).await;
+    }
+}
+
+// re-queries the blocks of included bundles to detect when they are reorged out
+#[derive(Debug)]
+pub struct ReorgTracker {
+    tracked: HashMap<String, Inclusion>,
+    rpc_url: String,
+}
+
+impl Default for ReorgTracker {
+    fn default() -> Self {
+        ReorgTracker {
+            tracked: HashMap::new(),
+            rpc_url: WVM_RPC_URL.to_string(),
+        }
+    }
+}
+
+impl ReorgTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // node re-queried for the blocks and receipts of the tracked bundles
+    pub fn rpc_url(mut self, rpc_url: &str) -> Self {
+        self.rpc_url = rpc_url.to_string();
+        self
+    }
+
+    pub fn track(&mut self, inclusion: Inclusion) {
+        self.tracked.insert(inclusion.txid.clone(), inclusion);
+    }
+
+    pub fn untrack(&mut self, txid: &str) -> Option<Inclusion> {
+        self.tracked.remove(txid)
+    }
+
+    pub fn tracked(&self) -> impl Iterator<Item = &Inclusion> {
+        self.tracked.values()
+    }
+
+    // re-included bundles keep being tracked at their new block, dropped ones are untracked
+    pub async fn check(&mut self) -> Result<Vec<ReorgEvent>, Error> {
+        let provider = create_http_client(&self.rpc_url)?;
+        let mut events = vec![];
+
+        let mut tracked: Vec<Inclusion> = self.tracked.values().cloned().collect();
+        tracked.sort_by_key(|i| i.block_number);
+        for previous in tracked {
+            let canonical_hash = provider
+                .get_block_by_number(
+                    BlockNumberOrTag::Number(previous.block_number),
+                    BlockTransactionsKind::Hashes,
+                )
+                .await?
+                .map(|block| block.header.hash.to_string());
+            if canonical_hash.as_deref() == Some(previous.block_hash.as_str()) {
+                continue;
+            }
+
+            let current = inclusion_from(&self.rpc_url, &previous.txid).await?;
+            match &current {
+                Some(current) => self.track(current.clone()),
+                None => {
+                    self.untrack(&previous.txid);
+                }
+            }
+            events.push(ReorgEvent { previous, current });
+        }
+
+        Ok(events)
+    }
+
+    pub fn watch(self, poll_interval: Duration) -> impl Stream<Item = Result<ReorgEvent, Error>> {
+        let state = (self, Vec::<ReorgEvent>::new());
+        stream::unfold(state, move |(mut tracker, mut pending)| async move {
+            loop {
+                if !pending.is_empty() {
+                    let event = pending.remove(0);
+                    return Some((Ok(event), (tracker, pending)));
+                }
+                if tracker.tracked.is_empty() {
+                    return None;
+                }
+
+                futures_timer::Delay::new(poll_interval).await;
+                match tracker.check().await {
+                    Ok(events) => pending = events,
+                    Err(e) => return Some((Err(e), (tracker, pending))),
+                }
+            }
+        })
+    }
+}
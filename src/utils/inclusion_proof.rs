@@ -0,0 +1,128 @@
+use crate::utils::constants::WVM_RPC_URL;
+use crate::utils::errors::Error;
+use crate::utils::evm::create_http_client;
+use {
+    alloy::{
+        consensus::{proofs::calculate_transaction_root, Header, TxEnvelope},
+        eips::eip2718::{Decodable2718, Encodable2718},
+        primitives::{keccak256, B256},
+        providers::Provider,
+        rlp::{Decodable, Encodable},
+        rpc::types::BlockTransactionsKind,
+    },
+    serde::{Deserialize, Serialize},
+    std::str::FromStr,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub txid: String,
+    pub block_number: u64,
+    pub block_hash: String,
+    pub transactions_root: String,
+    // position of the bundle tx in the block
+    pub index: usize,
+    // 0x hex EIP-2718 encoding of the bundle tx, its keccak256 is `txid`
+    pub raw_tx: String,
+    // 0x hex RLP of the block header, its keccak256 is `block_hash`
+    pub header: String,
+    // 0x hex EIP-2718 encodings of every tx of the block, in order, the trie they build
+    // having `transactions_root` as root
+    pub transactions: Vec<String>,
+}
+
+impl InclusionProof {
+    // checks the proof offline, from its own header and txs, against a block hash known
+    // from an independent source
+    pub fn verify(&self, trusted_block_hash: B256) -> Result<(), Error> {
+        let header = alloy::hex::decode(&self.header).map_err(|_| Error::UnverifiedInclusion)?;
+        let header =
+            Header::decode(&mut header.as_slice()).map_err(|_| Error::UnverifiedInclusion)?;
+        let transactions = self
+            .transactions
+            .iter()
+            .map(|tx| {
+                let raw = alloy::hex::decode(tx).map_err(|_| Error::UnverifiedInclusion)?;
+                TxEnvelope::decode_2718(&mut raw.as_slice()).map_err(|_| Error::UnverifiedInclusion)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let txid = B256::from_str(&self.txid)?;
+        let proof = verify_block_inclusion(&header, trusted_block_hash, &transactions, txid)?;
+        if proof != *self {
+            return Err(Error::UnverifiedInclusion);
+        }
+        Ok(())
+    }
+}
+
+// recomputes the header hash, the tx hash and the transactions root locally, so the
+// proof only trusts `block_hash`, to be checked against an independent source
+pub fn verify_block_inclusion(
+    header: &Header,
+    block_hash: B256,
+    transactions: &[TxEnvelope],
+    txid: B256,
+) -> Result<InclusionProof, Error> {
+    if header.hash_slow() != block_hash {
+        return Err(Error::UnverifiedInclusion);
+    }
+    let index = transactions
+        .iter()
+        .position(|tx| keccak256(tx.encoded_2718()) == txid)
+        .ok_or(Error::UnverifiedInclusion)?;
+    if calculate_transaction_root(transactions) != header.transactions_root {
+        return Err(Error::UnverifiedInclusion);
+    }
+
+    let mut rlp_header = Vec::new();
+    header.encode(&mut rlp_header);
+    Ok(InclusionProof {
+        txid: txid.to_string(),
+        block_number: header.number,
+        block_hash: block_hash.to_string(),
+        transactions_root: header.transactions_root.to_string(),
+        index,
+        raw_tx: alloy::hex::encode_prefixed(transactions[index].encoded_2718()),
+        header: alloy::hex::encode_prefixed(rlp_header),
+        transactions: transactions
+            .iter()
+            .map(|tx| alloy::hex::encode_prefixed(tx.encoded_2718()))
+            .collect(),
+    })
+}
+
+// fetches the inclusion block with its transactions and verifies the bundle tx against
+// the block header, instead of trusting the RPC receipt
+pub async fn prove_inclusion(txid: &str) -> Result<InclusionProof, Error> {
+    prove_inclusion_from(WVM_RPC_URL, txid, None).await
+}
+
+// the block of the node at `rpc_url` must have `trusted_block_hash` when one is given,
+// otherwise the proof only trusts its `block_hash`
+pub async fn prove_inclusion_from(
+    rpc_url: &str,
+    txid: &str,
+    trusted_block_hash: Option<B256>,
+) -> Result<InclusionProof, Error> {
+    let provider = create_http_client(rpc_url)?;
+    let hash = B256::from_str(txid)?;
+
+    let block_hash = provider
+        .get_transaction_by_hash(hash)
+        .await?
+        .and_then(|tx| tx.block_hash)
+        .ok_or(Error::UnverifiedInclusion)?;
+    if trusted_block_hash.is_some_and(|trusted| trusted != block_hash) {
+        return Err(Error::UnverifiedInclusion);
+    }
+    let block = provider
+        .get_block_by_hash(block_hash, BlockTransactionsKind::Full)
+        .await?
+        .ok_or(Error::UnverifiedInclusion)?;
+    let transactions: Vec<TxEnvelope> = block
+        .transactions
+        .txns()
+        .map(|tx| tx.inner.clone())
+        .collect();
+    verify_block_inclusion(&block.header.inner, block_hash, &transactions, hash)
+}
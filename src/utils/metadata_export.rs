@@ -0,0 +1,300 @@
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::retention::{unix_now, ExpiryPolicy};
+use crate::utils::core::tag::{find_tag, CONTENT_TYPE_TAG};
+use crate::utils::errors::Error;
+use crate::utils::scanner::ScannedBundle;
+use futures::{Stream, StreamExt};
+use std::io::Write;
+
+// one row per envelope, for analytics pipelines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvelopeRow {
+    pub bundle_txid: String,
+    pub index: usize,
+    pub id: String,
+    pub signer: Option<String>,
+    pub target: String,
+    pub size: usize,
+    pub content_type: Option<String>,
+    pub expires_at: Option<u64>,
+    pub retention_class: Option<String>,
+    // expired at export time
+    pub expired: bool,
+}
+
+impl EnvelopeRow {
+    pub fn from_bundle(bundle_txid: &str, bundle: &BundleData) -> Vec<EnvelopeRow> {
+        Self::from_bundle_with(bundle_txid, bundle, ExpiryPolicy::Flag)
+    }
+
+    // skipped rows keep the bundle index of the following ones unchanged
+    pub fn from_bundle_with(
+        bundle_txid: &str,
+        bundle: &BundleData,
+        policy: ExpiryPolicy,
+    ) -> Vec<EnvelopeRow> {
+        let now = unix_now();
+        bundle
+            .envelopes
+            .iter()
+            .enumerate()
+            .filter(|(_, envelope)| policy == ExpiryPolicy::Flag || !envelope.is_expired(now))
+            .map(|(index, envelope)| EnvelopeRow {
+                bundle_txid: bundle_txid.to_string(),
+                index,
+                id: envelope.hash.clone(),
+                signer: envelope.recover_signer().ok().map(|a| a.to_string()),
+                target: envelope.to.clone(),
+                size: envelope.input.trim_start_matches("0x").len() / 2,
+                content_type: find_tag(&envelope.tags, CONTENT_TYPE_TAG).map(str::to_string),
+                expires_at: envelope.expires_at(),
+                retention_class: envelope.retention_class().map(str::to_string),
+                expired: envelope.is_expired(now),
+            })
+            .collect()
+    }
+}
+
+pub trait BundleExporter {
+    fn write_bundle(&mut self, bundle_txid: &str, bundle: &BundleData) -> Result<(), Error>;
+    fn finish(self) -> Result<(), Error>;
+}
+
+// drains a scanner stream (e.g. `watch_bundles`) into `exporter`, bundle by bundle
+pub async fn export_bundles<S, E>(bundles: S, mut exporter: E) -> Result<(), Error>
+where
+    S: Stream<Item = Result<ScannedBundle, Error>>,
+    E: BundleExporter,
+{
+    let mut bundles = std::pin::pin!(bundles);
+    while let Some(scanned) = bundles.next().await {
+        let scanned = scanned?;
+        exporter.write_bundle(&scanned.txid, &scanned.bundle)?;
+    }
+    exporter.finish()
+}
+
+const CSV_HEADER: &str =
+    "bundle_txid,index,id,signer,target,size,content_type,expires_at,retention_class,expired";
+
+pub struct CsvExporter<W: Write> {
+    writer: W,
+    header_written: bool,
+    expiry_policy: ExpiryPolicy,
+}
+
+impl<W: Write> CsvExporter<W> {
+    pub fn new(writer: W) -> Self {
+        CsvExporter {
+            writer,
+            header_written: false,
+            expiry_policy: ExpiryPolicy::default(),
+        }
+    }
+
+    pub fn expiry_policy(mut self, policy: ExpiryPolicy) -> Self {
+        self.expiry_policy = policy;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_row(&mut self, row: &EnvelopeRow) -> Result<(), Error> {
+        if !self.header_written {
+            writeln!(self.writer, "{}", CSV_HEADER)?;
+            self.header_written = true;
+        }
+        writeln!(
+            self.writer,
+            "{},{},{},{},{},{},{},{},{},{}",
+            row.bundle_txid,
+            row.index,
+            row.id,
+            row.signer.as_deref().unwrap_or_default(),
+            row.target,
+            row.size,
+            csv_field(row.content_type.as_deref().unwrap_or_default()),
+            row.expires_at.map(|t| t.to_string()).unwrap_or_default(),
+            csv_field(row.retention_class.as_deref().unwrap_or_default()),
+            row.expired,
+        )?;
+        Ok(())
+    }
+}
+
+// only the content type and retention class are free text, the other fields are hex,
+// numbers or booleans
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl<W: Write> BundleExporter for CsvExporter<W> {
+    fn write_bundle(&mut self, bundle_txid: &str, bundle: &BundleData) -> Result<(), Error> {
+        for row in EnvelopeRow::from_bundle_with(bundle_txid, bundle, self.expiry_policy) {
+            self.write_row(&row)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "parquet")]
+pub use parquet_export::ParquetExporter;
+
+#[cfg(feature = "parquet")]
+mod parquet_export {
+    use super::{BundleExporter, EnvelopeRow};
+    use crate::utils::core::bundle_data::BundleData;
+    use crate::utils::core::retention::ExpiryPolicy;
+    use crate::utils::errors::Error;
+    use parquet::data_type::{BoolType, ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use std::io::Write;
+    use std::sync::Arc;
+
+    const SCHEMA: &str = "message envelope {
+        REQUIRED BYTE_ARRAY bundle_txid (UTF8);
+        REQUIRED INT64 index;
+        REQUIRED BYTE_ARRAY id (UTF8);
+        OPTIONAL BYTE_ARRAY signer (UTF8);
+        REQUIRED BYTE_ARRAY target (UTF8);
+        REQUIRED INT64 size;
+        OPTIONAL BYTE_ARRAY content_type (UTF8);
+        OPTIONAL INT64 expires_at;
+        OPTIONAL BYTE_ARRAY retention_class (UTF8);
+        REQUIRED BOOLEAN expired;
+    }";
+
+    // every bundle is written as its own row group
+    pub struct ParquetExporter<W: Write + Send> {
+        writer: SerializedFileWriter<W>,
+        expiry_policy: ExpiryPolicy,
+    }
+
+    impl<W: Write + Send> ParquetExporter<W> {
+        pub fn new(writer: W) -> Result<Self, Error> {
+            let schema = Arc::new(parse_message_type(SCHEMA)?);
+            let properties = Arc::new(WriterProperties::builder().build());
+            Ok(ParquetExporter {
+                writer: SerializedFileWriter::new(writer, schema, properties)?,
+                expiry_policy: ExpiryPolicy::default(),
+            })
+        }
+
+        pub fn expiry_policy(mut self, policy: ExpiryPolicy) -> Self {
+            self.expiry_policy = policy;
+            self
+        }
+    }
+
+    fn strings(values: impl Iterator<Item = String>) -> Vec<ByteArray> {
+        values.map(|v| ByteArray::from(v.into_bytes())).collect()
+    }
+
+    fn optional_strings(
+        values: impl Iterator<Item = Option<String>>,
+    ) -> (Vec<ByteArray>, Vec<i16>) {
+        let mut present = vec![];
+        let mut levels = vec![];
+        for value in values {
+            levels.push(value.is_some() as i16);
+            present.extend(value.map(|v| ByteArray::from(v.into_bytes())));
+        }
+        (present, levels)
+    }
+
+    impl<W: Write + Send> BundleExporter for ParquetExporter<W> {
+        fn write_bundle(&mut self, bundle_txid: &str, bundle: &BundleData) -> Result<(), Error> {
+            let rows = EnvelopeRow::from_bundle_with(bundle_txid, bundle, self.expiry_policy);
+            if rows.is_empty() {
+                return Ok(());
+            }
+            let mut row_group = self.writer.next_row_group()?;
+            let mut column = 0;
+            while let Some(mut writer) = row_group.next_column()? {
+                let rows = rows.iter().cloned();
+                match column {
+                    0 => writer.typed::<ByteArrayType>().write_batch(
+                        &strings(rows.map(|r| r.bundle_txid)),
+                        None,
+                        None,
+                    )?,
+                    1 => writer.typed::<Int64Type>().write_batch(
+                        &rows.map(|r| r.index as i64).collect::<Vec<_>>(),
+                        None,
+                        None,
+                    )?,
+                    2 => writer.typed::<ByteArrayType>().write_batch(
+                        &strings(rows.map(|r| r.id)),
+                        None,
+                        None,
+                    )?,
+                    3 => {
+                        let (values, levels) = optional_strings(rows.map(|r| r.signer));
+                        writer
+                            .typed::<ByteArrayType>()
+                            .write_batch(&values, Some(&levels), None)?
+                    }
+                    4 => writer.typed::<ByteArrayType>().write_batch(
+                        &strings(rows.map(|r| r.target)),
+                        None,
+                        None,
+                    )?,
+                    5 => writer.typed::<Int64Type>().write_batch(
+                        &rows.map(|r| r.size as i64).collect::<Vec<_>>(),
+                        None,
+                        None,
+                    )?,
+                    6 => {
+                        let (values, levels) = optional_strings(rows.map(|r| r.content_type));
+                        writer
+                            .typed::<ByteArrayType>()
+                            .write_batch(&values, Some(&levels), None)?
+                    }
+                    7 => {
+                        let expires_at: Vec<Option<u64>> = rows.map(|r| r.expires_at).collect();
+                        let levels: Vec<i16> =
+                            expires_at.iter().map(|e| e.is_some() as i16).collect();
+                        let values: Vec<i64> =
+                            expires_at.into_iter().flatten().map(|e| e as i64).collect();
+                        writer
+                            .typed::<Int64Type>()
+                            .write_batch(&values, Some(&levels), None)?
+                    }
+                    8 => {
+                        let (values, levels) = optional_strings(rows.map(|r| r.retention_class));
+                        writer
+                            .typed::<ByteArrayType>()
+                            .write_batch(&values, Some(&levels), None)?
+                    }
+                    _ => writer.typed::<BoolType>().write_batch(
+                        &rows.map(|r| r.expired).collect::<Vec<_>>(),
+                        None,
+                        None,
+                    )?,
+                };
+                writer.close()?;
+                column += 1;
+            }
+            row_group.close()?;
+            Ok(())
+        }
+
+        fn finish(self) -> Result<(), Error> {
+            self.writer.close()?;
+            Ok(())
+        }
+    }
+}
@@ -1,5 +1,42 @@
+pub mod adaptive_batching;
+pub mod async_decoder;
+pub mod audit_log;
+pub mod batcher;
+pub mod bundle_sink;
+pub mod cancellation;
+pub mod checkpoint;
 pub mod constants;
 pub mod core;
+pub mod decoder;
+pub mod dedup;
+pub mod dispatcher;
 pub mod env_var;
 pub mod errors;
 pub mod evm;
+pub mod gas_oracle;
+pub mod inclusion;
+pub mod inclusion_proof;
+pub mod metadata_export;
+pub mod partial_retrieval;
+pub mod path_index;
+pub mod quorum;
+pub mod rate_limit;
+pub mod receipt_store;
+pub mod registry;
+pub mod repack;
+pub mod replay;
+pub mod retry;
+pub mod roundtrip;
+pub mod scan_filter;
+pub mod scanner;
+pub mod search_index;
+pub mod sidecar;
+#[cfg(feature = "mnemonic")]
+pub mod signer;
+pub mod snapshot;
+pub mod spend_guard;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+pub mod submission_queue;
+pub mod testing;
+pub mod webhook;
@@ -0,0 +1,195 @@
+use crate::utils::cancellation::{check_cancellation, with_cancellation};
+use crate::utils::constants::ADDRESS_BABE1;
+use crate::utils::core::bundle_header::BundleHeader;
+use crate::utils::core::bundle_item::BundleItem;
+use crate::utils::core::decode_config::DecodeConfig;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::decoder::{decode_bundle_bytes_with, CalldataEncoding};
+use crate::utils::errors::Error;
+use crate::utils::evm::retrieve_bundle_tx;
+use base64::Engine;
+use futures::future::{select, Either};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+// where a partial retrieval stopped, (de)serialized as its opaque `encode()` string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContinuationToken {
+    pub txid: String,
+    // index of the first envelope not returned yet
+    pub next_envelope: usize,
+}
+
+impl ContinuationToken {
+    pub fn new(txid: &str, next_envelope: usize) -> Self {
+        ContinuationToken {
+            txid: txid.to_string(),
+            next_envelope,
+        }
+    }
+
+    // url-safe base64 of the borsh encoded txid and index
+    pub fn encode(&self) -> String {
+        let bytes = borsh::to_vec(&(&self.txid, self.next_envelope as u64)).unwrap();
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    pub fn decode(token: &str) -> Result<Self, Error> {
+        let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(token)
+            .map_err(|_| Error::InvalidContinuationToken)?;
+        let (txid, next_envelope): (String, u64) =
+            borsh::from_slice(&bytes).map_err(|_| Error::InvalidContinuationToken)?;
+        Ok(ContinuationToken::new(&txid, next_envelope as usize))
+    }
+}
+
+impl Serialize for ContinuationToken {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.encode())
+    }
+}
+
+impl<'de> Deserialize<'de> for ContinuationToken {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let token = String::deserialize(deserializer)?;
+        ContinuationToken::decode(&token).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartialBundle {
+    // `None` until the bundle tx was fetched, and for legacy bundles
+    pub header: Option<BundleHeader>,
+    // envelopes `first_envelope..first_envelope + envelopes.len()` of the bundle
+    pub first_envelope: usize,
+    pub envelopes: Vec<TxEnvelopeWrapper>,
+    // `None` until the bundle tx was fetched
+    pub envelope_count: Option<usize>,
+    // `None` once the last envelope was returned
+    pub continuation: Option<ContinuationToken>,
+}
+
+impl PartialBundle {
+    pub fn is_complete(&self) -> bool {
+        self.continuation.is_none()
+    }
+}
+
+// fetches a bundle tx and decodes its envelopes until `timeout` elapses, returning the
+// envelopes decoded so far and a token to resume from. A slow RPC gives an empty page
+// instead of an error.
+pub async fn retrieve_bundle_within(
+    txid: String,
+    timeout: Duration,
+    config: &DecodeConfig,
+) -> Result<PartialBundle, Error> {
+    retrieve_from(txid, 0, Instant::now() + timeout, config).await
+}
+
+pub async fn resume_bundle_within(
+    token: &ContinuationToken,
+    timeout: Duration,
+    config: &DecodeConfig,
+) -> Result<PartialBundle, Error> {
+    let deadline = Instant::now() + timeout;
+    retrieve_from(token.txid.clone(), token.next_envelope, deadline, config).await
+}
+
+async fn retrieve_from(
+    txid: String,
+    first_envelope: usize,
+    deadline: Instant,
+    config: &DecodeConfig,
+) -> Result<PartialBundle, Error> {
+    let timer = futures_timer::Delay::new(deadline.saturating_duration_since(Instant::now()));
+    let fetch = with_cancellation(
+        config.cancellation.as_ref(),
+        retrieve_bundle_tx(txid.clone()),
+    );
+    let metadata = match select(Box::pin(fetch), timer).await {
+        Either::Left((Ok(metadata), _)) => metadata,
+        Either::Left((Err(e), _)) => return Err(e),
+        Either::Right(_) => {
+            return Ok(PartialBundle {
+                header: None,
+                first_envelope,
+                envelopes: vec![],
+                envelope_count: None,
+                continuation: Some(ContinuationToken::new(&txid, first_envelope)),
+            })
+        }
+    };
+    if !metadata.to.eq_ignore_ascii_case(ADDRESS_BABE1) {
+        return Err(Error::UnverifiedAddress);
+    }
+    let calldata = CalldataEncoding::Hex.decode(metadata.calldata.as_bytes())?;
+    decode_partial(&txid, &calldata, first_envelope, deadline, config)
+}
+
+// envelopes from `first_envelope` until `deadline`, at least one so that resuming always
+// progresses. Items are indexed without decompression, only the returned envelopes are
+// decompressed and checked against the config policy. Legacy bundles are a single blob
+// and are decoded at once.
+pub fn decode_partial(
+    txid: &str,
+    calldata: &[u8],
+    first_envelope: usize,
+    deadline: Instant,
+    config: &DecodeConfig,
+) -> Result<PartialBundle, Error> {
+    let (version, header, body) = BundleHeader::split_versioned_calldata(calldata)?;
+    let Some(header) = header else {
+        let bundle = decode_bundle_bytes_with(calldata, config)?;
+        let envelope_count = bundle.envelopes.len();
+        let envelopes: Vec<_> = bundle.envelopes.into_iter().skip(first_envelope).collect();
+        return Ok(PartialBundle {
+            header: None,
+            first_envelope,
+            envelopes,
+            envelope_count: Some(envelope_count),
+            continuation: None,
+        });
+    };
+
+    let dictionary = match &header.dictionary_id {
+        Some(id) => Some(
+            config
+                .find_dictionary(id)
+                .ok_or_else(|| Error::UnknownDictionary(id.clone()))?,
+        ),
+        None => None,
+    };
+    let items = BundleItem::decode_items(version, body)?;
+    let envelope_count = items.len();
+    let policy = config.envelope_policy();
+    let mut remaining = config.max_decompressed_size;
+    let mut envelopes = vec![];
+    let mut next = first_envelope;
+    for item in items.into_iter().skip(first_envelope) {
+        if !envelopes.is_empty() && Instant::now() >= deadline {
+            break;
+        }
+        check_cancellation(config.cancellation.as_ref())?;
+        let (envelope, size) = item
+            .into_envelope(dictionary, remaining)
+            .map_err(|e| match e {
+                Error::DecompressedSizeExceeded(_) => {
+                    Error::DecompressedSizeExceeded(config.max_decompressed_size)
+                }
+                _ => Error::CorruptedEnvelope(next),
+            })?;
+        policy.check(next, &envelope)?;
+        remaining -= size;
+        envelopes.push(envelope);
+        next += 1;
+    }
+    let continuation = (next < envelope_count).then(|| ContinuationToken::new(txid, next));
+    Ok(PartialBundle {
+        header: Some(header),
+        first_envelope,
+        envelopes,
+        envelope_count: Some(envelope_count),
+        continuation,
+    })
+}
@@ -0,0 +1,187 @@
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::tag::path_has_prefix;
+#[cfg(feature = "sqlite")]
+use crate::utils::core::tag::sql_path_has_prefix;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use crate::utils::scanner::ScannedBundle;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+#[cfg(feature = "sqlite")]
+use std::sync::Mutex;
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathEntry {
+    pub path: String,
+    pub bundle_txid: String,
+    pub envelope_id: String,
+    // position in the bundle
+    pub index: usize,
+    // `None` for bundles indexed before their inclusion
+    pub block_number: Option<u64>,
+}
+
+// envelopes by their `Path` tag. An envelope is indexed once, inserting it again (e.g. from
+// a resumed backfill) is a no-op.
+pub trait PathIndex: Debug + Send + Sync {
+    fn insert(&self, entries: &[PathEntry]) -> Result<(), Error>;
+    // in insertion order
+    fn find_by_path(&self, path: &str) -> Result<Vec<PathEntry>, Error>;
+    // in path order, then insertion order, see `path_has_prefix`
+    fn find_by_prefix(&self, prefix: &str) -> Result<Vec<PathEntry>, Error>;
+}
+
+// entries of the envelopes with a path, in bundle order
+pub fn path_entries(
+    bundle_txid: &str,
+    block_number: Option<u64>,
+    bundle: &BundleData,
+) -> Vec<PathEntry> {
+    let entry = |(index, envelope): (usize, &TxEnvelopeWrapper)| {
+        Some(PathEntry {
+            path: envelope.path()?.to_string(),
+            bundle_txid: bundle_txid.to_lowercase(),
+            envelope_id: envelope.hash.to_lowercase(),
+            index,
+            block_number,
+        })
+    };
+    bundle
+        .envelopes
+        .iter()
+        .enumerate()
+        .filter_map(entry)
+        .collect()
+}
+
+// returns the number of envelopes with a path
+pub fn index_bundle(index: &dyn PathIndex, scanned: &ScannedBundle) -> Result<usize, Error> {
+    let entries = path_entries(&scanned.txid, Some(scanned.block_number), &scanned.bundle);
+    index.insert(&entries)?;
+    Ok(entries.len())
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryPathIndex {
+    paths: RwLock<BTreeMap<String, Vec<PathEntry>>>,
+}
+
+impl MemoryPathIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PathIndex for MemoryPathIndex {
+    fn insert(&self, entries: &[PathEntry]) -> Result<(), Error> {
+        let mut paths = self.paths.write().map_err(|_| Error::PathIndexError)?;
+        for entry in entries {
+            let indexed = paths.entry(entry.path.clone()).or_default();
+            let known =
+                |e: &PathEntry| e.bundle_txid == entry.bundle_txid && e.index == entry.index;
+            if !indexed.iter().any(known) {
+                indexed.push(entry.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn find_by_path(&self, path: &str) -> Result<Vec<PathEntry>, Error> {
+        let paths = self.paths.read().map_err(|_| Error::PathIndexError)?;
+        Ok(paths.get(path).cloned().unwrap_or_default())
+    }
+
+    fn find_by_prefix(&self, prefix: &str) -> Result<Vec<PathEntry>, Error> {
+        let paths = self.paths.read().map_err(|_| Error::PathIndexError)?;
+        Ok(paths
+            .range(prefix.to_string()..)
+            .take_while(|(path, _)| path_has_prefix(path, prefix))
+            .flat_map(|(_, entries)| entries.iter().cloned())
+            .collect())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqlitePathIndex {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqlitePathIndex {
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
+        Self::with_connection(rusqlite::Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<Self, Error> {
+        Self::with_connection(rusqlite::Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: rusqlite::Connection) -> Result<Self, Error> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS paths (
+                path TEXT NOT NULL,
+                bundle_txid TEXT NOT NULL,
+                idx INTEGER NOT NULL,
+                envelope_id TEXT NOT NULL,
+                block_number INTEGER,
+                UNIQUE (bundle_txid, idx)
+            );
+            CREATE INDEX IF NOT EXISTS paths_path ON paths (path);",
+        )?;
+        Ok(SqlitePathIndex {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn select(&self, condition: &str, value: &str) -> Result<Vec<PathEntry>, Error> {
+        let conn = self.conn.lock().map_err(|_| Error::PathIndexError)?;
+        let mut statement = conn.prepare(&format!(
+            "SELECT path, bundle_txid, envelope_id, idx, block_number FROM paths
+             WHERE {condition} ORDER BY path, rowid"
+        ))?;
+        let rows = statement.query_map([value], |row| {
+            Ok(PathEntry {
+                path: row.get(0)?,
+                bundle_txid: row.get(1)?,
+                envelope_id: row.get(2)?,
+                index: row.get::<_, i64>(3)? as usize,
+                block_number: row.get::<_, Option<i64>>(4)?.map(|block| block as u64),
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl PathIndex for SqlitePathIndex {
+    fn insert(&self, entries: &[PathEntry]) -> Result<(), Error> {
+        let mut conn = self.conn.lock().map_err(|_| Error::PathIndexError)?;
+        let tx = conn.transaction()?;
+        for entry in entries {
+            tx.execute(
+                "INSERT OR IGNORE INTO paths (path, bundle_txid, idx, envelope_id, block_number)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![
+                    entry.path,
+                    entry.bundle_txid,
+                    entry.index as i64,
+                    entry.envelope_id,
+                    entry.block_number.map(|block| block as i64)
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn find_by_path(&self, path: &str) -> Result<Vec<PathEntry>, Error> {
+        self.select("path = ?1", path)
+    }
+
+    fn find_by_prefix(&self, prefix: &str) -> Result<Vec<PathEntry>, Error> {
+        self.select(&sql_path_has_prefix("path", "?1"), prefix)
+    }
+}
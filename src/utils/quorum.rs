@@ -0,0 +1,90 @@
+use crate::utils::cancellation::with_cancellation;
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::bundle_tx_metadata::BundleTxMetadata;
+use crate::utils::core::decode_config::DecodeConfig;
+use crate::utils::decoder::CalldataEncoding;
+use crate::utils::errors::Error;
+use crate::utils::evm::{decode_bundle_tx, retrieve_bundle_tx_from};
+use alloy::primitives::keccak256;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuorumConfig {
+    pub endpoints: Vec<String>,
+    // matching responses needed out of `endpoints`
+    pub threshold: usize,
+}
+
+impl QuorumConfig {
+    pub fn new(endpoints: Vec<String>, threshold: usize) -> Self {
+        QuorumConfig {
+            endpoints,
+            threshold,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.threshold == 0 || self.threshold > self.endpoints.len() {
+            return Err(Error::InvalidQuorum(self.threshold, self.endpoints.len()));
+        }
+        Ok(())
+    }
+}
+
+// what one endpoint answered, `payload_hash` is the keccak256 of the bundle calldata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointReply {
+    pub endpoint: String,
+    pub payload_hash: Option<String>,
+    pub error: Option<String>,
+}
+
+// fetches the bundle tx from every endpoint and decodes the calldata at least
+// `threshold` of them agree on, failing with `Error::QuorumMismatch` otherwise
+pub async fn retrieve_bundle_quorum(
+    txid: String,
+    quorum: &QuorumConfig,
+    config: &DecodeConfig,
+) -> Result<(BundleTxMetadata, BundleData), Error> {
+    quorum.validate()?;
+    let requests = quorum
+        .endpoints
+        .iter()
+        .map(|endpoint| retrieve_bundle_tx_from(endpoint, txid.clone()));
+    let responses = with_cancellation(config.cancellation.as_ref(), async {
+        Ok(join_all(requests).await)
+    })
+    .await?;
+
+    let mut replies = Vec::with_capacity(responses.len());
+    let mut agreeing: Vec<(String, usize, BundleTxMetadata)> = vec![];
+    for (endpoint, response) in quorum.endpoints.iter().zip(responses) {
+        let hashed = response.and_then(|metadata| {
+            let calldata = CalldataEncoding::Hex.decode(metadata.calldata.as_bytes())?;
+            Ok((keccak256(&calldata).to_string(), metadata))
+        });
+        let (payload_hash, error) = match hashed {
+            Ok((hash, metadata)) => {
+                match agreeing.iter_mut().find(|(h, _, _)| *h == hash) {
+                    Some((_, count, _)) => *count += 1,
+                    None => agreeing.push((hash.clone(), 1, metadata)),
+                }
+                (Some(hash), None)
+            }
+            Err(e) => (None, Some(e.to_string())),
+        };
+        replies.push(EndpointReply {
+            endpoint: endpoint.clone(),
+            payload_hash,
+            error,
+        });
+    }
+
+    match agreeing.into_iter().max_by_key(|(_, count, _)| *count) {
+        Some((_, count, metadata)) if count >= quorum.threshold => {
+            decode_bundle_tx(metadata, config)
+        }
+        _ => Err(Error::QuorumMismatch(replies)),
+    }
+}
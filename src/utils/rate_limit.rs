@@ -0,0 +1,193 @@
+use crate::utils::errors::Error;
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::{TransportError, TransportFut};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+// process-wide limiter shared by every provider the library creates, see `set_rpc_rate_limit`
+static RPC_RATE_LIMITER: RwLock<Option<Arc<TokenBucket>>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    // requests allowed at once after an idle period
+    pub burst: u32,
+}
+
+impl RateLimit {
+    // the tokens are refilled at this rate, it must be positive and finite
+    pub fn per_second(requests_per_second: f64) -> Result<Self, Error> {
+        if !(requests_per_second.is_finite() && requests_per_second > 0.0) {
+            return Err(Error::InvalidRateLimit(requests_per_second));
+        }
+        Ok(RateLimit {
+            requests_per_second,
+            burst: (requests_per_second.ceil() as u32).max(1),
+        })
+    }
+
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+}
+
+// limits every RPC call made through the library from now on (providers created before
+// keep the previous limit), `None` removes the limit
+pub fn set_rpc_rate_limit(limit: Option<RateLimit>) {
+    let bucket = limit.map(|limit| Arc::new(TokenBucket::new(limit)));
+    *RPC_RATE_LIMITER.write().unwrap_or_else(|e| e.into_inner()) = bucket;
+}
+
+pub fn rpc_rate_limiter() -> Option<Arc<TokenBucket>> {
+    RPC_RATE_LIMITER
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[derive(Debug)]
+pub struct TokenBucket {
+    limit: RateLimit,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    // negative once requests are waiting for their tokens
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    // starts full
+    pub fn new(limit: RateLimit) -> Self {
+        TokenBucket {
+            limit,
+            state: Mutex::new(BucketState {
+                tokens: limit.burst as f64,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    // takes `tokens` right away and returns how long to wait before they are available, so
+    // concurrent callers are served in order
+    pub fn reserve(&self, tokens: u32) -> Duration {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        self.refill(&mut state);
+        state.tokens -= tokens as f64;
+        if state.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(-state.tokens / self.limit.requests_per_second)
+    }
+
+    // takes `tokens` when they are available right away, none otherwise
+    pub fn try_acquire(&self, tokens: u32) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        self.refill(&mut state);
+        if state.tokens < tokens as f64 {
+            return false;
+        }
+        state.tokens -= tokens as f64;
+        true
+    }
+
+    fn refill(&self, state: &mut BucketState) {
+        let now = Instant::now();
+        let refill =
+            now.duration_since(state.updated).as_secs_f64() * self.limit.requests_per_second;
+        state.tokens = (state.tokens + refill).min(self.limit.burst as f64);
+        state.updated = now;
+    }
+
+    pub async fn acquire(&self, tokens: u32) {
+        let wait = self.reserve(tokens);
+        if !wait.is_zero() {
+            futures_timer::Delay::new(wait).await;
+        }
+    }
+}
+
+// waits for a token per request (per request of a batch) before each call, retries
+// included when the layer sits under `RetryLayer`
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitLayer {
+    bucket: Option<Arc<TokenBucket>>,
+}
+
+impl RateLimitLayer {
+    pub fn new(limit: RateLimit) -> Self {
+        RateLimitLayer {
+            bucket: Some(Arc::new(TokenBucket::new(limit))),
+        }
+    }
+
+    // the limiter set with `set_rpc_rate_limit`, if any
+    pub fn global() -> Self {
+        RateLimitLayer {
+            bucket: rpc_rate_limiter(),
+        }
+    }
+
+    pub fn shared(bucket: Arc<TokenBucket>) -> Self {
+        RateLimitLayer {
+            bucket: Some(bucket),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            bucket: self.bucket.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    bucket: Option<Arc<TokenBucket>>,
+}
+
+impl<S> Service<RequestPacket> for RateLimitService<S>
+where
+    S: Service<RequestPacket, Future = TransportFut<'static>, Error = TransportError>
+        + Clone
+        + Send
+        + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let Some(bucket) = self.bucket.clone() else {
+            return self.inner.call(request);
+        };
+        let mut inner = self.inner.clone();
+        Box::pin(async move {
+            let tokens = match &request {
+                RequestPacket::Single(_) => 1,
+                RequestPacket::Batch(requests) => requests.len().max(1) as u32,
+            };
+            bucket.acquire(tokens).await;
+            inner.call(request).await
+        })
+    }
+}
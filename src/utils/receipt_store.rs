@@ -0,0 +1,396 @@
+use crate::utils::core::bundle_receipt::{BundleLocation, BundleReceipt};
+use crate::utils::errors::Error;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+
+// persists the receipts of propagated bundles to answer "which tx holds my envelope"
+pub trait ReceiptStore: Debug + Send + Sync {
+    fn save(&self, receipt: &BundleReceipt) -> Result<(), Error>;
+    fn receipt(&self, bundle_txid: &str) -> Result<Option<BundleReceipt>, Error>;
+    fn lookup_envelope(&self, envelope_id: &str) -> Result<Option<BundleLocation>, Error>;
+    // latest receipt saved with the idempotency key
+    fn find_idempotent(&self, idempotency_key: &str) -> Result<Option<BundleReceipt>, Error>;
+    // latest receipt saved with the bundle id
+    fn find_bundle(&self, bundle_id: &str) -> Result<Option<BundleReceipt>, Error>;
+    // every receipt, to copy the store elsewhere (see `StateSnapshot`). Saving them again
+    // in order rebuilds the same lookups.
+    fn export(&self) -> Result<Vec<BundleReceipt>, Error>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryReceiptStore {
+    receipts: RwLock<HashMap<String, BundleReceipt>>,
+    envelopes: RwLock<HashMap<String, BundleLocation>>,
+    // idempotency key -> bundle txid
+    idempotency_keys: RwLock<HashMap<String, String>>,
+    // bundle id -> bundle txid
+    bundle_ids: RwLock<HashMap<String, String>>,
+}
+
+impl MemoryReceiptStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ReceiptStore for MemoryReceiptStore {
+    fn save(&self, receipt: &BundleReceipt) -> Result<(), Error> {
+        let mut envelopes = self
+            .envelopes
+            .write()
+            .map_err(|_| Error::ReceiptStoreError)?;
+        for (index, id) in receipt.envelope_ids.iter().enumerate() {
+            envelopes.insert(
+                id.to_lowercase(),
+                BundleLocation {
+                    bundle_txid: receipt.bundle_txid.clone(),
+                    index,
+                },
+            );
+        }
+        if let Some(key) = &receipt.idempotency_key {
+            self.idempotency_keys
+                .write()
+                .map_err(|_| Error::ReceiptStoreError)?
+                .insert(key.clone(), receipt.bundle_txid.to_lowercase());
+        }
+        if let Some(id) = &receipt.bundle_id {
+            self.bundle_ids
+                .write()
+                .map_err(|_| Error::ReceiptStoreError)?
+                .insert(id.to_lowercase(), receipt.bundle_txid.to_lowercase());
+        }
+        self.receipts
+            .write()
+            .map_err(|_| Error::ReceiptStoreError)?
+            .insert(receipt.bundle_txid.to_lowercase(), receipt.clone());
+        Ok(())
+    }
+
+    fn receipt(&self, bundle_txid: &str) -> Result<Option<BundleReceipt>, Error> {
+        let receipts = self.receipts.read().map_err(|_| Error::ReceiptStoreError)?;
+        Ok(receipts.get(&bundle_txid.to_lowercase()).cloned())
+    }
+
+    fn lookup_envelope(&self, envelope_id: &str) -> Result<Option<BundleLocation>, Error> {
+        let envelopes = self
+            .envelopes
+            .read()
+            .map_err(|_| Error::ReceiptStoreError)?;
+        Ok(envelopes.get(&envelope_id.to_lowercase()).cloned())
+    }
+
+    fn find_idempotent(&self, idempotency_key: &str) -> Result<Option<BundleReceipt>, Error> {
+        let keys = self
+            .idempotency_keys
+            .read()
+            .map_err(|_| Error::ReceiptStoreError)?;
+        match keys.get(idempotency_key) {
+            Some(txid) => self.receipt(txid),
+            None => Ok(None),
+        }
+    }
+
+    fn find_bundle(&self, bundle_id: &str) -> Result<Option<BundleReceipt>, Error> {
+        let txid = self
+            .bundle_ids
+            .read()
+            .map_err(|_| Error::ReceiptStoreError)?
+            .get(&bundle_id.to_lowercase())
+            .cloned();
+        match txid {
+            Some(txid) => self.receipt(&txid),
+            None => Ok(None),
+        }
+    }
+
+    // in txid order
+    fn export(&self) -> Result<Vec<BundleReceipt>, Error> {
+        let receipts = self.receipts.read().map_err(|_| Error::ReceiptStoreError)?;
+        let mut exported: Vec<BundleReceipt> = receipts.values().cloned().collect();
+        exported.sort_by(|a, b| a.bundle_txid.cmp(&b.bundle_txid));
+        Ok(exported)
+    }
+}
+
+// append-only JSON lines file, one receipt per line
+#[derive(Debug)]
+pub struct FileReceiptStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileReceiptStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileReceiptStore {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn receipts(&self) -> Result<Vec<BundleReceipt>, Error> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut receipts = vec![];
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                receipts.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(receipts)
+    }
+}
+
+impl ReceiptStore for FileReceiptStore {
+    fn save(&self, receipt: &BundleReceipt) -> Result<(), Error> {
+        let _guard = self.lock.lock().map_err(|_| Error::ReceiptStoreError)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(receipt)?)?;
+        Ok(())
+    }
+
+    fn receipt(&self, bundle_txid: &str) -> Result<Option<BundleReceipt>, Error> {
+        let _guard = self.lock.lock().map_err(|_| Error::ReceiptStoreError)?;
+        Ok(self
+            .receipts()?
+            .into_iter()
+            .rev()
+            .find(|r| r.bundle_txid.eq_ignore_ascii_case(bundle_txid)))
+    }
+
+    fn lookup_envelope(&self, envelope_id: &str) -> Result<Option<BundleLocation>, Error> {
+        let _guard = self.lock.lock().map_err(|_| Error::ReceiptStoreError)?;
+        Ok(self
+            .receipts()?
+            .iter()
+            .rev()
+            .find_map(|r| r.locate(envelope_id)))
+    }
+
+    fn find_idempotent(&self, idempotency_key: &str) -> Result<Option<BundleReceipt>, Error> {
+        let _guard = self.lock.lock().map_err(|_| Error::ReceiptStoreError)?;
+        Ok(self
+            .receipts()?
+            .into_iter()
+            .rev()
+            .find(|r| r.idempotency_key.as_deref() == Some(idempotency_key)))
+    }
+
+    fn find_bundle(&self, bundle_id: &str) -> Result<Option<BundleReceipt>, Error> {
+        let _guard = self.lock.lock().map_err(|_| Error::ReceiptStoreError)?;
+        Ok(self.receipts()?.into_iter().rev().find(|r| {
+            r.bundle_id
+                .as_deref()
+                .is_some_and(|id| id.eq_ignore_ascii_case(bundle_id))
+        }))
+    }
+
+    // in save order, a receipt saved twice is exported twice
+    fn export(&self) -> Result<Vec<BundleReceipt>, Error> {
+        let _guard = self.lock.lock().map_err(|_| Error::ReceiptStoreError)?;
+        self.receipts()
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteReceiptStore {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteReceiptStore {
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
+        Self::with_connection(rusqlite::Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<Self, Error> {
+        Self::with_connection(rusqlite::Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: rusqlite::Connection) -> Result<Self, Error> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS envelopes (
+                envelope_id TEXT PRIMARY KEY,
+                bundle_txid TEXT NOT NULL,
+                idx INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS envelopes_bundle ON envelopes (bundle_txid, idx);
+            CREATE TABLE IF NOT EXISTS idempotency_keys (
+                idempotency_key TEXT PRIMARY KEY,
+                bundle_txid TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS bundle_ids (
+                bundle_id TEXT PRIMARY KEY,
+                bundle_txid TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS bundle_ids_txid ON bundle_ids (bundle_txid);
+            CREATE TABLE IF NOT EXISTS bundle_encodings (
+                bundle_txid TEXT PRIMARY KEY,
+                encoding TEXT NOT NULL
+            );",
+        )?;
+        Ok(SqliteReceiptStore {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl ReceiptStore for SqliteReceiptStore {
+    fn save(&self, receipt: &BundleReceipt) -> Result<(), Error> {
+        let mut conn = self.conn.lock().map_err(|_| Error::ReceiptStoreError)?;
+        let tx = conn.transaction()?;
+        for (index, id) in receipt.envelope_ids.iter().enumerate() {
+            tx.execute(
+                "INSERT OR REPLACE INTO envelopes (envelope_id, bundle_txid, idx) VALUES (?1, ?2, ?3)",
+                rusqlite::params![id.to_lowercase(), receipt.bundle_txid.to_lowercase(), index as i64],
+            )?;
+        }
+        if let Some(key) = &receipt.idempotency_key {
+            tx.execute(
+                "INSERT OR REPLACE INTO idempotency_keys (idempotency_key, bundle_txid) VALUES (?1, ?2)",
+                rusqlite::params![key, receipt.bundle_txid.to_lowercase()],
+            )?;
+        }
+        if let Some(id) = &receipt.bundle_id {
+            tx.execute(
+                "INSERT OR REPLACE INTO bundle_ids (bundle_id, bundle_txid) VALUES (?1, ?2)",
+                rusqlite::params![id.to_lowercase(), receipt.bundle_txid.to_lowercase()],
+            )?;
+        }
+        if let Some(encoding) = &receipt.encoding {
+            tx.execute(
+                "INSERT OR REPLACE INTO bundle_encodings (bundle_txid, encoding) VALUES (?1, ?2)",
+                rusqlite::params![
+                    receipt.bundle_txid.to_lowercase(),
+                    serde_json::to_string(encoding)?
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn receipt(&self, bundle_txid: &str) -> Result<Option<BundleReceipt>, Error> {
+        use rusqlite::OptionalExtension;
+        let conn = self.conn.lock().map_err(|_| Error::ReceiptStoreError)?;
+        let mut stmt =
+            conn.prepare("SELECT envelope_id FROM envelopes WHERE bundle_txid = ?1 ORDER BY idx")?;
+        let envelope_ids = stmt
+            .query_map([bundle_txid.to_lowercase()], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        if envelope_ids.is_empty() {
+            return Ok(None);
+        }
+        let idempotency_key = conn
+            .query_row(
+                "SELECT idempotency_key FROM idempotency_keys WHERE bundle_txid = ?1",
+                [bundle_txid.to_lowercase()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let bundle_id = conn
+            .query_row(
+                "SELECT bundle_id FROM bundle_ids WHERE bundle_txid = ?1",
+                [bundle_txid.to_lowercase()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let encoding: Option<String> = conn
+            .query_row(
+                "SELECT encoding FROM bundle_encodings WHERE bundle_txid = ?1",
+                [bundle_txid.to_lowercase()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(Some(BundleReceipt {
+            bundle_txid: bundle_txid.to_string(),
+            envelope_ids,
+            idempotency_key,
+            bundle_id,
+            encoding: encoding.as_deref().map(serde_json::from_str).transpose()?,
+        }))
+    }
+
+    fn lookup_envelope(&self, envelope_id: &str) -> Result<Option<BundleLocation>, Error> {
+        use rusqlite::OptionalExtension;
+        let conn = self.conn.lock().map_err(|_| Error::ReceiptStoreError)?;
+        let location = conn
+            .query_row(
+                "SELECT bundle_txid, idx FROM envelopes WHERE envelope_id = ?1",
+                [envelope_id.to_lowercase()],
+                |row| {
+                    Ok(BundleLocation {
+                        bundle_txid: row.get(0)?,
+                        index: row.get::<_, i64>(1)? as usize,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(location)
+    }
+    fn find_idempotent(&self, idempotency_key: &str) -> Result<Option<BundleReceipt>, Error> {
+        use rusqlite::OptionalExtension;
+        let txid: Option<String> = {
+            let conn = self.conn.lock().map_err(|_| Error::ReceiptStoreError)?;
+            conn.query_row(
+                "SELECT bundle_txid FROM idempotency_keys WHERE idempotency_key = ?1",
+                [idempotency_key],
+                |row| row.get(0),
+            )
+            .optional()?
+        };
+        match txid {
+            Some(txid) => self.receipt(&txid),
+            None => Ok(None),
+        }
+    }
+
+    fn find_bundle(&self, bundle_id: &str) -> Result<Option<BundleReceipt>, Error> {
+        use rusqlite::OptionalExtension;
+        let txid: Option<String> = {
+            let conn = self.conn.lock().map_err(|_| Error::ReceiptStoreError)?;
+            conn.query_row(
+                "SELECT bundle_txid FROM bundle_ids WHERE bundle_id = ?1",
+                [bundle_id.to_lowercase()],
+                |row| row.get(0),
+            )
+            .optional()?
+        };
+        match txid {
+            Some(txid) => self.receipt(&txid),
+            None => Ok(None),
+        }
+    }
+
+    // in save order of their first envelope
+    fn export(&self) -> Result<Vec<BundleReceipt>, Error> {
+        let txids: Vec<String> = {
+            let conn = self.conn.lock().map_err(|_| Error::ReceiptStoreError)?;
+            let mut stmt = conn.prepare(
+                "SELECT bundle_txid FROM envelopes GROUP BY bundle_txid ORDER BY min(rowid)",
+            )?;
+            let txids = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            txids
+        };
+        let mut receipts = vec![];
+        for txid in txids {
+            receipts.extend(self.receipt(&txid)?);
+        }
+        Ok(receipts)
+    }
+}
@@ -0,0 +1,101 @@
+use crate::utils::constants::WVM_RPC_URL;
+use crate::utils::core::bundle_config::BundleConfig;
+use crate::utils::core::chain_target::ChainTarget;
+use crate::utils::errors::Error;
+use crate::utils::evm::{create_http_client, send_contract_call};
+use {
+    alloy::{
+        primitives::{Address, B256},
+        sol,
+        sol_types::SolCall,
+    },
+    std::str::FromStr,
+};
+
+sol! {
+    #[sol(rpc)]
+    interface IBundleRegistry {
+        event Published(string name, bytes32 txid, address indexed publisher);
+
+        function resolve(string calldata name) external view returns (bytes32);
+        function publish(string calldata name, bytes32 txid) external;
+    }
+}
+
+// registry name under which a bundle id points to its bundle txid
+pub fn bundle_id_name(bundle_id: B256) -> String {
+    format!("bundles/{bundle_id}")
+}
+
+#[derive(Debug, Clone)]
+pub struct Registry {
+    pub address: String,
+    pub rpc_url: String,
+}
+
+impl Registry {
+    pub fn new(address: String) -> Self {
+        Registry {
+            address,
+            rpc_url: WVM_RPC_URL.to_string(),
+        }
+    }
+
+    pub fn rpc_url(mut self, rpc_url: String) -> Self {
+        self.rpc_url = rpc_url;
+        self
+    }
+
+    // resolves a name such as "my-app/snapshots/latest" to the latest bundle txid
+    pub async fn resolve(&self, name: &str) -> Result<String, Error> {
+        let provider = create_http_client(&self.rpc_url)?;
+        let registry = IBundleRegistry::new(self.address.parse::<Address>()?, provider);
+
+        let txid = registry.resolve(name.to_string()).call().await?._0;
+        if txid == B256::ZERO {
+            return Err(Error::NameNotRegistered(name.to_string()));
+        }
+        Ok(txid.to_string())
+    }
+
+    // points `name` to `bundle_txid`, returns the registry update tx hash
+    pub async fn publish(
+        &self,
+        name: &str,
+        bundle_txid: &str,
+        private_key: String,
+    ) -> Result<String, Error> {
+        let config = BundleConfig::new().target(ChainTarget::weavevm().rpc_url(&self.rpc_url));
+        self.publish_with(name, bundle_txid, private_key, &config)
+            .await
+    }
+
+    // the update tx is sent to the `config` target chain (its chain id and node), with
+    // the retries, audit log and spend guard of the bundle txs
+    pub async fn publish_with(
+        &self,
+        name: &str,
+        bundle_txid: &str,
+        private_key: String,
+        config: &BundleConfig,
+    ) -> Result<String, Error> {
+        let call = IBundleRegistry::publishCall {
+            name: name.to_string(),
+            txid: B256::from_str(bundle_txid)?,
+        };
+        let tx =
+            send_contract_call(&self.address, &call.abi_encode(), &private_key, config).await?;
+        Ok(tx.tx_hash().to_string())
+    }
+
+    // records the bundle as posted, for `BundleIndex` lookups before later submissions
+    pub async fn publish_bundle_id(
+        &self,
+        bundle_id: B256,
+        bundle_txid: &str,
+        private_key: String,
+    ) -> Result<String, Error> {
+        self.publish(&bundle_id_name(bundle_id), bundle_txid, private_key)
+            .await
+    }
+}
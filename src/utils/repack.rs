@@ -0,0 +1,175 @@
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::compression::Compression;
+use crate::utils::core::decode_config::DecodeConfig;
+use crate::utils::core::envelope::Envelope;
+use crate::utils::core::retention::unix_now;
+use crate::utils::core::tag::find_tag;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use crate::utils::evm::retrieve_bundle_with;
+use crate::utils::scan_filter::AddressFilter;
+use alloy::primitives::{keccak256, B256};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+pub type EnvelopePredicate = Arc<dyn Fn(&TxEnvelopeWrapper) -> bool + Send + Sync>;
+
+// which envelopes of the source bundles are kept, and how
+#[derive(Clone)]
+pub struct RepackOptions {
+    pub decode: DecodeConfig,
+    pub signers: AddressFilter,
+    pub targets: AddressFilter,
+    // (name, value) tags every kept envelope must carry
+    pub required_tags: Vec<(String, String)>,
+    pub drop_expired: bool,
+    // keeps the first of envelopes with the same data, target, tags and extensions
+    pub dedupe: bool,
+    // replaces the compression of every envelope
+    pub compression: Option<Compression>,
+    pub filter: Option<EnvelopePredicate>,
+}
+
+impl fmt::Debug for RepackOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RepackOptions")
+            .field("decode", &self.decode)
+            .field("signers", &self.signers)
+            .field("targets", &self.targets)
+            .field("required_tags", &self.required_tags)
+            .field("drop_expired", &self.drop_expired)
+            .field("dedupe", &self.dedupe)
+            .field("compression", &self.compression)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
+}
+
+impl Default for RepackOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RepackOptions {
+    pub fn new() -> Self {
+        RepackOptions {
+            decode: DecodeConfig::default(),
+            signers: AddressFilter::default(),
+            targets: AddressFilter::default(),
+            required_tags: vec![],
+            drop_expired: true,
+            dedupe: true,
+            compression: None,
+            filter: None,
+        }
+    }
+
+    pub fn decode_config(mut self, config: DecodeConfig) -> Self {
+        self.decode = config;
+        self
+    }
+
+    pub fn signers(mut self, filter: AddressFilter) -> Self {
+        self.signers = filter;
+        self
+    }
+
+    pub fn targets(mut self, filter: AddressFilter) -> Self {
+        self.targets = filter;
+        self
+    }
+
+    pub fn require_tag(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.required_tags.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn drop_expired(mut self, drop_expired: bool) -> Self {
+        self.drop_expired = drop_expired;
+        self
+    }
+
+    pub fn dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    pub fn filter(
+        mut self,
+        filter: impl Fn(&TxEnvelopeWrapper) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    fn keeps(&self, envelope: &TxEnvelopeWrapper, now: u64) -> bool {
+        if self.drop_expired && envelope.is_expired(now) {
+            return false;
+        }
+        let tagged = self
+            .required_tags
+            .iter()
+            .all(|(name, value)| find_tag(&envelope.tags, name) == Some(value.as_str()));
+        let target = envelope.to.parse();
+        let signed = self.signers.is_open()
+            || envelope
+                .recover_signer()
+                .is_ok_and(|signer| self.signers.allows(&signer));
+        tagged
+            && signed
+            && (self.targets.is_open() || target.is_ok_and(|to| self.targets.allows(&to)))
+            && self.filter.as_ref().is_none_or(|filter| filter(envelope))
+    }
+}
+
+// decodes the bundles of `txids` (in any format the decoder reads) and returns their kept
+// envelopes in order, unsigned, to be submitted again in as many bundles as needed
+pub async fn repack(txids: &[String], options: &RepackOptions) -> Result<Vec<Envelope>, Error> {
+    let mut bundles = Vec::with_capacity(txids.len());
+    for txid in txids {
+        let (_, bundle) = retrieve_bundle_with(txid.clone(), &options.decode).await?;
+        bundles.push(bundle);
+    }
+    repack_bundles(&bundles, options)
+}
+
+pub fn repack_bundles(
+    bundles: &[BundleData],
+    options: &RepackOptions,
+) -> Result<Vec<Envelope>, Error> {
+    let now = unix_now();
+    let mut seen: HashSet<B256> = HashSet::new();
+    let mut envelopes = vec![];
+    for envelope in bundles.iter().flat_map(|bundle| &bundle.envelopes) {
+        if !options.keeps(envelope, now) {
+            continue;
+        }
+        if options.dedupe && !seen.insert(content_digest(envelope)) {
+            continue;
+        }
+        let mut repacked = envelope.to_envelope()?;
+        if let Some(compression) = options.compression {
+            repacked.compression = compression;
+        }
+        envelopes.push(repacked);
+    }
+    Ok(envelopes)
+}
+
+// what an envelope carries, regardless of its signature
+fn content_digest(envelope: &TxEnvelopeWrapper) -> B256 {
+    let content = (
+        &envelope.input,
+        &envelope.to,
+        &envelope.tags,
+        &envelope.extensions,
+    );
+    keccak256(borsh::to_vec(&content).unwrap())
+}
@@ -0,0 +1,287 @@
+use crate::utils::errors::Error;
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::{TransportError, TransportErrorKind, TransportFut};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+// process-wide record or replay mode of every provider the library creates, see
+// `set_rpc_replay`
+static RPC_REPLAY: RwLock<Option<Arc<RpcReplay>>> = RwLock::new(None);
+
+// a JSON-RPC call and what the endpoint answered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcExchange {
+    // the request packet, an array for batches
+    pub request: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+    // transport error (timeout, refused connection, http status) in place of a response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum RpcReplay {
+    Record(RpcRecorder),
+    Replay(RpcReplayer),
+}
+
+impl RpcReplay {
+    // records to a new JSON lines file, replacing any file at `path`
+    pub fn record(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(RpcReplay::Record(RpcRecorder::create(path)?))
+    }
+
+    // answers from a recording, no request reaches the endpoint
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(RpcReplay::Replay(RpcReplayer::open(path)?))
+    }
+}
+
+// records or replays every RPC call made through the library from now on (providers
+// created before keep the previous mode), `None` goes back to live calls
+pub fn set_rpc_replay(replay: Option<RpcReplay>) {
+    *RPC_REPLAY.write().unwrap_or_else(|e| e.into_inner()) = replay.map(Arc::new);
+}
+
+pub fn rpc_replay() -> Option<Arc<RpcReplay>> {
+    RPC_REPLAY.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[derive(Debug)]
+pub struct RpcRecorder {
+    file: Mutex<File>,
+}
+
+impl RpcRecorder {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(RpcRecorder {
+            file: Mutex::new(File::create(path)?),
+        })
+    }
+
+    // a line per exchange, written as soon as the call returns so a crashing run keeps
+    // its calls. A failed write doesn't fail the call.
+    fn record(&self, request: Value, result: &Result<ResponsePacket, TransportError>) {
+        let exchange = match result {
+            Ok(response) => RpcExchange {
+                request,
+                response: match response {
+                    ResponsePacket::Single(response) => serde_json::to_value(response),
+                    ResponsePacket::Batch(responses) => serde_json::to_value(responses),
+                }
+                .ok(),
+                error: None,
+            },
+            Err(e) => RpcExchange {
+                request,
+                response: None,
+                error: Some(e.to_string()),
+            },
+        };
+        let Ok(mut line) = serde_json::to_vec(&exchange) else {
+            return;
+        };
+        line.push(b'\n');
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = file.write_all(&line).and_then(|_| file.flush());
+    }
+}
+
+#[derive(Debug)]
+pub struct RpcReplayer {
+    // `None` once replayed
+    exchanges: Mutex<Vec<Option<RpcExchange>>>,
+}
+
+impl RpcReplayer {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let exchanges = fs::read_to_string(path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(exchanges))
+    }
+
+    pub fn new(exchanges: Vec<RpcExchange>) -> Self {
+        RpcReplayer {
+            exchanges: Mutex::new(exchanges.into_iter().map(Some).collect()),
+        }
+    }
+
+    // exchanges not replayed yet, a run diverging from the recorded one leaves some
+    pub fn remaining(&self) -> usize {
+        let exchanges = self.exchanges.lock().unwrap_or_else(|e| e.into_inner());
+        exchanges.iter().flatten().count()
+    }
+
+    // each exchange is replayed once, in recording order: the first one with the same
+    // calls (methods and params, ids aside), else the first one with the same methods as
+    // params may embed values of the run such as timestamps
+    fn take(&self, request: &Value) -> Option<RpcExchange> {
+        let requested = calls(request);
+        let methods: Vec<&Value> = requested.iter().map(|(method, _)| method).collect();
+        let mut exchanges = self.exchanges.lock().unwrap_or_else(|e| e.into_inner());
+        let same_calls = exchanges
+            .iter()
+            .position(|e| e.as_ref().is_some_and(|e| calls(&e.request) == requested));
+        let position = same_calls.or_else(|| {
+            exchanges.iter().position(|e| {
+                e.as_ref().is_some_and(|e| {
+                    let recorded = calls(&e.request);
+                    recorded
+                        .iter()
+                        .map(|(method, _)| method)
+                        .eq(methods.iter().copied())
+                })
+            })
+        })?;
+        exchanges[position].take()
+    }
+
+    // recorded transport errors come back as custom transport errors with the recorded
+    // message
+    fn respond(&self, request: &RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let request = serde_json::to_value(request).map_err(TransportErrorKind::custom)?;
+        let Some(exchange) = self.take(&request) else {
+            let methods: Vec<String> = calls(&request)
+                .into_iter()
+                .map(|(method, _)| method.as_str().unwrap_or_default().to_string())
+                .collect();
+            return Err(TransportErrorKind::custom_str(&format!(
+                "no recorded response for {}",
+                methods.join(", ")
+            )));
+        };
+        if let Some(error) = exchange.error {
+            return Err(TransportErrorKind::custom_str(&error));
+        }
+        let mut response = exchange.response.unwrap_or_default();
+        let (recorded_ids, ids) = (ids(&exchange.request), ids(&request));
+        let rewrite = |item: &mut Value| {
+            let Some(id) = item.get_mut("id") else {
+                return;
+            };
+            if let Some(i) = recorded_ids.iter().position(|recorded| recorded == id) {
+                *id = ids.get(i).cloned().unwrap_or_default();
+            }
+        };
+        match &mut response {
+            Value::Array(items) => items.iter_mut().for_each(rewrite),
+            item => rewrite(item),
+        }
+        // `ResponsePacket` borrows raw values, it can't be read from a `Value`
+        serde_json::from_str(&response.to_string()).map_err(TransportErrorKind::custom)
+    }
+}
+
+fn packet_items(packet: &Value) -> Vec<&Value> {
+    match packet {
+        Value::Array(items) => items.iter().collect(),
+        item => vec![item],
+    }
+}
+
+fn calls(packet: &Value) -> Vec<(Value, Value)> {
+    let field = |item: &Value, name| item.get(name).cloned().unwrap_or_default();
+    packet_items(packet)
+        .into_iter()
+        .map(|item| (field(item, "method"), field(item, "params")))
+        .collect()
+}
+
+fn ids(packet: &Value) -> Vec<Value> {
+    let id = |item: &Value| item.get("id").cloned().unwrap_or_default();
+    packet_items(packet).into_iter().map(id).collect()
+}
+
+// records the calls and their outcome, or answers them from a recording without
+// calling the inner transport. Under `RetryLayer` each attempt is recorded.
+#[derive(Debug, Clone, Default)]
+pub struct RpcReplayLayer {
+    replay: Option<Arc<RpcReplay>>,
+}
+
+impl RpcReplayLayer {
+    pub fn new(replay: RpcReplay) -> Self {
+        RpcReplayLayer {
+            replay: Some(Arc::new(replay)),
+        }
+    }
+
+    // the mode set with `set_rpc_replay`, if any
+    pub fn global() -> Self {
+        RpcReplayLayer {
+            replay: rpc_replay(),
+        }
+    }
+
+    pub fn shared(replay: Arc<RpcReplay>) -> Self {
+        RpcReplayLayer {
+            replay: Some(replay),
+        }
+    }
+
+    pub fn mode(&self) -> Option<&RpcReplay> {
+        self.replay.as_deref()
+    }
+}
+
+impl<S> Layer<S> for RpcReplayLayer {
+    type Service = RpcReplayService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcReplayService {
+            inner,
+            replay: self.replay.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcReplayService<S> {
+    inner: S,
+    replay: Option<Arc<RpcReplay>>,
+}
+
+impl<S> Service<RequestPacket> for RpcReplayService<S>
+where
+    S: Service<RequestPacket, Future = TransportFut<'static>, Error = TransportError>
+        + Clone
+        + Send
+        + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let Some(replay) = self.replay.clone() else {
+            return self.inner.call(request);
+        };
+        if let RpcReplay::Replay(replayer) = replay.as_ref() {
+            let response = replayer.respond(&request);
+            return Box::pin(async move { response });
+        }
+        let recorded = serde_json::to_value(&request).unwrap_or_default();
+        let call = self.inner.call(request);
+        Box::pin(async move {
+            let result = call.await;
+            if let RpcReplay::Record(recorder) = replay.as_ref() {
+                recorder.record(recorded, &result);
+            }
+            result
+        })
+    }
+}
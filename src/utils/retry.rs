@@ -0,0 +1,224 @@
+use alloy::primitives::keccak256;
+use alloy::rpc::json_rpc::{
+    ErrorPayload, RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest,
+};
+use alloy::transports::{TransportError, TransportErrorKind, TransportFut};
+use serde_json::value::RawValue;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::{Layer, Service};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcErrorClass {
+    RateLimited,
+    // connection failures, gateway errors and lagging nodes
+    Transient,
+    // handled where the tx is built, see `send_bundle_tx`
+    NonceConflict,
+    Fatal,
+}
+
+pub fn classify_rpc_error(error: &TransportError) -> RpcErrorClass {
+    match error {
+        TransportError::ErrorResp(payload) => classify_error_payload(payload),
+        TransportError::Transport(TransportErrorKind::HttpError(e)) => match e.status {
+            429 => RpcErrorClass::RateLimited,
+            500 | 502 | 503 | 504 => RpcErrorClass::Transient,
+            _ => RpcErrorClass::Fatal,
+        },
+        TransportError::Transport(TransportErrorKind::Custom(e)) => {
+            match e.to_string().contains("429") {
+                true => RpcErrorClass::RateLimited,
+                // reqwest and socket errors end up here
+                false => RpcErrorClass::Transient,
+            }
+        }
+        TransportError::Transport(
+            TransportErrorKind::BackendGone | TransportErrorKind::MissingBatchResponse(_),
+        ) => RpcErrorClass::Transient,
+        TransportError::NullResp => RpcErrorClass::Transient,
+        _ => RpcErrorClass::Fatal,
+    }
+}
+
+fn classify_error_payload<E>(payload: &ErrorPayload<E>) -> RpcErrorClass {
+    let message = payload.message.to_lowercase();
+    if message.contains("nonce too low")
+        || message.contains("underpriced")
+        || message.contains("already known")
+    {
+        return RpcErrorClass::NonceConflict;
+    }
+    if payload.is_retry_err() || message.contains("rate limit") {
+        return RpcErrorClass::RateLimited;
+    }
+    if message.contains("header not found") || message.contains("timeout") {
+        return RpcErrorClass::Transient;
+    }
+    RpcErrorClass::Fatal
+}
+
+// exponential backoff, from `initial_backoff` up to `max_backoff`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+}
+
+// per class policies of every RPC call made for a bundle. Nonce conflicts and fatal
+// errors are never retried by the transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryConfig {
+    pub rate_limited: RetryPolicy,
+    pub transient: RetryPolicy,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            rate_limited: RetryPolicy {
+                max_retries: 5,
+                initial_backoff: Duration::from_secs(1),
+                max_backoff: Duration::from_secs(30),
+            },
+            transient: RetryPolicy {
+                max_retries: 3,
+                initial_backoff: Duration::from_millis(200),
+                max_backoff: Duration::from_secs(5),
+            },
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn none() -> Self {
+        RetryConfig {
+            rate_limited: RetryPolicy::none(),
+            transient: RetryPolicy::none(),
+        }
+    }
+
+    pub fn policy(&self, class: RpcErrorClass) -> Option<&RetryPolicy> {
+        match class {
+            RpcErrorClass::RateLimited => Some(&self.rate_limited),
+            RpcErrorClass::Transient => Some(&self.transient),
+            RpcErrorClass::NonceConflict | RpcErrorClass::Fatal => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryLayer {
+    config: RetryConfig,
+}
+
+impl RetryLayer {
+    pub fn new(config: RetryConfig) -> Self {
+        RetryLayer { config }
+    }
+}
+
+impl<S> Layer<S> for RetryLayer {
+    type Service = RetryService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RetryService {
+            inner,
+            config: self.config,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryService<S> {
+    inner: S,
+    config: RetryConfig,
+}
+
+impl<S> Service<RequestPacket> for RetryService<S>
+where
+    S: Service<RequestPacket, Future = TransportFut<'static>, Error = TransportError>
+        + Clone
+        + Send
+        + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let mut inner = self.inner.clone();
+        let config = self.config;
+        Box::pin(async move {
+            let mut retries = 0;
+            loop {
+                let error = match inner.call(request.clone()).await {
+                    Ok(response) => match response.as_error() {
+                        Some(payload) => TransportError::ErrorResp(payload.clone()),
+                        None => return Ok(response),
+                    },
+                    Err(e) => e,
+                };
+                // the first attempt of a retried send may have reached the mempool
+                if retries > 0 {
+                    if let Some(response) = already_sent(&request, &error) {
+                        return Ok(response);
+                    }
+                }
+                let class = classify_rpc_error(&error);
+                let Some(policy) = config.policy(class).filter(|p| retries < p.max_retries) else {
+                    return Err(error);
+                };
+                futures_timer::Delay::new(policy.backoff(retries)).await;
+                retries += 1;
+            }
+        })
+    }
+}
+
+// the tx hash of an `eth_sendRawTransaction` the node reports as already known
+fn already_sent(request: &RequestPacket, error: &TransportError) -> Option<ResponsePacket> {
+    let RequestPacket::Single(request) = request else {
+        return None;
+    };
+    let TransportError::ErrorResp(payload) = error else {
+        return None;
+    };
+    if request.method() != "eth_sendRawTransaction"
+        || !payload.message.to_lowercase().contains("already known")
+    {
+        return None;
+    }
+    let hash = raw_tx_hash(request)?;
+    Some(ResponsePacket::Single(Response {
+        id: request.id().clone(),
+        payload: ResponsePayload::Success(RawValue::from_string(format!("\"{hash}\"")).ok()?),
+    }))
+}
+
+fn raw_tx_hash(request: &SerializedRequest) -> Option<String> {
+    let params: Vec<String> = serde_json::from_str(request.params()?.get()).ok()?;
+    let raw_tx = alloy::hex::decode(params.first()?).ok()?;
+    Some(keccak256(raw_tx).to_string())
+}
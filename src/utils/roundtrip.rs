@@ -0,0 +1,248 @@
+use crate::utils::constants::{BUNDLE_FORMAT_V1, BUNDLE_FORMAT_VERSION, BUNDLE_MAGIC};
+use crate::utils::core::bundle_config::BundleConfig;
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::bundle_header::BundleHeader;
+use crate::utils::core::bundle_item::{BundleItem, BundleItemV1};
+use crate::utils::core::compression::Compression;
+use crate::utils::core::decode_config::DecodeConfig;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::decoder::decode_bundle_bytes_with;
+use crate::utils::errors::Error;
+
+// bundle calldata layouts still decoded, the ones a round trip is run through. A new
+// format version gets its variant here, with the layout its writer used frozen in
+// `encode`, so bundles of every older version keep being checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FormatVersion {
+    // headerless, the borsh serialized bundle compressed as a whole with brotli
+    Legacy,
+    // headed, items without version, extensions nor checksum
+    V1,
+    Current,
+}
+
+impl FormatVersion {
+    pub const ALL: [FormatVersion; 3] = [
+        FormatVersion::Legacy,
+        FormatVersion::V1,
+        FormatVersion::Current,
+    ];
+
+    // as read back by `BundleHeader::split_versioned_calldata`
+    pub fn version(&self) -> u8 {
+        match self {
+            FormatVersion::Legacy => 0,
+            FormatVersion::V1 => BUNDLE_FORMAT_V1,
+            FormatVersion::Current => BUNDLE_FORMAT_VERSION,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FormatVersion::Legacy => "legacy",
+            FormatVersion::V1 => "v1",
+            FormatVersion::Current => "current",
+        }
+    }
+
+    // calldata of `bundle` as the writer of this version encoded it
+    pub fn encode(&self, bundle: &BundleData, config: &BundleConfig) -> Result<Vec<u8>, Error> {
+        config.compression.validate()?;
+        match self {
+            FormatVersion::Legacy => {
+                Compression::Brotli.compress(&borsh::to_vec(bundle)?, &config.compression, None)
+            }
+            FormatVersion::V1 => {
+                let items = bundle
+                    .envelopes
+                    .iter()
+                    .map(|envelope| {
+                        let item = BundleItem::from_envelope(envelope, config)?;
+                        Ok(BundleItemV1 {
+                            compression: item.compression,
+                            data: item.data,
+                            tags: item.tags,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let mut calldata = headed(bundle, config).encode_calldata(&borsh::to_vec(&items)?);
+                calldata[BUNDLE_MAGIC.len()] = BUNDLE_FORMAT_V1;
+                Ok(calldata)
+            }
+            FormatVersion::Current => bundle.encode_calldata_with(config),
+        }
+    }
+
+    // what the layout keeps of `bundle`: legacy bundles lose their header and the
+    // compression, tags and extensions of their envelopes, v1 bundles the extensions
+    pub fn retained(&self, bundle: &BundleData, config: &BundleConfig) -> BundleData {
+        let mut retained = bundle.clone();
+        match self {
+            FormatVersion::Legacy => {
+                retained.header = BundleHeader::default();
+                for envelope in &mut retained.envelopes {
+                    envelope.compression = Compression::default();
+                    envelope.tags.clear();
+                    envelope.extensions.clear();
+                }
+            }
+            FormatVersion::V1 => {
+                retained.header = headed(bundle, config);
+                for envelope in &mut retained.envelopes {
+                    envelope.extensions.clear();
+                }
+            }
+            FormatVersion::Current => retained.header = headed(bundle, config),
+        }
+        retained
+    }
+}
+
+// headers are written with the id of the config dictionary
+fn headed(bundle: &BundleData, config: &BundleConfig) -> BundleHeader {
+    let mut header = bundle.header.clone();
+    header.dictionary_id = config.dictionary.as_ref().map(|d| d.id.clone());
+    header
+}
+
+// compressions envelopes can be stored with under `config`, the dictionary one only with
+// a config dictionary
+pub fn codecs(config: &BundleConfig) -> Vec<Compression> {
+    let mut codecs = vec![Compression::None, Compression::Brotli];
+    if config.dictionary.is_some() {
+        codecs.push(Compression::BrotliDictionary);
+    }
+    codecs
+}
+
+// a round trip that held, for callers to check more of it
+#[derive(Debug, Clone)]
+pub struct RoundTrip {
+    pub version: FormatVersion,
+    pub compression: Compression,
+    pub calldata: Vec<u8>,
+    pub decoded: BundleData,
+}
+
+// encode → compress → decode → validate: the envelopes of `bundle` compressed with
+// `compression` in the `version` layout, decoded and checked by the `decode_config`
+// policy, then encoded again. Fails with `Error::RoundTripMismatch` unless the decoded
+// bundle holds what the layout keeps of `bundle`, its items are stored with `compression`
+// (or raw, past the incompressible ratio), migrating it keeps its id and encoding it again
+// yields the same bytes.
+pub fn round_trip(
+    bundle: &BundleData,
+    version: FormatVersion,
+    compression: Compression,
+    config: &BundleConfig,
+    decode_config: &DecodeConfig,
+) -> Result<RoundTrip, Error> {
+    let mismatch = |what: String| {
+        Error::RoundTripMismatch(format!(
+            "{} bundle of {:?} envelopes: {what}",
+            version.as_str(),
+            compression
+        ))
+    };
+    let mut decode_config = decode_config.clone();
+    if let Some(dictionary) = &config.dictionary {
+        if decode_config.find_dictionary(&dictionary.id).is_none() {
+            decode_config = decode_config.dictionary(dictionary.clone());
+        }
+    }
+    let mut bundle = bundle.clone();
+    for envelope in &mut bundle.envelopes {
+        envelope.compression = compression;
+    }
+
+    let calldata = version.encode(&bundle, config)?;
+    let (read_version, _, _) = BundleHeader::split_versioned_calldata(&calldata)?;
+    if read_version != version.version() {
+        return Err(mismatch(format!("read back as version {read_version}")));
+    }
+    let decoded = decode_bundle_bytes_with(&calldata, &decode_config)?;
+    let expected = version.retained(&bundle, config);
+    if decoded.header != expected.header {
+        return Err(mismatch("header differs after decoding".to_string()));
+    }
+    if decoded.envelopes.len() != expected.envelopes.len() {
+        return Err(mismatch(format!(
+            "{} envelopes decoded out of {}",
+            decoded.envelopes.len(),
+            expected.envelopes.len()
+        )));
+    }
+    for (i, (decoded, expected)) in decoded
+        .envelopes
+        .iter()
+        .zip(&expected.envelopes)
+        .enumerate()
+    {
+        if version != FormatVersion::Legacy && !stored_as(decoded.compression, compression, config)
+        {
+            return Err(mismatch(format!(
+                "envelope {i} stored as {:?}",
+                decoded.compression
+            )));
+        }
+        // the stored compression is checked above, it may differ from the requested one
+        let decoded = TxEnvelopeWrapper {
+            compression: expected.compression,
+            ..decoded.clone()
+        };
+        if decoded != *expected {
+            return Err(mismatch(format!("envelope {i} differs after decoding")));
+        }
+    }
+    if decoded.id() != expected.id() {
+        return Err(mismatch("bundle id changed".to_string()));
+    }
+
+    let migrated = BundleData::migrate_calldata(&calldata)?;
+    if decode_bundle_bytes_with(&migrated, &decode_config)?.id() != expected.id() {
+        return Err(mismatch("bundle id changed by the migration".to_string()));
+    }
+    if version.encode(&decoded, config)? != calldata {
+        return Err(mismatch("calldata differs once encoded again".to_string()));
+    }
+    Ok(RoundTrip {
+        version,
+        compression,
+        calldata,
+        decoded,
+    })
+}
+
+fn stored_as(stored: Compression, requested: Compression, config: &BundleConfig) -> bool {
+    let expected = match (requested, &config.dictionary) {
+        (Compression::Brotli, Some(_)) => Compression::BrotliDictionary,
+        (requested, _) => requested,
+    };
+    stored == expected || (stored == Compression::None && config.incompressible_ratio.is_some())
+}
+
+// `round_trip` through every format version with every codec of `config`, legacy bundles
+// once as they are compressed as a whole
+pub fn round_trip_all(
+    bundle: &BundleData,
+    config: &BundleConfig,
+    decode_config: &DecodeConfig,
+) -> Result<Vec<RoundTrip>, Error> {
+    let mut round_trips = vec![];
+    for version in FormatVersion::ALL {
+        let codecs = match version {
+            FormatVersion::Legacy => vec![Compression::Brotli],
+            _ => codecs(config),
+        };
+        for compression in codecs {
+            round_trips.push(round_trip(
+                bundle,
+                version,
+                compression,
+                config,
+                decode_config,
+            )?);
+        }
+    }
+    Ok(round_trips)
+}
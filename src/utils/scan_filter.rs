@@ -0,0 +1,91 @@
+use crate::utils::core::bundle_data::BundleData;
+use alloy::primitives::Address;
+use std::collections::HashSet;
+
+// allows every address by default, an allowlist restricts it to the listed ones and the
+// denylist always wins
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddressFilter {
+    pub allow: Option<HashSet<Address>>,
+    pub deny: HashSet<Address>,
+}
+
+impl AddressFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(mut self, address: Address) -> Self {
+        self.allow.get_or_insert_with(HashSet::new).insert(address);
+        self
+    }
+
+    pub fn deny(mut self, address: Address) -> Self {
+        self.deny.insert(address);
+        self
+    }
+
+    pub fn allows(&self, address: &Address) -> bool {
+        !self.deny.contains(address) && self.allow.as_ref().is_none_or(|a| a.contains(address))
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.allow.is_none() && self.deny.is_empty()
+    }
+}
+
+// bundles kept by a scan or a watch, see `scan_bundles_matching`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanFilter {
+    // carrier bundle tx senders
+    pub senders: AddressFilter,
+    // envelope signers
+    pub signers: AddressFilter,
+    // bundles with at least one envelope under the path prefix
+    pub path_prefix: Option<String>,
+}
+
+impl ScanFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn senders(mut self, filter: AddressFilter) -> Self {
+        self.senders = filter;
+        self
+    }
+
+    pub fn signers(mut self, filter: AddressFilter) -> Self {
+        self.signers = filter;
+        self
+    }
+
+    pub fn path_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.path_prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn matches_sender(&self, sender: &Address) -> bool {
+        self.senders.allows(sender)
+    }
+
+    // a bundle is kept only when every one of its envelopes has an allowed signer, an
+    // envelope whose signer can't be recovered never matches a restricted filter
+    pub fn matches_signers(&self, bundle: &BundleData) -> bool {
+        if self.signers.is_open() {
+            return true;
+        }
+        bundle.envelopes.iter().all(|envelope| {
+            envelope
+                .recover_signer()
+                .is_ok_and(|signer| self.signers.allows(&signer))
+        })
+    }
+
+    pub fn matches_path(&self, bundle: &BundleData) -> bool {
+        match &self.path_prefix {
+            Some(prefix) => bundle.find_by_prefix(prefix).next().is_some(),
+            None => true,
+        }
+    }
+}
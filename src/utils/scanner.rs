@@ -0,0 +1,356 @@
+use crate::utils::cancellation::CancellationToken;
+use crate::utils::checkpoint::CheckpointStore;
+use crate::utils::constants::{ADDRESS_BABE1, WVM_RPC_URL};
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::retention::unix_now;
+use crate::utils::errors::Error;
+use crate::utils::evm::{create_http_client, retrieve_bundle_data, EvmProvider};
+use crate::utils::scan_filter::ScanFilter;
+use {
+    alloy::{
+        consensus::Transaction, eips::BlockNumberOrTag, primitives::Address, providers::Provider,
+        rpc::types::BlockTransactionsKind,
+    },
+    futures::stream::{self, Stream, StreamExt},
+    std::{collections::VecDeque, pin::Pin, sync::Arc, time::Duration},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannedBundle {
+    pub txid: String,
+    pub block_number: u64,
+    pub sender: String,
+    pub bundle: BundleData,
+    // indices of the envelopes already expired when the bundle was scanned
+    pub expired: Vec<usize>,
+}
+
+pub async fn latest_block_number() -> Result<u64, Error> {
+    let provider = create_http_client(WVM_RPC_URL)?;
+    Ok(provider.get_block_number().await?)
+}
+
+// returns the bundles settled in blocks `from_block..=to_block`, in chain order
+pub async fn scan_bundles(from_block: u64, to_block: u64) -> Result<Vec<ScannedBundle>, Error> {
+    scan_bundles_with(from_block, to_block, &CancellationToken::new()).await
+}
+
+// stops between blocks, or while waiting on the RPC, once `cancellation` is cancelled
+pub async fn scan_bundles_with(
+    from_block: u64,
+    to_block: u64,
+    cancellation: &CancellationToken,
+) -> Result<Vec<ScannedBundle>, Error> {
+    scan_bundles_matching(from_block, to_block, &ScanFilter::default(), cancellation).await
+}
+
+// only the bundles passing `filter`, senders being checked before any decoding
+pub async fn scan_bundles_matching(
+    from_block: u64,
+    to_block: u64,
+    filter: &ScanFilter,
+    cancellation: &CancellationToken,
+) -> Result<Vec<ScannedBundle>, Error> {
+    cancellation.check()?;
+    let provider = create_http_client(WVM_RPC_URL)?;
+    let babe1 = ADDRESS_BABE1.parse::<Address>()?;
+    let mut bundles: Vec<ScannedBundle> = vec![];
+
+    for block_number in from_block..=to_block {
+        bundles.extend(scan_block(&provider, babe1, block_number, filter, cancellation).await?);
+    }
+
+    Ok(bundles)
+}
+
+async fn scan_block(
+    provider: &EvmProvider,
+    babe1: Address,
+    block_number: u64,
+    filter: &ScanFilter,
+    cancellation: &CancellationToken,
+) -> Result<Vec<ScannedBundle>, Error> {
+    let block = cancellation
+        .run(async {
+            let block = provider
+                .get_block_by_number(
+                    BlockNumberOrTag::Number(block_number),
+                    BlockTransactionsKind::Full,
+                )
+                .await?;
+            Ok(block)
+        })
+        .await?;
+    let Some(block) = block else {
+        return Ok(vec![]);
+    };
+
+    let mut bundles = vec![];
+    for tx in block.transactions.txns() {
+        if tx.to() != Some(babe1) || !filter.matches_sender(&tx.from) {
+            continue;
+        }
+        // a tx sent to 0xbabe1 is not necessarily a valid bundle
+        let Ok(bundle) = retrieve_bundle_data(tx.input().to_string()).await else {
+            continue;
+        };
+        if !filter.matches_signers(&bundle) || !filter.matches_path(&bundle) {
+            continue;
+        }
+        let now = unix_now();
+        let expired = (0..bundle.envelopes.len())
+            .filter(|&i| bundle.envelopes[i].is_expired(now))
+            .collect();
+        bundles.push(ScannedBundle {
+            txid: tx.inner.tx_hash().to_string(),
+            block_number,
+            sender: tx.from.to_string(),
+            bundle,
+            expired,
+        });
+    }
+    Ok(bundles)
+}
+
+pub const DEFAULT_SCAN_CONCURRENCY: usize = 8;
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 100;
+
+#[derive(Debug, Clone)]
+pub struct BackfillConfig {
+    pub rpc_url: String,
+    pub filter: ScanFilter,
+    // blocks fetched at once, their bundles are still yielded in chain order
+    pub concurrency: usize,
+    pub checkpoint_store: Option<Arc<dyn CheckpointStore>>,
+    // blocks processed between two checkpoint saves, a resumed scan may yield again the
+    // bundles of up to this many blocks
+    pub checkpoint_interval: u64,
+    pub cancellation: CancellationToken,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackfillConfig {
+    pub fn new() -> Self {
+        BackfillConfig {
+            rpc_url: WVM_RPC_URL.to_string(),
+            filter: ScanFilter::default(),
+            concurrency: DEFAULT_SCAN_CONCURRENCY,
+            checkpoint_store: None,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    pub fn rpc_url(mut self, rpc_url: &str) -> Self {
+        self.rpc_url = rpc_url.to_string();
+        self
+    }
+
+    pub fn filter(mut self, filter: ScanFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn checkpoint_store(mut self, store: Arc<dyn CheckpointStore>) -> Self {
+        self.checkpoint_store = Some(store);
+        self
+    }
+
+    pub fn checkpoint_interval(mut self, blocks: u64) -> Self {
+        self.checkpoint_interval = blocks.max(1);
+        self
+    }
+
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    // the block after the checkpoint when there is one past `from_block`
+    pub fn resume_block(&self, from_block: u64) -> Result<u64, Error> {
+        let checkpoint = match &self.checkpoint_store {
+            Some(store) => store.load()?,
+            None => None,
+        };
+        Ok(checkpoint.map_or(from_block, |block| from_block.max(block + 1)))
+    }
+}
+
+type ScannedBlocks = Pin<Box<dyn Stream<Item = Result<(u64, Vec<ScannedBundle>), Error>> + Send>>;
+
+struct Backfill {
+    blocks: ScannedBlocks,
+    pending: VecDeque<ScannedBundle>,
+    // last block whose bundles were all yielded, and the last one saved
+    processed: Option<u64>,
+    saved: Option<u64>,
+    config: BackfillConfig,
+    done: bool,
+}
+
+impl Backfill {
+    fn checkpoint(&mut self, force: bool) -> Result<(), Error> {
+        let (Some(store), Some(processed)) = (&self.config.checkpoint_store, self.processed) else {
+            return Ok(());
+        };
+        let due = match self.saved {
+            Some(saved) => processed >= saved + self.config.checkpoint_interval,
+            None => true,
+        };
+        if (force || due) && self.saved != Some(processed) {
+            store.save(processed)?;
+            self.saved = Some(processed);
+        }
+        Ok(())
+    }
+}
+
+// scans `from_block..=to_block` (or the blocks after the checkpoint) fetching up to
+// `concurrency` blocks at once. The checkpoint only moves past a block once all its
+// bundles were yielded, so a crashed backfill resumes without missing any. The stream
+// ends after its first error, and without error once cancelled.
+pub async fn backfill_bundles(
+    from_block: u64,
+    to_block: u64,
+    config: BackfillConfig,
+) -> Result<impl Stream<Item = Result<ScannedBundle, Error>>, Error> {
+    let start = config.resume_block(from_block)?;
+    let provider = create_http_client(&config.rpc_url)?;
+    let babe1 = ADDRESS_BABE1.parse::<Address>()?;
+    let (filter, cancellation) = (config.filter.clone(), config.cancellation.clone());
+    let blocks = stream::iter(start..=to_block)
+        .map(move |block_number| {
+            let (provider, filter) = (provider.clone(), filter.clone());
+            let cancellation = cancellation.clone();
+            async move {
+                let bundles =
+                    scan_block(&provider, babe1, block_number, &filter, &cancellation).await?;
+                Ok((block_number, bundles))
+            }
+        })
+        .buffered(config.concurrency.max(1));
+
+    let state = Backfill {
+        blocks: Box::pin(blocks),
+        pending: VecDeque::new(),
+        processed: None,
+        saved: None,
+        config,
+        done: false,
+    };
+    Ok(stream::unfold(state, |mut state| async move {
+        loop {
+            if state.done {
+                return None;
+            }
+            if let Some(bundle) = state.pending.pop_front() {
+                return Some((Ok(bundle), state));
+            }
+            if let Err(e) = state.checkpoint(false) {
+                state.done = true;
+                return Some((Err(e), state));
+            }
+            match state.blocks.next().await {
+                Some(Ok((block_number, bundles))) => {
+                    state.pending = bundles.into();
+                    state.processed = Some(block_number);
+                }
+                // every block before the failed one was fully yielded
+                Some(Err(e)) => {
+                    state.done = true;
+                    let saved = state.checkpoint(true);
+                    let error = match e {
+                        Error::Cancelled => saved.err()?,
+                        e => e,
+                    };
+                    return Some((Err(error), state));
+                }
+                None => {
+                    state.done = true;
+                    if let Err(e) = state.checkpoint(true) {
+                        return Some((Err(e), state));
+                    }
+                }
+            }
+        }
+    }))
+}
+
+// polls the chain head and yields bundles settled after `from_block`
+pub fn watch_bundles(
+    from_block: u64,
+    poll_interval: Duration,
+) -> impl Stream<Item = Result<ScannedBundle, Error>> {
+    watch_bundles_with(from_block, poll_interval, CancellationToken::new())
+}
+
+// the stream ends as soon as `cancellation` is cancelled, without error
+pub fn watch_bundles_with(
+    from_block: u64,
+    poll_interval: Duration,
+    cancellation: CancellationToken,
+) -> impl Stream<Item = Result<ScannedBundle, Error>> {
+    watch_bundles_matching(
+        from_block,
+        poll_interval,
+        ScanFilter::default(),
+        cancellation,
+    )
+}
+
+pub fn watch_bundles_matching(
+    from_block: u64,
+    poll_interval: Duration,
+    filter: ScanFilter,
+    cancellation: CancellationToken,
+) -> impl Stream<Item = Result<ScannedBundle, Error>> {
+    let state = (from_block, Vec::<ScannedBundle>::new());
+    stream::unfold(state, move |(mut next_block, mut pending)| {
+        let (filter, cancellation) = (filter.clone(), cancellation.clone());
+        async move {
+            loop {
+                if cancellation.is_cancelled() {
+                    return None;
+                }
+                if !pending.is_empty() {
+                    let bundle = pending.remove(0);
+                    return Some((Ok(bundle), (next_block, pending)));
+                }
+
+                let latest = match cancellation.run(latest_block_number()).await {
+                    Ok(latest) => latest,
+                    Err(Error::Cancelled) => return None,
+                    Err(e) => return Some((Err(e), (next_block, pending))),
+                };
+                if latest < next_block {
+                    let delay = async {
+                        futures_timer::Delay::new(poll_interval).await;
+                        Ok(())
+                    };
+                    if cancellation.run(delay).await.is_err() {
+                        return None;
+                    }
+                    continue;
+                }
+                match scan_bundles_matching(next_block, latest, &filter, &cancellation).await {
+                    Ok(bundles) => {
+                        pending = bundles;
+                        next_block = latest + 1;
+                    }
+                    Err(Error::Cancelled) => return None,
+                    Err(e) => return Some((Err(e), (next_block, pending))),
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,455 @@
+#[cfg(feature = "sqlite")]
+use crate::utils::core::tag::sql_path_has_prefix;
+use crate::utils::core::tag::{find_tag, path_has_prefix, Tag, PATH_TAG};
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use crate::utils::scanner::ScannedBundle;
+use alloy::primitives::keccak256;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Debug;
+#[cfg(feature = "sqlite")]
+use std::sync::Mutex;
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexedEnvelope {
+    pub bundle_txid: String,
+    pub block_number: u64,
+    // position in the bundle
+    pub index: usize,
+    pub envelope_id: String,
+    // `None` when the signer can't be recovered
+    pub signer: Option<String>,
+    // `None` for data-only envelopes
+    pub target: Option<String>,
+    // keccak of the payload
+    pub content_hash: String,
+    pub size: usize,
+    pub tags: Vec<Tag>,
+}
+
+impl IndexedEnvelope {
+    // hex values are lowercased
+    pub fn new(
+        bundle_txid: &str,
+        block_number: u64,
+        index: usize,
+        envelope: &TxEnvelopeWrapper,
+    ) -> Result<Self, Error> {
+        let payload = envelope.payload()?;
+        Ok(IndexedEnvelope {
+            bundle_txid: bundle_txid.to_lowercase(),
+            block_number,
+            index,
+            envelope_id: envelope.hash.to_lowercase(),
+            signer: envelope
+                .recover_signer()
+                .ok()
+                .map(|s| s.to_string().to_lowercase()),
+            target: envelope.target()?.map(|t| t.to_string().to_lowercase()),
+            content_hash: keccak256(&payload).to_string(),
+            size: payload.len(),
+            tags: envelope.tags.clone(),
+        })
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        find_tag(&self.tags, PATH_TAG)
+    }
+}
+
+// every condition must match, an empty query matches every envelope
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchQuery {
+    // tag names are case insensitive, values are not
+    pub tags: Vec<Tag>,
+    pub signer: Option<String>,
+    pub target: Option<String>,
+    pub content_hash: Option<String>,
+    pub path_prefix: Option<String>,
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    // first envelopes in chain order
+    pub limit: Option<usize>,
+}
+
+impl SearchQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tag(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push(Tag::new(name, value));
+        self
+    }
+
+    pub fn signer(mut self, signer: &str) -> Self {
+        self.signer = Some(signer.to_lowercase());
+        self
+    }
+
+    pub fn target(mut self, target: &str) -> Self {
+        self.target = Some(target.to_lowercase());
+        self
+    }
+
+    pub fn content_hash(mut self, hash: &str) -> Self {
+        self.content_hash = Some(hash.to_lowercase());
+        self
+    }
+
+    pub fn path_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.path_prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn from_block(mut self, block: u64) -> Self {
+        self.from_block = Some(block);
+        self
+    }
+
+    pub fn to_block(mut self, block: u64) -> Self {
+        self.to_block = Some(block);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn matches(&self, envelope: &IndexedEnvelope) -> bool {
+        // like the sqlite index, any of the envelope tags of the name may match
+        let tagged = self.tags.iter().all(|wanted| {
+            envelope
+                .tags
+                .iter()
+                .any(|tag| tag.name.eq_ignore_ascii_case(&wanted.name) && tag.value == wanted.value)
+        });
+        let eq = |wanted: &Option<String>, value: Option<&str>| {
+            wanted.as_deref().is_none_or(|wanted| value == Some(wanted))
+        };
+        tagged
+            && eq(&self.signer, envelope.signer.as_deref())
+            && eq(&self.target, envelope.target.as_deref())
+            && eq(&self.content_hash, Some(&envelope.content_hash))
+            && self
+                .path_prefix
+                .as_deref()
+                .is_none_or(|prefix| envelope.path().is_some_and(|p| path_has_prefix(p, prefix)))
+            && self
+                .from_block
+                .is_none_or(|from| envelope.block_number >= from)
+            && self.to_block.is_none_or(|to| envelope.block_number <= to)
+    }
+}
+
+// envelopes of scanned bundles, searchable by tags, signer, target and content. A bundle
+// is indexed once, indexing it again (e.g. from a resumed backfill) is a no-op.
+pub trait SearchIndex: Debug + Send + Sync {
+    fn insert(&self, envelopes: &[IndexedEnvelope]) -> Result<(), Error>;
+    // drops the envelopes of a bundle, e.g. reorged out of the chain
+    fn remove_bundle(&self, bundle_txid: &str) -> Result<(), Error>;
+    // in chain order
+    fn search(&self, query: &SearchQuery) -> Result<Vec<IndexedEnvelope>, Error>;
+}
+
+// returns the number of indexed envelopes
+pub fn index_scanned(index: &dyn SearchIndex, scanned: &ScannedBundle) -> Result<usize, Error> {
+    let envelopes = scanned
+        .bundle
+        .envelopes
+        .iter()
+        .enumerate()
+        .map(|(i, envelope)| IndexedEnvelope::new(&scanned.txid, scanned.block_number, i, envelope))
+        .collect::<Result<Vec<_>, _>>()?;
+    index.insert(&envelopes)?;
+    Ok(envelopes.len())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexerStats {
+    pub bundles: usize,
+    pub envelopes: usize,
+    pub last_block: Option<u64>,
+}
+
+// indexes the bundles of a scanner, backfill or watcher stream as they arrive, until it
+// ends or yields an error
+pub async fn run_indexer<S>(index: &dyn SearchIndex, bundles: S) -> Result<IndexerStats, Error>
+where
+    S: Stream<Item = Result<ScannedBundle, Error>>,
+{
+    let mut bundles = std::pin::pin!(bundles);
+    let mut stats = IndexerStats::default();
+    while let Some(scanned) = bundles.next().await {
+        let scanned = scanned?;
+        stats.envelopes += index_scanned(index, &scanned)?;
+        stats.bundles += 1;
+        stats.last_block = Some(scanned.block_number);
+    }
+    Ok(stats)
+}
+
+#[derive(Debug, Default)]
+pub struct MemorySearchIndex {
+    envelopes: RwLock<Vec<IndexedEnvelope>>,
+    locations: RwLock<HashSet<(String, usize)>>,
+}
+
+impl MemorySearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SearchIndex for MemorySearchIndex {
+    fn insert(&self, envelopes: &[IndexedEnvelope]) -> Result<(), Error> {
+        let mut indexed = self
+            .envelopes
+            .write()
+            .map_err(|_| Error::SearchIndexError)?;
+        let mut locations = self
+            .locations
+            .write()
+            .map_err(|_| Error::SearchIndexError)?;
+        for envelope in envelopes {
+            if locations.insert((envelope.bundle_txid.clone(), envelope.index)) {
+                // after the envelopes of the same block, which keep their insertion order
+                let at = indexed.partition_point(|e| e.block_number <= envelope.block_number);
+                indexed.insert(at, envelope.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_bundle(&self, bundle_txid: &str) -> Result<(), Error> {
+        let bundle_txid = bundle_txid.to_lowercase();
+        let mut indexed = self
+            .envelopes
+            .write()
+            .map_err(|_| Error::SearchIndexError)?;
+        let mut locations = self
+            .locations
+            .write()
+            .map_err(|_| Error::SearchIndexError)?;
+        indexed.retain(|envelope| envelope.bundle_txid != bundle_txid);
+        locations.retain(|(txid, _)| *txid != bundle_txid);
+        Ok(())
+    }
+
+    fn search(&self, query: &SearchQuery) -> Result<Vec<IndexedEnvelope>, Error> {
+        let indexed = self.envelopes.read().map_err(|_| Error::SearchIndexError)?;
+        Ok(indexed
+            .iter()
+            .filter(|envelope| query.matches(envelope))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteSearchIndex {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteSearchIndex {
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
+        Self::with_connection(rusqlite::Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<Self, Error> {
+        Self::with_connection(rusqlite::Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: rusqlite::Connection) -> Result<Self, Error> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS indexed_envelopes (
+                bundle_txid TEXT NOT NULL,
+                idx INTEGER NOT NULL,
+                block_number INTEGER NOT NULL,
+                envelope_id TEXT NOT NULL,
+                signer TEXT,
+                target TEXT,
+                content_hash TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                path TEXT,
+                PRIMARY KEY (bundle_txid, idx)
+            );
+            CREATE INDEX IF NOT EXISTS indexed_envelopes_block ON indexed_envelopes (block_number);
+            CREATE INDEX IF NOT EXISTS indexed_envelopes_signer ON indexed_envelopes (signer);
+            CREATE INDEX IF NOT EXISTS indexed_envelopes_target ON indexed_envelopes (target);
+            CREATE INDEX IF NOT EXISTS indexed_envelopes_content ON indexed_envelopes (content_hash);
+            CREATE INDEX IF NOT EXISTS indexed_envelopes_path ON indexed_envelopes (path);
+            CREATE TABLE IF NOT EXISTS indexed_tags (
+                bundle_txid TEXT NOT NULL,
+                idx INTEGER NOT NULL,
+                position INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                lower_name TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (bundle_txid, idx, position)
+            );
+            CREATE INDEX IF NOT EXISTS indexed_tags_value ON indexed_tags (lower_name, value);",
+        )?;
+        Ok(SqliteSearchIndex {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn tags(conn: &rusqlite::Connection, bundle_txid: &str, index: i64) -> Result<Vec<Tag>, Error> {
+        let mut statement = conn.prepare(
+            "SELECT name, value FROM indexed_tags WHERE bundle_txid = ?1 AND idx = ?2 ORDER BY position",
+        )?;
+        let tags = statement
+            .query_map(rusqlite::params![bundle_txid, index], |row| {
+                Ok(Tag::new(row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(tags)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl SearchIndex for SqliteSearchIndex {
+    fn insert(&self, envelopes: &[IndexedEnvelope]) -> Result<(), Error> {
+        let mut conn = self.conn.lock().map_err(|_| Error::SearchIndexError)?;
+        let tx = conn.transaction()?;
+        for envelope in envelopes {
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO indexed_envelopes
+                 (bundle_txid, idx, block_number, envelope_id, signer, target, content_hash, size, path)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                rusqlite::params![
+                    envelope.bundle_txid,
+                    envelope.index as i64,
+                    envelope.block_number as i64,
+                    envelope.envelope_id,
+                    envelope.signer,
+                    envelope.target,
+                    envelope.content_hash,
+                    envelope.size as i64,
+                    envelope.path(),
+                ],
+            )?;
+            if inserted == 0 {
+                continue;
+            }
+            for (position, tag) in envelope.tags.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO indexed_tags (bundle_txid, idx, position, name, lower_name, value)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    rusqlite::params![
+                        envelope.bundle_txid,
+                        envelope.index as i64,
+                        position as i64,
+                        tag.name,
+                        tag.name.to_lowercase(),
+                        tag.value,
+                    ],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn remove_bundle(&self, bundle_txid: &str) -> Result<(), Error> {
+        let mut conn = self.conn.lock().map_err(|_| Error::SearchIndexError)?;
+        let tx = conn.transaction()?;
+        let bundle_txid = bundle_txid.to_lowercase();
+        tx.execute(
+            "DELETE FROM indexed_envelopes WHERE bundle_txid = ?1",
+            [&bundle_txid],
+        )?;
+        tx.execute(
+            "DELETE FROM indexed_tags WHERE bundle_txid = ?1",
+            [&bundle_txid],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn search(&self, query: &SearchQuery) -> Result<Vec<IndexedEnvelope>, Error> {
+        use rusqlite::types::Value;
+
+        let mut conditions = vec![];
+        let mut params: Vec<Value> = vec![];
+        // numbers the `?` of each clause after the parameters of the previous ones
+        let mut condition = |clause: &str, values: Vec<Value>| {
+            let mut parts = clause.split('?');
+            let mut numbered = parts.next().unwrap_or_default().to_string();
+            for (value, part) in values.into_iter().zip(parts) {
+                params.push(value);
+                numbered.push_str(&format!("?{}{part}", params.len()));
+            }
+            conditions.push(numbered);
+        };
+        for tag in &query.tags {
+            condition(
+                "EXISTS (SELECT 1 FROM indexed_tags t WHERE t.bundle_txid = e.bundle_txid
+                 AND t.idx = e.idx AND t.lower_name = ? AND t.value = ?)",
+                vec![tag.name.to_lowercase().into(), tag.value.clone().into()],
+            );
+        }
+        let columns = [
+            ("signer", &query.signer),
+            ("target", &query.target),
+            ("content_hash", &query.content_hash),
+        ];
+        for (column, value) in columns {
+            if let Some(value) = value {
+                condition(&format!("e.{column} = ?"), vec![value.clone().into()]);
+            }
+        }
+        if let Some(prefix) = &query.path_prefix {
+            condition(
+                &sql_path_has_prefix("e.path", "?"),
+                vec![prefix.clone().into(), prefix.clone().into()],
+            );
+        }
+        if let Some(from) = query.from_block {
+            condition("e.block_number >= ?", vec![(from as i64).into()]);
+        }
+        if let Some(to) = query.to_block {
+            condition("e.block_number <= ?", vec![(to as i64).into()]);
+        }
+        let filter = match conditions.is_empty() {
+            true => String::new(),
+            false => format!("WHERE {}", conditions.join(" AND ")),
+        };
+        let limit = query.limit.map_or(-1, |limit| limit as i64);
+
+        let conn = self.conn.lock().map_err(|_| Error::SearchIndexError)?;
+        let mut statement = conn.prepare(&format!(
+            "SELECT bundle_txid, idx, block_number, envelope_id, signer, target, content_hash, size
+             FROM indexed_envelopes e {filter} ORDER BY block_number, rowid LIMIT {limit}"
+        ))?;
+        let rows = statement
+            .query_map(rusqlite::params_from_iter(params), |row| {
+                Ok(IndexedEnvelope {
+                    bundle_txid: row.get(0)?,
+                    index: row.get::<_, i64>(1)? as usize,
+                    block_number: row.get::<_, i64>(2)? as u64,
+                    envelope_id: row.get(3)?,
+                    signer: row.get(4)?,
+                    target: row.get(5)?,
+                    content_hash: row.get(6)?,
+                    size: row.get::<_, i64>(7)? as usize,
+                    tags: vec![],
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .map(|mut envelope| {
+                envelope.tags = Self::tags(&conn, &envelope.bundle_txid, envelope.index as i64)?;
+                Ok(envelope)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,382 @@
+use crate::utils::core::bundle_header::BundleHeader;
+use crate::utils::core::bundle_inspection::items_root;
+use crate::utils::core::bundle_item::BundleItem;
+use crate::utils::core::retention::unix_now;
+use crate::utils::core::tag::Tag;
+use crate::utils::errors::Error;
+use alloy::primitives::B256;
+use async_trait::async_trait;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const SIDECAR_VERSION: u8 = 1;
+pub const DEFAULT_SIDECAR_TIMEOUT: Duration = Duration::from_secs(10);
+
+// what indexers need to know about a posted bundle, without decoding its calldata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleSidecar {
+    pub version: u8,
+    pub bundle_id: String,
+    pub bundle_txid: String,
+    pub chain_id: u64,
+    pub envelope_ids: Vec<String>,
+    // per envelope, in bundle order
+    pub tags: Vec<Vec<Tag>>,
+    // see `items_root`, `None` for legacy calldata
+    pub merkle_root: Option<String>,
+    pub calldata_size: usize,
+    // unix seconds of the submission
+    pub timestamp: u64,
+}
+
+impl BundleSidecar {
+    // tags and merkle root are read from the calldata items, nothing is decompressed
+    pub fn new(
+        bundle_txid: String,
+        chain_id: u64,
+        bundle_id: B256,
+        envelope_ids: Vec<String>,
+        calldata: &[u8],
+    ) -> Result<Self, Error> {
+        let (version, header, body) = BundleHeader::split_versioned_calldata(calldata)?;
+        let (tags, merkle_root) = match header {
+            Some(_) => {
+                let items = BundleItem::decode_items(version, body)?;
+                let root = items_root(&items).to_string();
+                (
+                    items.into_iter().map(|item| item.tags).collect(),
+                    Some(root),
+                )
+            }
+            None => (vec![], None),
+        };
+        Ok(BundleSidecar {
+            version: SIDECAR_VERSION,
+            bundle_id: bundle_id.to_string(),
+            bundle_txid,
+            chain_id,
+            envelope_ids,
+            tags,
+            merkle_root,
+            calldata_size: calldata.len(),
+            timestamp: unix_now(),
+        })
+    }
+}
+
+// receives the sidecar of every bundle posted with the config, see `BundleConfig::sidecar_sink`
+#[async_trait]
+pub trait SidecarSink: Debug + Send + Sync {
+    async fn publish(&self, sidecar: &BundleSidecar) -> Result<(), Error>;
+}
+
+// to every sink at once, one result per sink in order
+pub async fn publish_sidecars(
+    sinks: &[Arc<dyn SidecarSink>],
+    sidecar: &BundleSidecar,
+) -> Vec<Result<(), Error>> {
+    join_all(sinks.iter().map(|sink| sink.publish(sidecar))).await
+}
+
+// posts the sidecar as JSON
+#[derive(Debug, Clone)]
+pub struct HttpSidecarSink {
+    pub url: String,
+    // e.g. an authorization header of the indexer
+    pub headers: Vec<(String, String)>,
+    pub timeout: Duration,
+    client: reqwest::Client,
+}
+
+impl HttpSidecarSink {
+    pub fn new(url: &str) -> Self {
+        HttpSidecarSink {
+            url: url.to_string(),
+            headers: vec![],
+            timeout: DEFAULT_SIDECAR_TIMEOUT,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[async_trait]
+impl SidecarSink for HttpSidecarSink {
+    async fn publish(&self, sidecar: &BundleSidecar) -> Result<(), Error> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .timeout(self.timeout)
+            .json(sidecar);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(Error::SidecarRejected(
+                self.url.clone(),
+                response.status().as_u16(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// `<dir>/<bundle id>.json`, written through a temporary file so readers never see a
+// partial sidecar
+#[derive(Debug, Clone)]
+pub struct FileSidecarSink {
+    pub dir: PathBuf,
+}
+
+impl FileSidecarSink {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FileSidecarSink { dir: dir.into() }
+    }
+
+    pub fn path(&self, bundle_id: &str) -> PathBuf {
+        self.dir.join(format!("{bundle_id}.json"))
+    }
+}
+
+#[async_trait]
+impl SidecarSink for FileSidecarSink {
+    async fn publish(&self, sidecar: &BundleSidecar) -> Result<(), Error> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(&sidecar.bundle_id);
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, serde_json::to_vec(sidecar)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+// `PUT <endpoint>/<bucket>/<prefix><bundle id>.json` signed with AWS signature v4, for S3
+// and S3 compatible stores (path-style addressing)
+#[cfg(feature = "s3")]
+#[derive(Debug, Clone)]
+pub struct S3SidecarSink {
+    // e.g. https://s3.eu-west-1.amazonaws.com
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub prefix: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub timeout: Duration,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "s3")]
+impl S3SidecarSink {
+    pub fn new(
+        endpoint: &str,
+        bucket: &str,
+        region: &str,
+        access_key_id: &str,
+        secret_access_key: &str,
+    ) -> Self {
+        S3SidecarSink {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            bucket: bucket.to_string(),
+            region: region.to_string(),
+            prefix: String::new(),
+            access_key_id: access_key_id.to_string(),
+            secret_access_key: secret_access_key.to_string(),
+            timeout: DEFAULT_SIDECAR_TIMEOUT,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    // e.g. `sidecars/`
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn object_key(&self, bundle_id: &str) -> String {
+        format!("{}{bundle_id}.json", self.prefix)
+    }
+}
+
+#[cfg(feature = "s3")]
+#[async_trait]
+impl SidecarSink for S3SidecarSink {
+    async fn publish(&self, sidecar: &BundleSidecar) -> Result<(), Error> {
+        let body = serde_json::to_vec(sidecar)?;
+        let path = format!(
+            "/{}/{}",
+            s3::uri_encode(&self.bucket, true),
+            s3::uri_encode(&self.object_key(&sidecar.bundle_id), false)
+        );
+        let url = reqwest::Url::parse(&format!("{}{path}", self.endpoint))
+            .map_err(|_| Error::InvalidSidecarSink(self.endpoint.clone()))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(Error::InvalidSidecarSink(self.endpoint.clone())),
+        };
+        let signed = s3::sign_put(
+            &host,
+            &path,
+            &body,
+            &self.region,
+            &self.access_key_id,
+            &self.secret_access_key,
+            unix_now(),
+        );
+        let response = self
+            .client
+            .put(url)
+            .timeout(self.timeout)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("x-amz-date", signed.amz_date)
+            .header("x-amz-content-sha256", signed.payload_hash)
+            .header(reqwest::header::AUTHORIZATION, signed.authorization)
+            .body(body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(Error::SidecarRejected(
+                self.endpoint.clone(),
+                response.status().as_u16(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "s3")]
+pub mod s3 {
+    use hmac::{Hmac, Mac};
+    use sha2::{Digest, Sha256};
+
+    pub struct SignedHeaders {
+        pub amz_date: String,
+        pub payload_hash: String,
+        pub authorization: String,
+    }
+
+    // RFC 3986 unreserved characters are kept, `/` too in object keys
+    pub fn uri_encode(input: &str, encode_slash: bool) -> String {
+        let mut encoded = String::with_capacity(input.len());
+        for byte in input.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                    encoded.push(byte as char)
+                }
+                b'/' if !encode_slash => encoded.push('/'),
+                _ => encoded.push_str(&format!("%{byte:02X}")),
+            }
+        }
+        encoded
+    }
+
+    // `YYYYMMDDTHHMMSSZ` of unix seconds
+    pub fn amz_date(timestamp: u64) -> String {
+        let (days, secs) = (timestamp / 86_400, timestamp % 86_400);
+        // days to civil date, proleptic gregorian calendar
+        let z = days as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        format!(
+            "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+            secs / 3_600,
+            secs % 3_600 / 60,
+            secs % 60
+        )
+    }
+
+    fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac takes any key size");
+        mac.update(data.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    // region and credentials the requests are signed for
+    pub struct SigningKey<'a> {
+        pub region: &'a str,
+        pub access_key_id: &'a str,
+        pub secret_access_key: &'a str,
+    }
+
+    // headers of an unsigned-query `PUT` of `body` at `path` (already uri encoded)
+    pub fn sign_put(
+        host: &str,
+        path: &str,
+        body: &[u8],
+        region: &str,
+        access_key_id: &str,
+        secret_access_key: &str,
+        timestamp: u64,
+    ) -> SignedHeaders {
+        let key = SigningKey {
+            region,
+            access_key_id,
+            secret_access_key,
+        };
+        sign_request("PUT", host, path, "", body, &key, timestamp)
+    }
+
+    // `query` being the canonical query string (sorted and uri encoded `name=value` pairs)
+    pub fn sign_request(
+        method: &str,
+        host: &str,
+        path: &str,
+        query: &str,
+        body: &[u8],
+        key: &SigningKey,
+        timestamp: u64,
+    ) -> SignedHeaders {
+        let amz_date = amz_date(timestamp);
+        let date = &amz_date[..8];
+        let payload_hash = hex::encode(Sha256::digest(body));
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{method}\n{path}\n{query}\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n{signed_headers}\n{payload_hash}"
+        );
+        let scope = format!("{date}/{}/s3/aws4_request", key.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signing_key = hmac(format!("AWS4{}", key.secret_access_key).as_bytes(), date);
+        let signing_key = hmac(&signing_key, key.region);
+        let signing_key = hmac(&signing_key, "s3");
+        let signing_key = hmac(&signing_key, "aws4_request");
+        let signature = hex::encode(hmac(&signing_key, &string_to_sign));
+        SignedHeaders {
+            authorization: format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                key.access_key_id
+            ),
+            amz_date,
+            payload_hash,
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use crate::utils::errors::Error;
+use alloy::signers::local::coins_bip39::English;
+use alloy::signers::local::MnemonicBuilder;
+
+// first account of the standard Ethereum path
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
+
+// standard Ethereum path of the `account`-th derived key, e.g. one account per environment
+pub fn derivation_path(account: u32) -> String {
+    format!("m/44'/60'/0'/0/{account}")
+}
+
+// hex private key (accepted wherever the library takes one) derived from a BIP-39 English
+// phrase at `derivation_path`
+pub fn from_mnemonic(phrase: &str, derivation_path: &str) -> Result<String, Error> {
+    let signer = MnemonicBuilder::<English>::default()
+        .phrase(phrase)
+        .derivation_path(derivation_path)?
+        .build()?;
+    Ok(alloy::hex::encode(signer.to_bytes()))
+}
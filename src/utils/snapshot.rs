@@ -0,0 +1,147 @@
+use crate::utils::checkpoint::CheckpointStore;
+use crate::utils::core::bundle_receipt::BundleReceipt;
+use crate::utils::core::retention::unix_now;
+use crate::utils::errors::Error;
+use crate::utils::inclusion::Inclusion;
+use crate::utils::receipt_store::ReceiptStore;
+use crate::utils::submission_queue::{QueuedEnvelope, SubmissionQueue};
+use crate::utils::webhook::LifecycleEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// a submitted bundle still watched for its confirmation or for reorgs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackedSnapshot {
+    pub txid: String,
+    pub envelope_count: usize,
+    pub inclusion: Option<Inclusion>,
+    // the reorg watch of confirmed bundles restarts on restore
+    pub confirmed: bool,
+}
+
+// state of a daemon tenant, next to the default signer state of the snapshot
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TenantSnapshot {
+    #[serde(default)]
+    pub queue: Vec<QueuedEnvelope>,
+    #[serde(default)]
+    pub receipts: Vec<BundleReceipt>,
+    #[serde(default)]
+    pub tracked: Vec<TrackedSnapshot>,
+}
+
+// state of a bundler service, to move it to another host without losing in-flight work.
+// Newer versions only add `#[serde(default)]` fields, so older snapshots keep loading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub version: u32,
+    // unix seconds
+    pub created_at: u64,
+    #[serde(default)]
+    pub queue: Vec<QueuedEnvelope>,
+    #[serde(default)]
+    pub receipts: Vec<BundleReceipt>,
+    // last block fully processed by a scan
+    #[serde(default)]
+    pub checkpoint: Option<u64>,
+    // next nonce of each payer address when the snapshot was taken, lowercase hex
+    #[serde(default)]
+    pub nonces: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub tracked: Vec<TrackedSnapshot>,
+    // lifecycle events not delivered to their webhooks yet
+    #[serde(default)]
+    pub events: Vec<LifecycleEvent>,
+    // by tenant id
+    #[serde(default)]
+    pub tenants: BTreeMap<String, TenantSnapshot>,
+}
+
+impl Default for StateSnapshot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateSnapshot {
+    pub fn new() -> Self {
+        StateSnapshot {
+            version: SNAPSHOT_VERSION,
+            created_at: unix_now(),
+            queue: vec![],
+            receipts: vec![],
+            checkpoint: None,
+            nonces: BTreeMap::new(),
+            tracked: vec![],
+            events: vec![],
+            tenants: BTreeMap::new(),
+        }
+    }
+
+    pub fn queue(mut self, queue: &SubmissionQueue) -> Self {
+        self.queue = queue.snapshot();
+        self
+    }
+
+    pub fn receipts(mut self, store: &dyn ReceiptStore) -> Result<Self, Error> {
+        self.receipts = store.export()?;
+        Ok(self)
+    }
+
+    pub fn checkpoint(mut self, store: &dyn CheckpointStore) -> Result<Self, Error> {
+        self.checkpoint = store.load()?;
+        Ok(self)
+    }
+
+    pub fn nonce(mut self, address: &str, next_nonce: u64) -> Self {
+        self.nonces.insert(address.to_lowercase(), next_nonce);
+        self
+    }
+
+    pub fn next_nonce(&self, address: &str) -> Option<u64> {
+        self.nonces.get(&address.to_lowercase()).copied()
+    }
+
+    // JSON, replaced atomically through a temporary file next to it
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let mut tmp = path.to_path_buf().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let snapshot: StateSnapshot = serde_json::from_slice(&fs::read(path)?)?;
+        if snapshot.version > SNAPSHOT_VERSION {
+            return Err(Error::UnsupportedSnapshot(snapshot.version));
+        }
+        Ok(snapshot)
+    }
+
+    pub fn restore_queue(&self, queue: &mut SubmissionQueue) -> Result<(), Error> {
+        queue.restore(self.queue.clone())
+    }
+
+    pub fn restore_receipts(&self, store: &dyn ReceiptStore) -> Result<(), Error> {
+        self.receipts
+            .iter()
+            .try_for_each(|receipt| store.save(receipt))
+    }
+
+    // a store already past the snapshot checkpoint is left as is
+    pub fn restore_checkpoint(&self, store: &dyn CheckpointStore) -> Result<(), Error> {
+        let Some(checkpoint) = self.checkpoint else {
+            return Ok(());
+        };
+        if store.load()?.is_none_or(|current| current < checkpoint) {
+            store.save(checkpoint)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use crate::utils::errors::Error;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpendLimit {
+    // wei
+    pub amount: u128,
+    pub window: Duration,
+}
+
+#[derive(Debug, Default)]
+struct Ledger {
+    // fees reserved by the bundle txs sent within the window, oldest first
+    entries: VecDeque<(u64, Instant, u128)>,
+    next_id: u64,
+}
+
+impl Ledger {
+    fn prune(&mut self, window: Duration, now: Instant) {
+        while let Some((_, at, _)) = self.entries.front() {
+            if now.duration_since(*at) < window {
+                break;
+            }
+            self.entries.pop_front();
+        }
+    }
+
+    fn total(&self) -> u128 {
+        self.entries
+            .iter()
+            .fold(0u128, |total, (_, _, fee)| total.saturating_add(*fee))
+    }
+}
+
+// fee of a bundle tx held against the window until it is released
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpendReservation {
+    id: u64,
+    pub fee: u128,
+}
+
+// checked before every bundle tx broadcast, fees being the worst case of the tx (gas
+// limit * max fee per gas). Clones share their spend ledger, so one guard set on several
+// configs caps their combined spend.
+#[derive(Debug, Clone, Default)]
+pub struct SpendGuard {
+    // wei the payer must keep once the bundle tx fee is paid
+    pub min_balance: Option<u128>,
+    // wei, per bundle tx
+    pub max_bundle_fee: Option<u128>,
+    // total over a sliding window
+    pub spend_limit: Option<SpendLimit>,
+    ledger: Arc<Mutex<Ledger>>,
+}
+
+impl SpendGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn min_balance(mut self, wei: u128) -> Self {
+        self.min_balance = Some(wei);
+        self
+    }
+
+    pub fn max_bundle_fee(mut self, wei: u128) -> Self {
+        self.max_bundle_fee = Some(wei);
+        self
+    }
+
+    pub fn max_spend(mut self, wei: u128, window: Duration) -> Self {
+        self.spend_limit = Some(SpendLimit {
+            amount: wei,
+            window,
+        });
+        self
+    }
+
+    // fees reserved within the spend limit window, 0 without a limit
+    pub fn spent(&self) -> u128 {
+        let Some(limit) = self.spend_limit else {
+            return 0;
+        };
+        let mut ledger = self.ledger.lock().unwrap_or_else(|e| e.into_inner());
+        ledger.prune(limit.window, Instant::now());
+        ledger.total()
+    }
+
+    // `balance` is the payer balance, needed with a minimum balance only
+    pub fn reserve(&self, fee: u128, balance: Option<u128>) -> Result<SpendReservation, Error> {
+        if let Some(max) = self.max_bundle_fee {
+            if fee > max {
+                return Err(Error::BundleFeeExceeded(fee, max));
+            }
+        }
+        if let (Some(min), Some(balance)) = (self.min_balance, balance) {
+            if balance.saturating_sub(fee) < min {
+                return Err(Error::InsufficientBalance(balance, fee.saturating_add(min)));
+            }
+        }
+        let mut ledger = self.ledger.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if let Some(limit) = self.spend_limit {
+            ledger.prune(limit.window, now);
+            let spent = ledger.total();
+            if spent.saturating_add(fee) > limit.amount {
+                return Err(Error::SpendLimitExceeded(spent, limit.amount));
+            }
+        }
+        let id = ledger.next_id;
+        ledger.next_id += 1;
+        if self.spend_limit.is_some() {
+            ledger.entries.push_back((id, now, fee));
+        }
+        Ok(SpendReservation { id, fee })
+    }
+
+    // gives the fee of a tx that was not broadcasted back to the window
+    pub fn release(&self, reservation: SpendReservation) {
+        let mut ledger = self.ledger.lock().unwrap_or_else(|e| e.into_inner());
+        ledger.entries.retain(|(id, _, _)| *id != reservation.id);
+    }
+}
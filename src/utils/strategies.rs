@@ -0,0 +1,124 @@
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::bundle_header::BundleHeader;
+use crate::utils::core::compression::{Compression, CompressionParams};
+use crate::utils::core::envelope::Envelope;
+use crate::utils::core::extension::Extension;
+use crate::utils::core::stream::StreamPointer;
+use crate::utils::core::tag::Tag;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::evm::sign_envelope;
+use alloy::primitives::Address;
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+
+// up to `max_len` bytes: random ones, which brotli can't shrink and are stored raw past
+// the incompressible ratio, or a short pattern repeated, which compresses well
+pub fn payload(max_len: usize) -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        vec(any::<u8>(), 0..=max_len),
+        (vec(any::<u8>(), 1..8), 0..=max_len).prop_map(|(pattern, len)| pattern
+            .iter()
+            .copied()
+            .cycle()
+            .take(len)
+            .collect()),
+    ]
+}
+
+pub fn tag() -> impl Strategy<Value = Tag> {
+    ("[a-zA-Z][a-zA-Z0-9-]{0,15}", "[ -~]{0,32}").prop_map(|(name, value)| Tag::new(name, value))
+}
+
+pub fn extension() -> impl Strategy<Value = Extension> {
+    (any::<u16>(), vec(any::<u8>(), 0..32)).prop_map(|(id, data)| Extension::new(id, data))
+}
+
+// what an envelope asks for, the encoder switches brotli envelopes to the dictionary of
+// the bundle config when there is one
+pub fn compression() -> impl Strategy<Value = Compression> {
+    prop_oneof![Just(Compression::None), Just(Compression::Brotli)]
+}
+
+// within the `CompressionParams::validate` bounds
+pub fn compression_params() -> impl Strategy<Value = CompressionParams> {
+    (0..=11u32, 10..=24u32).prop_map(|(quality, window)| CompressionParams { quality, window })
+}
+
+pub fn envelope(max_len: usize) -> impl Strategy<Value = Envelope> {
+    (
+        payload(max_len),
+        option::of(any::<[u8; 20]>()),
+        compression(),
+        vec(tag(), 0..4),
+        vec(extension(), 0..3),
+    )
+        .prop_map(|(data, target, compression, tags, extensions)| Envelope {
+            data: Some(data),
+            target: target.map(|target| Address::from(target).to_string()),
+            compression,
+            tags,
+            extensions,
+        })
+}
+
+// an `envelope` signed with `private_key`, panics on a key that can't be parsed. The
+// signing is local, no RPC call is made.
+pub fn signed_envelope(
+    private_key: &str,
+    max_len: usize,
+) -> impl Strategy<Value = TxEnvelopeWrapper> {
+    let private_key = private_key.to_string();
+    envelope(max_len).prop_map(move |input| {
+        futures::executor::block_on(sign_envelope(input, &private_key))
+            .expect("envelope signing failed")
+    })
+}
+
+// 1 to `max_envelopes` signed envelopes
+pub fn envelope_set(
+    private_key: &str,
+    max_envelopes: usize,
+    max_len: usize,
+) -> impl Strategy<Value = Vec<TxEnvelopeWrapper>> {
+    vec(
+        signed_envelope(private_key, max_len),
+        1..=max_envelopes.max(1),
+    )
+}
+
+pub fn stream_pointer() -> impl Strategy<Value = StreamPointer> {
+    (
+        "[a-z0-9-]{1,16}",
+        any::<u64>(),
+        option::of("0x[0-9a-f]{64}"),
+    )
+        .prop_map(|(stream_id, sequence, previous_txid)| StreamPointer {
+            stream_id,
+            sequence,
+            previous_txid,
+        })
+}
+
+// without dictionary id, the encoder writes the one of the bundle config
+pub fn bundle_header() -> impl Strategy<Value = BundleHeader> {
+    (option::of(stream_pointer()), option::of("[0-9a-f]{32}")).prop_map(
+        |(stream, idempotency_key)| BundleHeader {
+            stream,
+            dictionary_id: None,
+            idempotency_key,
+        },
+    )
+}
+
+pub fn bundle(
+    private_key: &str,
+    max_envelopes: usize,
+    max_len: usize,
+) -> impl Strategy<Value = BundleData> {
+    (
+        envelope_set(private_key, max_envelopes, max_len),
+        bundle_header(),
+    )
+        .prop_map(|(envelopes, header)| BundleData { envelopes, header })
+}
@@ -0,0 +1,216 @@
+use crate::utils::core::bundle_config::BundleConfig;
+use crate::utils::core::envelope::Envelope;
+use crate::utils::errors::Error;
+use crate::utils::evm::submit_bundles;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_LOW_PRIORITY_DELAY: Duration = Duration::from_secs(60);
+// raw envelope data packed in one bundle before it is considered full
+pub const DEFAULT_PACKING_TARGET: usize = 8_000_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    // bundled at the next flush, even alone
+    High,
+    // waits for a full bundle, or `low_priority_delay`
+    #[default]
+    Low,
+}
+
+#[derive(Debug)]
+struct Queued {
+    envelope: Envelope,
+    enqueued_at: Instant,
+}
+
+// a queued envelope as exported in a `StateSnapshot`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedEnvelope {
+    pub envelope: Envelope,
+    pub priority: Priority,
+    // time already spent in the queue, kept by `restore`
+    pub waited_ms: u64,
+}
+
+// envelope queue of a long-lived submitter
+#[derive(Debug)]
+pub struct SubmissionQueue {
+    config: BundleConfig,
+    high: VecDeque<Queued>,
+    low: VecDeque<Queued>,
+    low_priority_delay: Duration,
+    packing_target: usize,
+}
+
+impl SubmissionQueue {
+    pub fn new(config: BundleConfig) -> Self {
+        SubmissionQueue {
+            config,
+            high: VecDeque::new(),
+            low: VecDeque::new(),
+            low_priority_delay: DEFAULT_LOW_PRIORITY_DELAY,
+            packing_target: DEFAULT_PACKING_TARGET,
+        }
+    }
+
+    pub fn low_priority_delay(mut self, delay: Duration) -> Self {
+        self.low_priority_delay = delay;
+        self
+    }
+
+    pub fn packing_target(mut self, bytes: usize) -> Self {
+        self.packing_target = bytes;
+        self
+    }
+
+    // the envelope is validated now rather than when its bundle is built
+    pub fn enqueue(&mut self, envelope: Envelope, priority: Priority) -> Result<(), Error> {
+        let index = self.len();
+        envelope
+            .validate_with(&self.config)
+            .map_err(|e| Error::InvalidEnvelope(index, e))?;
+        let queued = Queued {
+            envelope,
+            enqueued_at: Instant::now(),
+        };
+        match priority {
+            Priority::High => self.high.push_back(queued),
+            Priority::Low => self.low.push_back(queued),
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.high.len() + self.low.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // queued envelopes in queue order, high priority ones first
+    pub fn snapshot(&self) -> Vec<QueuedEnvelope> {
+        let entry = |priority| {
+            move |queued: &Queued| QueuedEnvelope {
+                envelope: queued.envelope.clone(),
+                priority,
+                waited_ms: queued.enqueued_at.elapsed().as_millis() as u64,
+            }
+        };
+        let high = self.high.iter().map(entry(Priority::High));
+        high.chain(self.low.iter().map(entry(Priority::Low)))
+            .collect()
+    }
+
+    // queues the envelopes of a snapshot after the queued ones, all of them being
+    // validated before any is queued
+    pub fn restore(&mut self, entries: Vec<QueuedEnvelope>) -> Result<(), Error> {
+        for (i, entry) in entries.iter().enumerate() {
+            entry
+                .envelope
+                .validate_with(&self.config)
+                .map_err(|e| Error::InvalidEnvelope(self.len() + i, e))?;
+        }
+        let now = Instant::now();
+        for entry in entries {
+            let waited = Duration::from_millis(entry.waited_ms);
+            let queued = Queued {
+                envelope: entry.envelope,
+                enqueued_at: now.checked_sub(waited).unwrap_or(now),
+            };
+            match entry.priority {
+                Priority::High => self.high.push_back(queued),
+                Priority::Low => self.low.push_back(queued),
+            }
+        }
+        Ok(())
+    }
+
+    // takes the bundles due at `now`: every high priority envelope, then full low
+    // priority bundles, then the rest of them once the oldest waited long enough
+    pub fn ready_batches(&mut self, now: Instant) -> Vec<Vec<Envelope>> {
+        let mut batches = vec![];
+        while !self.high.is_empty() {
+            batches.push(self.take_batch(Priority::High));
+        }
+        while self.low_bundle_is_full() {
+            batches.push(self.take_batch(Priority::Low));
+        }
+        let waited = |q: &Queued| now.saturating_duration_since(q.enqueued_at);
+        if self
+            .low
+            .front()
+            .is_some_and(|q| waited(q) >= self.low_priority_delay)
+        {
+            while !self.low.is_empty() {
+                batches.push(self.take_batch(Priority::Low));
+            }
+        }
+        batches
+    }
+
+    // takes every queued envelope, due or not, high priority ones first
+    pub fn drain_batches(&mut self) -> Vec<Vec<Envelope>> {
+        let mut batches = vec![];
+        while !self.high.is_empty() {
+            batches.push(self.take_batch(Priority::High));
+        }
+        while !self.low.is_empty() {
+            batches.push(self.take_batch(Priority::Low));
+        }
+        batches
+    }
+
+    // submits the bundles due now, see `submit_bundles`
+    pub async fn submit_ready(
+        &mut self,
+        private_key: String,
+    ) -> Result<Vec<Result<String, Error>>, Error> {
+        let batches = self.ready_batches(Instant::now());
+        if batches.is_empty() {
+            return Ok(vec![]);
+        }
+        submit_bundles(batches, private_key, &self.config).await
+    }
+
+    fn low_bundle_is_full(&self) -> bool {
+        let mut size = 0;
+        for (count, queued) in self.low.iter().enumerate() {
+            if count + 1 >= self.config.max_envelopes_per_bundle.max(1) {
+                return true;
+            }
+            size += data_len(&queued.envelope);
+            if size >= self.packing_target {
+                return true;
+            }
+        }
+        false
+    }
+
+    // fills a bundle up to the envelope count and packing target, at least one envelope
+    fn take_batch(&mut self, priority: Priority) -> Vec<Envelope> {
+        let max_count = self.config.max_envelopes_per_bundle.max(1);
+        let lane = match priority {
+            Priority::High => &mut self.high,
+            Priority::Low => &mut self.low,
+        };
+        let mut batch = vec![];
+        let mut size = 0;
+        while let Some(queued) = lane.front() {
+            let len = data_len(&queued.envelope);
+            if !batch.is_empty() && (batch.len() >= max_count || size + len > self.packing_target) {
+                break;
+            }
+            size += len;
+            batch.push(lane.pop_front().unwrap().envelope);
+        }
+        batch
+    }
+}
+
+fn data_len(envelope: &Envelope) -> usize {
+    envelope.data.as_ref().map_or(0, Vec::len)
+}
@@ -0,0 +1,61 @@
+use crate::utils::core::envelope::Envelope;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// reproducible test data, the same seed always yields the same calldata and envelopes
+#[derive(Debug, Clone)]
+pub struct TestDataGenerator {
+    rng: StdRng,
+}
+
+impl TestDataGenerator {
+    pub fn new(seed: u64) -> Self {
+        TestDataGenerator {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn from_entropy() -> Self {
+        TestDataGenerator {
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    // "0x" prefixed random hex string of `length` characters, at least 10
+    pub fn calldata(&mut self, length: usize) -> String {
+        // Ensure minimum length of 10 (0x + 4 bytes function selector)
+        let actual_length = length.max(10);
+
+        let mut calldata = String::with_capacity(actual_length);
+        calldata.push_str("0x");
+        for _ in 2..actual_length {
+            let random_hex: u8 = self.rng.gen_range(0..16);
+            calldata.push_str(&format!("{:x}", random_hex));
+        }
+        calldata
+    }
+
+    pub fn bytes(&mut self, length: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; length];
+        self.rng.fill(&mut bytes[..]);
+        bytes
+    }
+
+    // envelope without target carrying `data_len` bytes of random hex calldata
+    pub fn envelope(&mut self, data_len: usize) -> Envelope {
+        Envelope::new().data(Some(self.calldata(data_len).into_bytes()))
+    }
+
+    pub fn envelopes(&mut self, count: usize, data_len: usize) -> Vec<Envelope> {
+        (0..count).map(|_| self.envelope(data_len)).collect()
+    }
+
+    // one envelope per entry of `sizes`
+    pub fn envelopes_with_sizes(&mut self, sizes: &[usize]) -> Vec<Envelope> {
+        sizes.iter().map(|&size| self.envelope(size)).collect()
+    }
+}
+
+pub fn generate_random_calldata(length: usize) -> String {
+    TestDataGenerator::from_entropy().calldata(length)
+}
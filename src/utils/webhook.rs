@@ -0,0 +1,230 @@
+use crate::utils::core::retention::unix_now;
+use crate::utils::errors::Error;
+use futures::future::join_all;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::Duration;
+
+pub const SIGNATURE_HEADER: &str = "x-bundler-signature";
+pub const EVENT_HEADER: &str = "x-bundler-event";
+pub const DEFAULT_WEBHOOK_RETRIES: u32 = 5;
+pub const DEFAULT_WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(1);
+pub const DEFAULT_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleEventKind {
+    // the bundle tx was broadcasted
+    Submitted,
+    // the bundle tx reached the confirmation level
+    Confirmed,
+    // the block of a confirmed bundle tx left the canonical chain, it is tracked again
+    Reorged,
+    // the bundle was never broadcasted, or its tx was dropped
+    Failed,
+}
+
+impl LifecycleEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleEventKind::Submitted => "submitted",
+            LifecycleEventKind::Confirmed => "confirmed",
+            LifecycleEventKind::Reorged => "reorged",
+            LifecycleEventKind::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    pub event: LifecycleEventKind,
+    // `None` for bundles failing before their broadcast
+    pub txid: Option<String>,
+    pub envelope_count: usize,
+    // inclusion block of confirmed bundles, previous one of reorged bundles
+    pub block_number: Option<u64>,
+    pub error: Option<String>,
+    // unix seconds
+    pub timestamp: u64,
+    // daemon tenant of the bundle, `None` for the default signer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+impl LifecycleEvent {
+    pub fn new(event: LifecycleEventKind, txid: Option<String>, envelope_count: usize) -> Self {
+        LifecycleEvent {
+            event,
+            txid,
+            envelope_count,
+            block_number: None,
+            error: None,
+            timestamp: unix_now(),
+            tenant: None,
+        }
+    }
+
+    pub fn tenant(mut self, tenant: Option<String>) -> Self {
+        self.tenant = tenant;
+        self
+    }
+
+    pub fn block_number(mut self, block_number: u64) -> Self {
+        self.block_number = Some(block_number);
+        self
+    }
+
+    pub fn error(mut self, error: String) -> Self {
+        self.error = Some(error);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub url: String,
+    // signs the request bodies, see `webhook_signature`
+    pub secret: Option<Vec<u8>>,
+    // every event when empty
+    pub events: Vec<LifecycleEventKind>,
+    // attempts after the first one, on transport errors, 429 and 5xx responses
+    pub retries: u32,
+    // doubled after each attempt
+    pub retry_delay: Duration,
+    pub timeout: Duration,
+}
+
+impl Webhook {
+    pub fn new(url: &str) -> Self {
+        Webhook {
+            url: url.to_string(),
+            secret: None,
+            events: vec![],
+            retries: DEFAULT_WEBHOOK_RETRIES,
+            retry_delay: DEFAULT_WEBHOOK_RETRY_DELAY,
+            timeout: DEFAULT_WEBHOOK_TIMEOUT,
+        }
+    }
+
+    pub fn secret(mut self, secret: Vec<u8>) -> Self {
+        self.secret = Some(secret);
+        self
+    }
+
+    pub fn event(mut self, event: LifecycleEventKind) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn accepts(&self, event: LifecycleEventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+// the `x-bundler-signature` header: `sha256=` and the hex encoded HMAC-SHA256 of the
+// body, keyed with the secret
+pub fn webhook_signature(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("hmac takes any key size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+// for receivers, `signature` is the `x-bundler-signature` header
+pub fn verify_webhook_signature(secret: &[u8], body: &[u8], signature: &str) -> Result<(), Error> {
+    let expected = webhook_signature(secret, body);
+    if !constant_time_eq(signature.as_bytes(), expected.as_bytes()) {
+        return Err(Error::InvalidWebhookSignature);
+    }
+    Ok(())
+}
+
+// the length of the compared values is public, shared by the signature and key checks
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// posts the event as JSON until the endpoint accepts it or the retries run out
+pub async fn deliver(
+    client: &reqwest::Client,
+    webhook: &Webhook,
+    event: &LifecycleEvent,
+) -> Result<(), Error> {
+    let body = serde_json::to_vec(event)?;
+    let mut delay = webhook.retry_delay;
+    let mut attempt = 0;
+    loop {
+        let mut request = client
+            .post(&webhook.url)
+            .timeout(webhook.timeout)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event.event.as_str());
+        if let Some(secret) = &webhook.secret {
+            request = request.header(SIGNATURE_HEADER, webhook_signature(secret, &body));
+        }
+        let error = match request.body(body.clone()).send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                let error = Error::WebhookRejected(webhook.url.clone(), status.as_u16());
+                if !status.is_server_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    return Err(error);
+                }
+                error
+            }
+            Err(e) => e.into(),
+        };
+        if attempt >= webhook.retries {
+            return Err(error);
+        }
+        attempt += 1;
+        futures_timer::Delay::new(delay).await;
+        delay *= 2;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WebhookNotifier {
+    pub webhooks: Vec<Webhook>,
+    client: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(webhooks: Vec<Webhook>) -> Self {
+        WebhookNotifier {
+            webhooks,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.webhooks.is_empty()
+    }
+
+    // delivers to every webhook subscribed to the event at once, one result per webhook
+    // in `webhooks` order (`Ok` for the ones not subscribed)
+    pub async fn notify(&self, event: &LifecycleEvent) -> Vec<Result<(), Error>> {
+        let deliveries = self.webhooks.iter().map(|webhook| async move {
+            if !webhook.accepts(event.event) {
+                return Ok(());
+            }
+            deliver(&self.client, webhook, event).await
+        });
+        join_all(deliveries).await
+    }
+}